@@ -132,12 +132,12 @@ fn main() -> io::Result<()> {
         let mut stream = MmapStream::with_buffers(&dev, Type::VideoCapture, buffer_count).unwrap();
 
         loop {
-            let (buf, _) = stream.next().unwrap();
+            let (buf, meta) = stream.next().unwrap();
             let data = match &format.fourcc.repr {
                 b"RGB3" => buf.to_vec(),
                 b"MJPG" => {
-                    // Decode the JPEG frame to RGB
-                    let mut decoder = jpeg::Decoder::new(buf);
+                    // Decode the JPEG frame to RGB, the rest of the buffer is not part of it
+                    let mut decoder = jpeg::Decoder::new(meta.valid(buf));
                     decoder.decode().expect("failed to decode JPEG")
                 }
                 _ => panic!("invalid buffer pixelformat"),
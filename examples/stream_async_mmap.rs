@@ -1,4 +1,5 @@
 use std::io;
+use std::time::Duration;
 use tokio::time::Instant;
 use v4l::buffer::Type;
 use v4l::io::traits::AsyncCaptureStream;
@@ -22,7 +23,10 @@ fn main() -> io::Result<()> {
     println!("Active parameters:\n{}", params);
 
     // Setup a buffer stream and grab a frame, then print its data
-    let stream = MmapStream::with_buffers(&dev, Type::VideoCapture, buffer_count)?;
+    // The first frame is discarded as warmup, frames which may be corrupt are discarded too.
+    let stream = MmapStream::with_buffers(&dev, Type::VideoCapture, buffer_count)?
+        .skip_initial(1)
+        .skip_errors(true);
 
     let rt = tokio::runtime::Builder::new_current_thread()
         .enable_all()
@@ -32,16 +36,24 @@ fn main() -> io::Result<()> {
 }
 
 async fn run(mut stream: MmapStream<'_>, count: usize) -> io::Result<()> {
-    // warmup
-    stream.poll_next().await?;
-
     let start = Instant::now();
     let mut megabytes_ps: f64 = 0.0;
+    let mut last_sequence: Option<u32> = None;
     for i in 0..count {
         let t0 = Instant::now();
-        let (buf, _meta) = stream.poll_next().await?;
+        // fail instead of hanging forever if the device stalls
+        let (buf, meta) = stream.poll_next_timeout(Duration::from_secs(2)).await?;
         let duration_us = t0.elapsed().as_micros();
 
+        // gaps in the sequence numbers mean the driver dropped frames
+        if let Some(last) = last_sequence {
+            let dropped = meta.sequence.wrapping_sub(last).saturating_sub(1);
+            if dropped > 0 {
+                println!("Dropped {} frame(s) before #{}", dropped, meta.sequence);
+            }
+        }
+        last_sequence = Some(meta.sequence);
+
         let cur = buf.len() as f64 / 1_048_576.0 * 1_000_000.0 / duration_us as f64;
         if i == 0 {
             megabytes_ps = cur;
@@ -56,6 +68,7 @@ async fn run(mut stream: MmapStream<'_>, count: usize) -> io::Result<()> {
     println!();
     println!("FPS: {}", count as f64 / start.elapsed().as_secs_f64());
     println!("MB/s: {}", megabytes_ps);
+    println!("Corrupt frames: {}", stream.stats().error_frames);
 
     Ok(())
 }
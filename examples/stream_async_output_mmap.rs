@@ -0,0 +1,60 @@
+use std::io;
+use std::time::Duration;
+use tokio::time::Instant;
+use v4l::buffer::Type;
+use v4l::io::traits::AsyncOutputStream;
+use v4l::prelude::*;
+use v4l::video::Output;
+use v4l::{Format, FourCC};
+
+fn main() -> io::Result<()> {
+    // e.g. a v4l2loopback device
+    let path = "/dev/video1";
+    println!("Using device: {}\n", path);
+
+    // Output 100 frames by default
+    let count = 100;
+
+    // Allocate 4 buffers by default
+    let buffer_count = 4;
+
+    let dev = Device::with_path(path)?;
+    let format = Output::set_format(&dev, &Format::new(640, 480, FourCC::new(b"YUYV")))?;
+    if format.fourcc != FourCC::new(b"YUYV") {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "device does not support YUYV output",
+        ));
+    }
+    println!("Active format:\n{}", format);
+
+    let stream = MmapStream::with_buffers(&dev, Type::VideoOutput, buffer_count)?;
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    rt.block_on(run(stream, format, count))?;
+    Ok(())
+}
+
+async fn run(mut stream: MmapStream<'_>, format: Format, count: usize) -> io::Result<()> {
+    // pace the frames at 30 FPS without blocking the runtime
+    let mut interval = tokio::time::interval(Duration::from_millis(33));
+    let start = Instant::now();
+    for i in 0..count {
+        interval.tick().await;
+
+        // the buffer is queued to the driver on the next call
+        let (buf, meta) = stream.poll_write_next().await?;
+        // gray frame fading from black to white, chroma is neutral
+        let luma = (i * 255 / count) as u8;
+        for pair in buf[..format.size as usize].chunks_exact_mut(2) {
+            pair.copy_from_slice(&[luma, 128]);
+        }
+        meta.bytesused = format.size;
+    }
+
+    println!("FPS: {}", count as f64 / start.elapsed().as_secs_f64());
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use std::io;
+use v4l::buffer::Type;
+use v4l::io::dmabuf::Arena;
+use v4l::prelude::*;
+use v4l::video::Capture;
+
+fn main() -> io::Result<()> {
+    let path = "/dev/video0";
+    println!("Using device: {}\n", path);
+
+    // Allocate 4 buffers by default
+    let buffer_count = 4;
+
+    let dev = Device::with_path(path)?;
+    let format = dev.format()?;
+    println!("Active format:\n{}", format);
+
+    // Let the driver allocate the buffers and export them as dma-buf file descriptors.
+    // These can be handed to a GPU or encoder API which imports dma-bufs, no copy involved.
+    let mut arena = Arena::new(dev.handle(), Type::VideoCapture);
+    let count = arena.allocate(buffer_count)?;
+    println!("Exported {} buffers", count);
+
+    for index in 0..arena.len() {
+        let fds = arena.fds(index).unwrap();
+        let sizes = arena.sizes(index).unwrap();
+        for (plane, (fd, size)) in fds.iter().zip(sizes).enumerate() {
+            println!(
+                "Buffer {} plane {}: fd {} ({} bytes)",
+                index, plane, fd, size
+            );
+        }
+    }
+
+    // All file descriptors are closed and the buffers are released once the arena is dropped
+    Ok(())
+}
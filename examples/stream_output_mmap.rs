@@ -0,0 +1,66 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use v4l::io::mmap::OutputStream;
+use v4l::prelude::*;
+use v4l::video::Output;
+use v4l::{Format, FourCC};
+
+// 75% color bars as (Y, U, V)
+const BARS: [(u8, u8, u8); 8] = [
+    (180, 128, 128), // white
+    (162, 44, 142),  // yellow
+    (131, 156, 44),  // cyan
+    (112, 72, 58),   // green
+    (84, 184, 198),  // magenta
+    (65, 100, 212),  // red
+    (35, 212, 114),  // blue
+    (16, 128, 128),  // black
+];
+
+/// Writes color bars shifted to the left by `offset` pixels
+fn color_bars(buf: &mut [u8], width: usize, height: usize, offset: usize) {
+    let bar_width = (width / BARS.len()).max(1);
+    for (y, line) in buf.chunks_exact_mut(width * 2).take(height).enumerate() {
+        // YUYV: two pixels share one pair of chroma samples
+        for (x, pair) in line.chunks_exact_mut(4).enumerate() {
+            let bar = ((x * 2 + offset + y / 4) / bar_width) % BARS.len();
+            let (luma, u, v) = BARS[bar];
+            pair.copy_from_slice(&[luma, u, luma, v]);
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    // e.g. a v4l2loopback device
+    let path = "/dev/video1";
+    println!("Using device: {}\n", path);
+
+    // Output 100 frames by default
+    let count = 100;
+
+    let dev = Device::with_path(path)?;
+    let format = Output::set_format(&dev, &Format::new(640, 480, FourCC::new(b"YUYV")))?;
+    if format.fourcc != FourCC::new(b"YUYV") {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "device does not support YUYV output",
+        ));
+    }
+    println!("Active format:\n{}", format);
+
+    let mut stream = OutputStream::new(&dev)?;
+    stream.set_timeout(Duration::from_secs(2));
+
+    let start = Instant::now();
+    for i in 0..count {
+        let buf = stream.next_mut()?;
+        color_bars(buf, format.width as usize, format.height as usize, i * 4);
+        stream.set_bytesused(format.size as usize);
+        stream.queue()?;
+    }
+
+    println!("FPS: {}", count as f64 / start.elapsed().as_secs_f64());
+
+    Ok(())
+}
@@ -1,16 +1,111 @@
-use std::alloc::{alloc, Layout};
+use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::slice;
 
-/// get aligned memory block
+/// Memory block with a custom alignment
 ///
-/// Reference from: https://qiita.com/moriai/items/67761b3c0d83da3b6bb5
-pub fn aligned_alloc(size: usize, align: usize) -> Vec<u8> {
-    unsafe {
+/// The block is released with the same layout it was allocated with when the buffer is dropped.
+/// A `Vec<u8>` cannot be used for this since it would be deallocated with an alignment of one.
+pub struct AlignedBuffer {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+// The buffer owns its memory block exclusively, just like a Vec<u8>.
+unsafe impl Send for AlignedBuffer {}
+unsafe impl Sync for AlignedBuffer {}
+
+impl AlignedBuffer {
+    /// Returns a zero-initialized memory block
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Size in bytes
+    /// * `align` - Alignment in bytes, must be a power of two
+    pub fn new(size: usize, align: usize) -> Self {
         let layout = Layout::from_size_align(size, align).unwrap();
-        let raw_mem = alloc(layout);
-        Vec::from_raw_parts(raw_mem, size, size)
+        if size == 0 {
+            // zero sized allocations are not allowed, use a dangling (but aligned) pointer
+            let ptr = NonNull::new(align as *mut u8).unwrap();
+            return AlignedBuffer { ptr, layout };
+        }
+
+        let ptr = unsafe { alloc(layout) };
+        let ptr = match NonNull::new(ptr) {
+            Some(ptr) => ptr,
+            None => handle_alloc_error(layout),
+        };
+        // the driver may not fill the whole buffer, do not expose uninitialized memory
+        unsafe { ptr.as_ptr().write_bytes(0, size) };
+        AlignedBuffer { ptr, layout }
+    }
+
+    /// Returns a raw pointer to the memory block
+    pub fn as_ptr(&self) -> *const u8 {
+        self.ptr.as_ptr()
+    }
+
+    /// Returns a raw mutable pointer to the memory block
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    /// Returns the size in bytes
+    pub fn len(&self) -> usize {
+        self.layout.size()
+    }
+
+    /// Returns true if the size is zero
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the alignment in bytes
+    pub fn align(&self) -> usize {
+        self.layout.align()
+    }
+
+    /// Returns the memory block as slice
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len()) }
+    }
+
+    /// Returns the memory block as mutable slice
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len()) }
     }
 }
 
+impl Deref for AlignedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl DerefMut for AlignedBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        if self.layout.size() > 0 {
+            unsafe { dealloc(self.ptr.as_ptr(), self.layout) }
+        }
+    }
+}
+
+/// get aligned memory block
+///
+/// Reference from: https://qiita.com/moriai/items/67761b3c0d83da3b6bb5
+pub fn aligned_alloc(size: usize, align: usize) -> AlignedBuffer {
+    AlignedBuffer::new(size, align)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -34,7 +129,22 @@ mod tests {
                 let size = 1024;
                 let buf = aligned_alloc(size, align);
                 assert!(bound(&buf) >= align);
+                assert_eq!(size, buf.len());
+                assert_eq!(align, buf.align());
             }
         }
     }
+
+    #[test]
+    fn test_aligned_buffer_access() {
+        let mut buf = AlignedBuffer::new(64, 4096);
+        assert!(buf.iter().all(|&b| b == 0));
+        buf.as_mut_slice()[63] = 0xde;
+        assert_eq!(0xde, buf[63]);
+        assert_eq!(buf.as_ptr(), buf.as_slice().as_ptr());
+
+        let empty = AlignedBuffer::new(0, 64);
+        assert!(empty.is_empty());
+        assert_eq!(0, empty.as_ptr() as usize % 64);
+    }
 }
@@ -1,6 +1,10 @@
-use std::fmt;
+use std::{fmt, time};
 
 use crate::timestamp::Timestamp;
+use crate::v4l_sys::*;
+
+/// Maximum number of planes of a multi-planar buffer
+const MAX_PLANES: usize = VIDEO_MAX_PLANES as usize;
 
 /// Buffer type
 ///
@@ -29,6 +33,13 @@ pub enum Type {
     Private             = 0x80,
 }
 
+impl Type {
+    /// Returns true for buffer types which hold one memory plane per color plane
+    pub fn is_multiplanar(&self) -> bool {
+        matches!(self, Type::VideoCaptureMplane | Type::VideoOutputMplane)
+    }
+}
+
 bitflags::bitflags! {
     #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
     pub struct Flags: u32 {
@@ -97,6 +108,54 @@ impl fmt::Display for Flags {
     }
 }
 
+/// Metadata of a single plane of a multi-planar buffer
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct PlaneMeta {
+    /// Number of bytes occupied by the data in the plane, including the data offset
+    pub bytesused: u32,
+    /// Size of the plane in bytes
+    pub length: u32,
+    /// Offset of the data from the start of the plane
+    pub data_offset: u32,
+}
+
+impl PlaneMeta {
+    /// Returns the plane metadata of a dequeued buffer along with the number of planes
+    ///
+    /// Single-planar buffers have no planes.
+    ///
+    /// # Safety
+    ///
+    /// For multi-planar buffers, `m.planes` must either be null or point to `length` planes.
+    pub(crate) unsafe fn from_buffer(v4l2_buf: &v4l2_buffer) -> ([PlaneMeta; MAX_PLANES], u8) {
+        let mut planes = [PlaneMeta::default(); MAX_PLANES];
+        let mplane = v4l2_buf.type_ == Type::VideoCaptureMplane as u32
+            || v4l2_buf.type_ == Type::VideoOutputMplane as u32;
+        if !mplane || v4l2_buf.m.planes.is_null() {
+            return (planes, 0);
+        }
+
+        let count = (v4l2_buf.length as usize).min(MAX_PLANES);
+        for (meta, plane) in planes
+            .iter_mut()
+            .zip(std::slice::from_raw_parts(v4l2_buf.m.planes, count))
+        {
+            *meta = PlaneMeta::from(plane);
+        }
+        (planes, count as u8)
+    }
+}
+
+impl From<&v4l2_plane> for PlaneMeta {
+    fn from(plane: &v4l2_plane) -> Self {
+        PlaneMeta {
+            bytesused: plane.bytesused,
+            length: plane.length,
+            data_offset: plane.data_offset,
+        }
+    }
+}
+
 /// Buffer metadata, mostly used not to convolute the main buffer structs
 #[derive(Copy, Clone, Default)]
 pub struct Metadata {
@@ -110,4 +169,36 @@ pub struct Metadata {
     pub timestamp: Timestamp,
     /// Sequence number, counting the frames
     pub sequence: u32,
+    /// Time of capture in the system clock domain (only set if requested from the stream)
+    pub wall_clock: Option<time::SystemTime>,
+    /// Per-plane metadata, only the first `num_planes` entries are valid
+    pub(crate) planes: [PlaneMeta; MAX_PLANES],
+    pub(crate) num_planes: u8,
+}
+
+impl Metadata {
+    /// Returns the per-plane metadata of multi-planar buffers, empty for single-planar buffers
+    ///
+    /// Only the planes carry valid byte counts for multi-planar buffers, `bytesused` is zero.
+    pub fn planes(&self) -> &[PlaneMeta] {
+        &self.planes[..self.num_planes as usize]
+    }
+
+    /// Returns the part of a buffer which holds the frame data
+    ///
+    /// The mapped buffer spans the whole allocation, but compressed frames (e.g. MJPG) only fill
+    /// `bytesused` bytes of it, the remainder is garbage. For multi-planar buffers, the data of
+    /// the first plane is returned, skipping its data offset.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Buffer this metadata belongs to
+    pub fn valid<'b>(&self, buf: &'b [u8]) -> &'b [u8] {
+        let (start, end) = match self.planes().first() {
+            Some(plane) => (plane.data_offset as usize, plane.bytesused as usize),
+            None => (0, self.bytesused as usize),
+        };
+        let end = end.min(buf.len());
+        &buf[start.min(end)..end]
+    }
 }
@@ -38,6 +38,7 @@ bitflags::bitflags! {
         const META_OUTPUT           = 0x08000000;
 
         const TOUCH                 = 0x10000000;
+        const IO_MC                 = 0x20000000;
 
         const DEVICE_CAPS           = 0x80000000;
     }
@@ -73,8 +74,13 @@ pub struct Capabilities {
     /// Version number MAJOR.MINOR.PATCH
     pub version: (u8, u8, u8),
 
-    /// Capability flags
+    /// Capability flags of the opened device node
     pub capabilities: Flags,
+    /// Capability flags of the physical device as a whole
+    ///
+    /// A device may expose its functions through several nodes (e.g. capture and metadata), this
+    /// is the union of all of them plus [`Flags::DEVICE_CAPS`].
+    pub physical_capabilities: Flags,
 }
 
 impl From<v4l2_capability> for Capabilities {
@@ -97,7 +103,14 @@ impl From<v4l2_capability> for Capabilities {
                 ((cap.version >> 8) & 0xff) as u8,
                 (cap.version & 0xff) as u8,
             ),
-            capabilities: Flags::from(cap.device_caps),
+            // device_caps is only set by drivers which report DEVICE_CAPS, older drivers only
+            // fill in the capabilities of the physical device
+            capabilities: if cap.capabilities & Flags::DEVICE_CAPS.bits() != 0 {
+                Flags::from(cap.device_caps)
+            } else {
+                Flags::from(cap.capabilities)
+            },
+            physical_capabilities: Flags::from(cap.capabilities),
         }
     }
 }
@@ -0,0 +1,244 @@
+//! Software control loops
+//!
+//! Some devices lack automatic algorithms (or they perform poorly). The helpers in this module
+//! implement simple replacements on top of the manual controls, driven by captured frames.
+
+use std::io;
+
+use crate::control::{Control, Description, Value};
+use crate::device::Device;
+use crate::format::{Format, FourCC};
+use crate::util::control::coerce;
+use crate::v4l_sys::*;
+
+/// Returns the mean luminance of a frame
+///
+/// Supported are the packed YUV 4:2:2 formats (YUYV, YVYU, UYVY, VYUY), the planar and
+/// semi-planar YUV formats (their luma plane comes first) as well as 8 bit greyscale and
+/// 24 bit RGB. Only every fourth pixel of every fourth line is sampled.
+///
+/// # Arguments
+///
+/// * `frame` - Frame data
+/// * `format` - Format of the frame
+pub fn mean_luma(frame: &[u8], format: &Format) -> io::Result<u8> {
+    // byte offset of the first luma sample and distance between two samples
+    let (offset, pitch) = match &format.fourcc.repr {
+        b"YUYV" | b"YVYU" => (0, 2),
+        b"UYVY" | b"VYUY" => (1, 2),
+        b"GREY" | b"NV12" | b"NV21" | b"NV16" | b"NV61" | b"YU12" | b"YV12" => (0, 1),
+        b"RGB3" | b"BGR3" => (0, 3),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot measure luminance of {}", format.fourcc),
+            ))
+        }
+    };
+    let rgb = format.fourcc == FourCC::new(b"RGB3");
+    let bgr = format.fourcc == FourCC::new(b"BGR3");
+    let stride = if format.stride > 0 {
+        format.stride as usize
+    } else {
+        format.width as usize * pitch
+    };
+
+    let mut sum = 0u64;
+    let mut count = 0u64;
+    for y in (0..format.height as usize).step_by(4) {
+        for x in (0..format.width as usize).step_by(4) {
+            let i = y * stride + x * pitch + offset;
+            let luma = match (rgb, bgr) {
+                // BT.601 weights, scaled by 256
+                (true, _) if i + 2 < frame.len() => {
+                    (77 * frame[i] as u32 + 150 * frame[i + 1] as u32 + 29 * frame[i + 2] as u32)
+                        >> 8
+                }
+                (_, true) if i + 2 < frame.len() => {
+                    (29 * frame[i] as u32 + 150 * frame[i + 1] as u32 + 77 * frame[i + 2] as u32)
+                        >> 8
+                }
+                (false, false) if i < frame.len() => frame[i] as u32,
+                _ => continue,
+            };
+            sum += luma as u64;
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "frame does not contain any pixels",
+        ));
+    }
+    Ok((sum / count) as u8)
+}
+
+/// Proportional exposure controller
+///
+/// Each step measures the mean luminance of a frame and scales the exposure time by the ratio
+/// of the target to the measured luminance (damped by the gain of the controller). Once the
+/// exposure time hits its limits, the analog gain is adjusted instead (if available).
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::control::auto::ExposureController;
+/// use v4l::io::traits::CaptureStream;
+/// use v4l::prelude::*;
+/// use v4l::video::Capture;
+///
+/// let dev = Device::new(0).unwrap();
+/// let format = dev.format().unwrap();
+/// let controller = ExposureController::new(&dev, format).unwrap();
+/// let mut stream = MmapStream::new(&dev, Type::VideoCapture).unwrap();
+///
+/// for _ in 0..100 {
+///     let (buf, _) = stream.next().unwrap();
+///     controller.step(&dev, buf, 128).unwrap();
+/// }
+/// ```
+pub struct ExposureController {
+    format: Format,
+    exposure: Description,
+    gain: Option<Description>,
+    kp: f64,
+    tolerance: u8,
+}
+
+impl ExposureController {
+    /// Returns a controller for the given device
+    ///
+    /// Automatic exposure is switched off if the device supports it.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Capture device
+    /// * `format` - Format of the frames passed to [`ExposureController::step`]
+    pub fn new(dev: &Device, format: Format) -> io::Result<Self> {
+        let exposure = dev
+            .probe_control(V4L2_CID_EXPOSURE_ABSOLUTE, "V4L2_CID_EXPOSURE_ABSOLUTE")
+            .or_else(|_| dev.probe_control(V4L2_CID_EXPOSURE, "V4L2_CID_EXPOSURE"))?;
+        let gain = dev.query_control(V4L2_CID_GAIN).ok();
+
+        if let Ok(auto) = dev.query_control(V4L2_CID_EXPOSURE_AUTO) {
+            // V4L2_EXPOSURE_MANUAL
+            dev.set_control(Control {
+                id: auto.id,
+                value: Value::Integer(1),
+            })?;
+        }
+
+        Ok(ExposureController {
+            format,
+            exposure,
+            gain,
+            kp: 0.5,
+            tolerance: 4,
+        })
+    }
+
+    /// Sets the gain of the controller
+    ///
+    /// Higher values converge faster, but may overshoot. The default is 0.5.
+    ///
+    /// # Arguments
+    ///
+    /// * `kp` - Proportional gain in the range `(0, 1]`
+    pub fn with_gain(mut self, kp: f64) -> Self {
+        self.kp = kp;
+        self
+    }
+
+    /// Sets the luminance deviation which is tolerated without adjusting the controls
+    ///
+    /// The default is 4.
+    ///
+    /// # Arguments
+    ///
+    /// * `tolerance` - Tolerated deviation from the target luminance
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Adjusts exposure (or gain) toward the target luminance
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Capture device
+    /// * `frame` - Most recent frame
+    /// * `target_luma` - Desired mean luminance
+    pub fn step(&self, dev: &Device, frame: &[u8], target_luma: u8) -> io::Result<()> {
+        let luma = mean_luma(frame, &self.format)?;
+        if luma.abs_diff(target_luma) <= self.tolerance {
+            return Ok(());
+        }
+
+        // avoid dividing by zero for black frames
+        let ratio = target_luma as f64 / luma.max(1) as f64;
+        let factor = 1.0 + self.kp * (ratio - 1.0);
+
+        if self.adjust(dev, &self.exposure, factor)? {
+            return Ok(());
+        }
+        if let Some(gain) = &self.gain {
+            self.adjust(dev, gain, factor)?;
+        }
+        Ok(())
+    }
+
+    /// Scales a control value, returns false if the value is already at its limit
+    fn adjust(&self, dev: &Device, desc: &Description, factor: f64) -> io::Result<bool> {
+        let current = match dev.control(desc)?.value {
+            Value::Integer(value) => value,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unexpected {} value type", desc.name),
+                ))
+            }
+        };
+
+        let target = (current.max(1) as f64 * factor).round() as i64;
+        let value =
+            coerce(target, desc.minimum, desc.maximum, desc.step as i64).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid {} range", desc.name),
+                )
+            })?;
+        if value == current {
+            return Ok(false);
+        }
+
+        dev.set_control(Control {
+            id: desc.id,
+            value: Value::Integer(value),
+        })?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mean_luma() {
+        let mut format = Format::new(8, 8, FourCC::new(b"YUYV"));
+        format.stride = 16;
+        let frame: Vec<u8> = (0..8 * 16)
+            .map(|i| if i % 2 == 0 { 100 } else { 0 })
+            .collect();
+        assert_eq!(100, mean_luma(&frame, &format).unwrap());
+
+        format.fourcc = FourCC::new(b"UYVY");
+        assert_eq!(0, mean_luma(&frame, &format).unwrap());
+
+        format.fourcc = FourCC::new(b"MJPG");
+        assert!(mean_luma(&frame, &format).is_err());
+    }
+}
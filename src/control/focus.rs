@@ -0,0 +1,114 @@
+//! High-level focus helpers
+//!
+//! Cameras with autofocus expose a set of related controls in the camera control class:
+//! * `V4L2_CID_FOCUS_AUTO` (continuous autofocus on/off)
+//! * `V4L2_CID_AUTO_FOCUS_START` (one-shot autofocus trigger)
+//! * `V4L2_CID_AUTO_FOCUS_STATUS` (state of the autofocus algorithm)
+//!
+//! Devices may only implement a subset of them. Each helper probes its control first and
+//! fails with [`io::ErrorKind::Unsupported`] if it is missing.
+
+use std::{fmt, io};
+
+use crate::control::{Control, Value};
+use crate::device::Device;
+use crate::v4l_sys::*;
+
+bitflags::bitflags! {
+    #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+    pub struct Status: u32 {
+        /// Autofocus is running
+        const BUSY      = 0x0001;
+        /// Autofocus has converged
+        const REACHED   = 0x0002;
+        /// Autofocus has failed to converge
+        const FAILED    = 0x0004;
+    }
+}
+
+impl Status {
+    /// Returns true if the autofocus algorithm is not running at all
+    pub fn is_idle(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl From<u32> for Status {
+    fn from(status: u32) -> Self {
+        Self::from_bits_retain(status)
+    }
+}
+
+impl From<Status> for u32 {
+    fn from(status: Status) -> Self {
+        status.bits()
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Enables or disables continuous autofocus
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+/// * `enable` - Whether continuous autofocus should be active
+pub fn set_auto_focus(dev: &Device, enable: bool) -> io::Result<()> {
+    let desc = dev.probe_control(V4L2_CID_FOCUS_AUTO, "V4L2_CID_FOCUS_AUTO")?;
+    dev.set_control(Control {
+        id: desc.id,
+        value: Value::Boolean(enable),
+    })
+}
+
+/// Returns whether continuous autofocus is active
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+pub fn auto_focus(dev: &Device) -> io::Result<bool> {
+    let desc = dev.probe_control(V4L2_CID_FOCUS_AUTO, "V4L2_CID_FOCUS_AUTO")?;
+    match dev.control(&desc)?.value {
+        Value::Boolean(enabled) => Ok(enabled),
+        Value::Integer(enabled) => Ok(enabled != 0),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected V4L2_CID_FOCUS_AUTO value type",
+        )),
+    }
+}
+
+/// Triggers a one-shot autofocus run
+///
+/// Use [`auto_focus_status`] to find out when the run has finished.
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+pub fn trigger_auto_focus(dev: &Device) -> io::Result<()> {
+    let desc = dev.probe_control(V4L2_CID_AUTO_FOCUS_START, "V4L2_CID_AUTO_FOCUS_START")?;
+    dev.set_control(Control {
+        id: desc.id,
+        value: Value::None,
+    })
+}
+
+/// Returns the state of the autofocus algorithm
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+pub fn auto_focus_status(dev: &Device) -> io::Result<Status> {
+    let desc = dev.probe_control(V4L2_CID_AUTO_FOCUS_STATUS, "V4L2_CID_AUTO_FOCUS_STATUS")?;
+    match dev.control(&desc)?.value {
+        Value::Integer(status) => Ok(Status::from(status as u32)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected V4L2_CID_AUTO_FOCUS_STATUS value type",
+        )),
+    }
+}
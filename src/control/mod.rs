@@ -1,8 +1,13 @@
 use std::convert::{TryFrom, TryInto};
-use std::{ffi, fmt, mem, str};
+use std::{error, ffi, fmt, io, mem, str};
 
 use crate::v4l_sys::*;
 
+pub mod auto;
+pub mod focus;
+pub mod orientation;
+pub mod raw;
+
 /// Control data type
 #[allow(clippy::unreadable_literal)]
 #[rustfmt::skip]
@@ -78,6 +83,107 @@ impl fmt::Display for Type {
     }
 }
 
+/// Control class
+///
+/// The class of a control is encoded in the upper 16 bits of its id.
+#[allow(clippy::unreadable_literal)]
+#[rustfmt::skip]
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Class {
+    User            = 0x980000,
+    Codec           = 0x990000,
+    Camera          = 0x9a0000,
+    FmTx            = 0x9b0000,
+    Flash           = 0x9c0000,
+    Jpeg            = 0x9d0000,
+    ImageSource     = 0x9e0000,
+    ImageProc       = 0x9f0000,
+    Dv              = 0xa00000,
+    FmRx            = 0xa10000,
+    RfTuner         = 0xa20000,
+    Detect          = 0xa30000,
+    CodecStateless  = 0xa40000,
+    Colorimetry     = 0xa50000,
+
+    Unknown(u32),
+}
+
+impl Class {
+    /// Returns the class of a control
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Control id
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::control::Class;
+    /// use v4l::v4l_sys::V4L2_CID_BRIGHTNESS;
+    /// assert_eq!(Class::of(V4L2_CID_BRIGHTNESS), Class::User);
+    /// ```
+    pub fn of(id: u32) -> Self {
+        Self::from(id & 0xFFFF0000)
+    }
+}
+
+// V4L2_CTRL_CLASS_CODEC_STATELESS and V4L2_CTRL_CLASS_COLORIMETRY were added in Linux 5.11 and
+// 5.14, older headers do not define them
+const CLASS_CODEC_STATELESS: u32 = 0x00a40000;
+const CLASS_COLORIMETRY: u32 = 0x00a50000;
+
+impl From<u32> for Class {
+    fn from(repr: u32) -> Self {
+        match repr {
+            V4L2_CTRL_CLASS_USER => Self::User,
+            // renamed to V4L2_CTRL_CLASS_CODEC in Linux 5.11, the old name is kept as an alias
+            V4L2_CTRL_CLASS_MPEG => Self::Codec,
+            V4L2_CTRL_CLASS_CAMERA => Self::Camera,
+            V4L2_CTRL_CLASS_FM_TX => Self::FmTx,
+            V4L2_CTRL_CLASS_FLASH => Self::Flash,
+            V4L2_CTRL_CLASS_JPEG => Self::Jpeg,
+            V4L2_CTRL_CLASS_IMAGE_SOURCE => Self::ImageSource,
+            V4L2_CTRL_CLASS_IMAGE_PROC => Self::ImageProc,
+            V4L2_CTRL_CLASS_DV => Self::Dv,
+            V4L2_CTRL_CLASS_FM_RX => Self::FmRx,
+            V4L2_CTRL_CLASS_RF_TUNER => Self::RfTuner,
+            V4L2_CTRL_CLASS_DETECT => Self::Detect,
+            CLASS_CODEC_STATELESS => Self::CodecStateless,
+            CLASS_COLORIMETRY => Self::Colorimetry,
+            repr => Self::Unknown(repr),
+        }
+    }
+}
+
+impl From<Class> for u32 {
+    fn from(class: Class) -> Self {
+        match class {
+            Class::User => V4L2_CTRL_CLASS_USER,
+            Class::Codec => V4L2_CTRL_CLASS_MPEG,
+            Class::Camera => V4L2_CTRL_CLASS_CAMERA,
+            Class::FmTx => V4L2_CTRL_CLASS_FM_TX,
+            Class::Flash => V4L2_CTRL_CLASS_FLASH,
+            Class::Jpeg => V4L2_CTRL_CLASS_JPEG,
+            Class::ImageSource => V4L2_CTRL_CLASS_IMAGE_SOURCE,
+            Class::ImageProc => V4L2_CTRL_CLASS_IMAGE_PROC,
+            Class::Dv => V4L2_CTRL_CLASS_DV,
+            Class::FmRx => V4L2_CTRL_CLASS_FM_RX,
+            Class::RfTuner => V4L2_CTRL_CLASS_RF_TUNER,
+            Class::Detect => V4L2_CTRL_CLASS_DETECT,
+            Class::CodecStateless => CLASS_CODEC_STATELESS,
+            Class::Colorimetry => CLASS_COLORIMETRY,
+            Class::Unknown(class) => class,
+        }
+    }
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
 bitflags::bitflags! {
     #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
     pub struct Flags: u32 {
@@ -116,7 +222,7 @@ impl fmt::Display for Flags {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// Device control menu item
 pub enum MenuItem {
     Name(String),
@@ -156,7 +262,7 @@ impl TryFrom<(Type, v4l2_querymenu)> for MenuItem {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// Device control description
 pub struct Description {
     /// Control identifier, set by the the application
@@ -175,6 +281,10 @@ pub struct Description {
     pub default: i64,
     /// Control flags
     pub flags: Flags,
+    /// Size of a single element of the payload in bytes (only valid for payload controls)
+    pub elem_size: u32,
+    /// Number of elements of the payload (only valid for payload controls)
+    pub elems: u32,
 
     /// Items for menu controls (only valid if typ is a menu type)
     pub items: Option<Vec<(u32, MenuItem)>>,
@@ -194,6 +304,8 @@ impl From<v4l2_query_ext_ctrl> for Description {
             step: ctrl.step,
             default: ctrl.default_value,
             flags: Flags::from(ctrl.flags),
+            elem_size: ctrl.elem_size,
+            elems: ctrl.elems,
             items: None,
         }
     }
@@ -225,7 +337,39 @@ pub struct Control {
     pub value: Value,
 }
 
+/// Control rejected by the driver when setting several controls at once
+///
+/// Carried as the inner error of the [`io::Error`] returned by
+/// [`crate::device::Device::set_controls`] if the driver points at the offending control.
+/// Retrieve it with `error.get_ref().and_then(|e| e.downcast_ref::<RejectedControl>())`.
+#[derive(Debug)]
+pub struct RejectedControl {
+    /// Position of the control in the batch
+    pub index: usize,
+    /// Id of the control
+    pub id: u32,
+    /// Error reported by the driver
+    pub error: io::Error,
+}
+
+impl fmt::Display for RejectedControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "control {:#x} (index {}) rejected: {}",
+            self.id, self.index, self.error
+        )
+    }
+}
+
+impl error::Error for RejectedControl {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Device control value
 pub enum Value {
     /* buttons */
@@ -239,6 +383,8 @@ pub enum Value {
     CompoundU16(Vec<u16>),
     CompoundU32(Vec<u32>),
     CompoundPtr(Vec<u8>),
+    /* opaque payload of vendor-specific controls */
+    Raw(Vec<u8>),
 }
 
 impl TryInto<v4l2_control> for Control {
@@ -0,0 +1,151 @@
+//! High-level image orientation helpers
+//!
+//! Orientation is controlled by the following controls in the user class:
+//! * `V4L2_CID_HFLIP` (mirror horizontally)
+//! * `V4L2_CID_VFLIP` (flip vertically)
+//! * `V4L2_CID_ROTATE` (rotate by the given number of degrees)
+//!
+//! Most devices only implement the flip controls. Each helper probes its controls first and
+//! fails with [`io::ErrorKind::Unsupported`] if they are missing.
+
+use std::{fmt, io};
+
+use crate::control::{Control, Value};
+use crate::device::Device;
+use crate::v4l_sys::*;
+
+/// Clockwise image rotation
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rotation {
+    None,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl Rotation {
+    /// Returns the rotation in degrees
+    pub fn degrees(&self) -> i64 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Rotate90 => 90,
+            Rotation::Rotate180 => 180,
+            Rotation::Rotate270 => 270,
+        }
+    }
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}°", self.degrees())
+    }
+}
+
+fn flip(dev: &Device, id: u32, name: &str) -> io::Result<bool> {
+    let desc = dev.probe_control(id, name)?;
+    match dev.control(&desc)?.value {
+        Value::Boolean(enabled) => Ok(enabled),
+        Value::Integer(enabled) => Ok(enabled != 0),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected {} value type", name),
+        )),
+    }
+}
+
+/// Enables or disables horizontal mirroring
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+/// * `enable` - Whether the image should be mirrored
+pub fn set_hflip(dev: &Device, enable: bool) -> io::Result<()> {
+    let desc = dev.probe_control(V4L2_CID_HFLIP, "V4L2_CID_HFLIP")?;
+    dev.set_control(Control {
+        id: desc.id,
+        value: Value::Boolean(enable),
+    })
+}
+
+/// Returns whether the image is mirrored horizontally
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+pub fn hflip(dev: &Device) -> io::Result<bool> {
+    flip(dev, V4L2_CID_HFLIP, "V4L2_CID_HFLIP")
+}
+
+/// Enables or disables vertical flipping
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+/// * `enable` - Whether the image should be flipped
+pub fn set_vflip(dev: &Device, enable: bool) -> io::Result<()> {
+    let desc = dev.probe_control(V4L2_CID_VFLIP, "V4L2_CID_VFLIP")?;
+    dev.set_control(Control {
+        id: desc.id,
+        value: Value::Boolean(enable),
+    })
+}
+
+/// Returns whether the image is flipped vertically
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+pub fn vflip(dev: &Device) -> io::Result<bool> {
+    flip(dev, V4L2_CID_VFLIP, "V4L2_CID_VFLIP")
+}
+
+/// Rotates the image
+///
+/// `V4L2_CID_ROTATE` is used if the device supports the requested angle. Otherwise, a rotation
+/// by 0° or 180° is composed of the flip controls (both off or both on). Rotating by 90° or
+/// 270° requires `V4L2_CID_ROTATE`.
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+/// * `rotation` - Clockwise rotation
+pub fn set_rotation(dev: &Device, rotation: Rotation) -> io::Result<()> {
+    let degrees = rotation.degrees();
+    if let Ok(desc) = dev.query_control(V4L2_CID_ROTATE) {
+        let step = desc.step.max(1) as i64;
+        if degrees >= desc.minimum
+            && degrees <= desc.maximum
+            && (degrees - desc.minimum) % step == 0
+        {
+            return dev.set_control(Control {
+                id: desc.id,
+                value: Value::Integer(degrees),
+            });
+        }
+    }
+
+    let flipped = match rotation {
+        Rotation::None => false,
+        Rotation::Rotate180 => true,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("rotation by {} is not supported by the device", rotation),
+            ))
+        }
+    };
+
+    let hflip = dev.probe_control(V4L2_CID_HFLIP, "V4L2_CID_HFLIP")?;
+    let vflip = dev.probe_control(V4L2_CID_VFLIP, "V4L2_CID_VFLIP")?;
+    // both controls are in the user class, so they can be set at once
+    dev.set_controls(vec![
+        Control {
+            id: hflip.id,
+            value: Value::Boolean(flipped),
+        },
+        Control {
+            id: vflip.id,
+            value: Value::Boolean(flipped),
+        },
+    ])
+}
@@ -0,0 +1,215 @@
+//! Raw sensor helpers
+//!
+//! Raw (Bayer) sensors add a constant offset, the black level, to every pixel value so that
+//! noise around zero is not clipped. Any processing of raw frames (e.g. dark frame subtraction,
+//! white balancing or debayering) has to subtract the black level first, so it must be known
+//! exactly and should be stable over a capture session.
+//!
+//! There is no single standard control for this. The helpers in this module look for:
+//! * `V4L2_CID_BLACK_LEVEL` (deprecated, but still implemented by some drivers)
+//! * integer controls named "Black Level" (e.g. vendor controls of sensor drivers)
+//!
+//! Per-channel black levels are exposed as array controls, those are accessed through the
+//! compound control path. All helpers fail with [`io::ErrorKind::Unsupported`] if the device
+//! has no black level control.
+
+use std::{convert::TryFrom, io};
+
+use crate::control::{Control, Description, Flags, Type, Value};
+use crate::device::Device;
+use crate::util::control::is_aligned;
+use crate::v4l_sys::*;
+
+/// Black level of a raw sensor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlackLevel {
+    /// Same level for all color channels
+    Uniform(i64),
+    /// One level per color channel, in the order of the Bayer pattern
+    PerChannel(Vec<i64>),
+}
+
+fn is_numeric(desc: &Description) -> bool {
+    matches!(
+        desc.typ,
+        Type::Integer | Type::Integer64 | Type::U8 | Type::U16 | Type::U32
+    )
+}
+
+/// Returns the black level control of the device
+///
+/// The standard control is preferred over controls found by their name, as long as it is
+/// numeric.
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+pub fn black_level_control(dev: &Device) -> io::Result<Description> {
+    if let Ok(desc) = dev.query_control(V4L2_CID_BLACK_LEVEL) {
+        if is_numeric(&desc) {
+            return Ok(desc);
+        }
+    }
+
+    dev.query_controls()?
+        .into_iter()
+        .find(|desc| is_numeric(desc) && desc.name.to_lowercase().contains("black level"))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "device does not have a black level control",
+            )
+        })
+}
+
+fn check(desc: &Description, value: i64) -> io::Result<()> {
+    let step = i64::try_from(desc.step.max(1)).unwrap_or(i64::MAX);
+    if value < desc.minimum || value > desc.maximum || !is_aligned(value, desc.minimum, step) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "black level {} is out of range [{}, {}] (step {}) of '{}'",
+                value, desc.minimum, desc.maximum, step, desc.name
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the black level of the sensor
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+pub fn black_level(dev: &Device) -> io::Result<BlackLevel> {
+    let desc = black_level_control(dev)?;
+    match dev.control(&desc)?.value {
+        Value::Integer(level) => Ok(BlackLevel::Uniform(level)),
+        Value::CompoundU8(levels) => Ok(BlackLevel::PerChannel(
+            levels.into_iter().map(i64::from).collect(),
+        )),
+        Value::CompoundU16(levels) => Ok(BlackLevel::PerChannel(
+            levels.into_iter().map(i64::from).collect(),
+        )),
+        Value::CompoundU32(levels) => Ok(BlackLevel::PerChannel(
+            levels.into_iter().map(i64::from).collect(),
+        )),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected value type of '{}'", desc.name),
+        )),
+    }
+}
+
+/// Sets the black level of the sensor
+///
+/// Every level is validated against the range of the control before anything is written.
+/// A uniform level is applied to all channels of per-channel controls. Per-channel levels
+/// must match the number of channels of the control.
+///
+/// # Arguments
+///
+/// * `dev` - Capture device
+/// * `level` - Black level to apply
+pub fn set_black_level(dev: &Device, level: &BlackLevel) -> io::Result<()> {
+    let desc = black_level_control(dev)?;
+    if desc.flags.contains(Flags::READ_ONLY) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("'{}' is read-only", desc.name),
+        ));
+    }
+
+    let value = match (desc.typ, level) {
+        (Type::Integer | Type::Integer64, BlackLevel::Uniform(level)) => {
+            check(&desc, *level)?;
+            Value::Integer(*level)
+        }
+        (Type::Integer | Type::Integer64, BlackLevel::PerChannel(_)) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{}' does not support per-channel levels", desc.name),
+            ))
+        }
+        (typ, level) => {
+            let levels = match level {
+                BlackLevel::Uniform(level) => vec![*level; desc.elems as usize],
+                BlackLevel::PerChannel(levels) => levels.clone(),
+            };
+            if levels.len() != desc.elems as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "'{}' has {} channels, got {} levels",
+                        desc.name,
+                        desc.elems,
+                        levels.len()
+                    ),
+                ));
+            }
+            for level in &levels {
+                check(&desc, *level)?;
+            }
+
+            // the range check above guarantees that the casts are lossless
+            match typ {
+                Type::U8 => Value::CompoundU8(levels.iter().map(|&l| l as u8).collect()),
+                Type::U16 => Value::CompoundU16(levels.iter().map(|&l| l as u16).collect()),
+                _ => Value::CompoundU32(levels.iter().map(|&l| l as u32).collect()),
+            }
+        }
+    };
+
+    dev.set_control(Control { id: desc.id, value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desc(minimum: i64, maximum: i64, step: u64) -> Description {
+        Description {
+            id: V4L2_CID_BLACK_LEVEL,
+            typ: Type::Integer,
+            name: "Black Level".to_string(),
+            minimum,
+            maximum,
+            step,
+            default: 0,
+            flags: Flags::empty(),
+            elem_size: 0,
+            elems: 0,
+            items: None,
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let d = desc(16, 256, 16);
+        let td = vec![
+            (16, true),
+            (32, true),
+            (256, true),
+            (0, false),
+            (24, false),
+            (272, false),
+        ];
+        for (value, ok) in td {
+            assert_eq!(ok, check(&d, value).is_ok(), "check({})", value);
+        }
+        assert_eq!(
+            io::ErrorKind::InvalidInput,
+            check(&d, 24).unwrap_err().kind()
+        );
+
+        // the distance to the minimum does not fit into i64
+        let d = desc(i64::MIN, i64::MAX, 2);
+        assert!(check(&d, i64::MAX - 1).is_ok());
+        assert!(check(&d, i64::MAX).is_err());
+
+        // steps beyond i64 only allow the minimum
+        let d = desc(0, i64::MAX, u64::MAX);
+        assert!(check(&d, 0).is_ok());
+        assert!(check(&d, 1).is_err());
+    }
+}
@@ -1,12 +1,19 @@
 use std::convert::TryFrom;
-use std::path::Path;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::{io, mem};
+use std::time::{Duration, Instant};
+use std::{io, mem, thread};
 
 use libc;
 
-use crate::capability::Capabilities;
+use crate::buffer;
+use crate::capability::{self, Capabilities};
+use crate::context;
 use crate::control::{self, Control, Description};
+use crate::event::{self, Event};
+use crate::format::Description as FormatDescription;
+use crate::memory::Memory;
 use crate::v4l2;
 use crate::v4l2::videodev::v4l2_ext_controls;
 use crate::v4l_sys::*;
@@ -15,6 +22,10 @@ use crate::v4l_sys::*;
 pub struct Device {
     /// Raw handle
     handle: Arc<Handle>,
+    /// Number of frames streams discard after starting by default
+    initial_frame_skip: usize,
+    /// Upper bound for the memory of all buffers of a stream
+    max_total_buffer_bytes: Option<usize>,
 }
 
 impl Device {
@@ -42,7 +53,9 @@ impl Device {
         }
 
         Ok(Device {
-            handle: Arc::new(Handle::new(fd)),
+            handle: Arc::new(Handle::new(fd, false)),
+            initial_frame_skip: 0,
+            max_total_buffer_bytes: None,
         })
     }
 
@@ -61,6 +74,33 @@ impl Device {
     /// let dev = Device::with_path("/dev/video0");
     /// ```
     pub fn with_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Device::open(path, false)
+    }
+
+    /// Returns a capture device by path whose streams never wait for buffers
+    ///
+    /// By default, streams wait (with an optional timeout) until a buffer is ready before
+    /// dequeueing it. Streams of a non-blocking device dequeue right away instead and fail with
+    /// [`io::ErrorKind::WouldBlock`] (`EAGAIN`) if no buffer is ready yet. This is meant for
+    /// custom event loops, which register [`Handle::fd`] themselves (e.g. with mio) and only
+    /// call `dequeue` or `next` once the descriptor became readable.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path (e.g. "/dev/video0")
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::device::Device;
+    /// let dev = Device::with_path_nonblocking("/dev/video0");
+    /// ```
+    pub fn with_path_nonblocking<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Device::open(path, true)
+    }
+
+    fn open<P: AsRef<Path>>(path: P, nonblocking: bool) -> io::Result<Self> {
+        // the descriptor is non-blocking either way, blocking streams poll it before dequeueing
         let fd = v4l2::open(&path, libc::O_RDWR | libc::O_NONBLOCK)?;
 
         if fd == -1 {
@@ -68,7 +108,9 @@ impl Device {
         }
 
         Ok(Device {
-            handle: Arc::new(Handle::new(fd)),
+            handle: Arc::new(Handle::new(fd, nonblocking)),
+            initial_frame_skip: 0,
+            max_total_buffer_bytes: None,
         })
     }
 
@@ -77,6 +119,42 @@ impl Device {
         self.handle.clone()
     }
 
+    /// Returns the number of frames streams created for this device discard after starting
+    pub fn initial_frame_skip(&self) -> usize {
+        self.initial_frame_skip
+    }
+
+    /// Sets the number of frames streams created for this device discard after starting
+    ///
+    /// Some drivers return zero-filled or partial buffers right after `VIDIOC_STREAMON`.
+    /// This value is used as default for new streams, see
+    /// [`crate::io::mmap::Stream::skip_initial`].
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of frames to discard
+    pub fn set_initial_frame_skip(&mut self, count: usize) {
+        self.initial_frame_skip = count;
+    }
+
+    /// Returns the memory budget for the buffers of streams created for this device
+    pub fn max_total_buffer_bytes(&self) -> Option<usize> {
+        self.max_total_buffer_bytes
+    }
+
+    /// Sets the memory budget for the buffers of streams created for this device
+    ///
+    /// Streams check the number of requested buffers times the image size of the current
+    /// format against the budget before allocating anything and fail with
+    /// [`io::ErrorKind::OutOfMemory`] if it would be exceeded.
+    ///
+    /// # Arguments
+    ///
+    /// * `max` - Budget in bytes, `None` to allow any size
+    pub fn set_max_total_buffer_bytes(&mut self, max: Option<usize>) {
+        self.max_total_buffer_bytes = max;
+    }
+
     /// Returns video4linux framework defined information such as card, driver, etc.
     pub fn query_caps(&self) -> io::Result<Capabilities> {
         unsafe {
@@ -91,6 +169,80 @@ impl Device {
         }
     }
 
+    /// Returns the formats supported for buffers of the given type
+    ///
+    /// [`crate::video::Capture::enum_formats`] and [`crate::video::Output::enum_formats`] cover
+    /// the video buffer types, this allows to enumerate the formats of any buffer type (e.g.
+    /// metadata). Returns an empty vector if the buffer type is not supported.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Type of the buffers, e.g. [`buffer::Type::VideoCaptureMplane`]
+    pub fn enum_formats_for(&self, buf_type: buffer::Type) -> io::Result<Vec<FormatDescription>> {
+        let mut formats = Vec::new();
+        let mut v4l2_fmt = v4l2_fmtdesc {
+            index: 0,
+            type_: buf_type as u32,
+            ..unsafe { mem::zeroed() }
+        };
+
+        // the end of the list is signaled by an error, an error on the first index means that
+        // there are no formats for the buffer type at all
+        while unsafe {
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_ENUM_FMT,
+                &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+        .is_ok()
+        {
+            formats.push(FormatDescription::from(v4l2_fmt));
+            v4l2_fmt = v4l2_fmtdesc {
+                index: v4l2_fmt.index + 1,
+                type_: buf_type as u32,
+                ..unsafe { mem::zeroed() }
+            };
+        }
+
+        Ok(formats)
+    }
+
+    /// Returns the memory types supported for buffers of the given type
+    ///
+    /// Older kernels do not report this information, the returned list is empty then.
+    /// Must not be called while buffers are allocated for the buffer type.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_type` - Type of the buffers
+    pub fn supported_memory(&self, buf_type: buffer::Type) -> io::Result<Vec<Memory>> {
+        let caps = unsafe {
+            let mut v4l2_reqbufs = v4l2_requestbuffers {
+                count: 0,
+                type_: buf_type as u32,
+                memory: Memory::Mmap as u32,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_REQBUFS,
+                &mut v4l2_reqbufs as *mut _ as *mut std::os::raw::c_void,
+            )?;
+            v4l2_reqbufs.capabilities
+        };
+
+        Ok([
+            (V4L2_BUF_CAP_SUPPORTS_MMAP, Memory::Mmap),
+            (V4L2_BUF_CAP_SUPPORTS_USERPTR, Memory::UserPtr),
+            (V4L2_BUF_CAP_SUPPORTS_DMABUF, Memory::DmaBuf),
+        ]
+        .iter()
+        .filter(|(cap, _)| caps & cap != 0)
+        .map(|(_, memory)| *memory)
+        .collect())
+    }
+
     /// Returns the supported controls for a device such as gain, focus, white balance, etc.
     pub fn query_controls(&self) -> io::Result<Vec<Description>> {
         let mut controls = Vec::new();
@@ -113,42 +265,7 @@ impl Device {
                         if control.typ == control::Type::Menu
                             || control.typ == control::Type::IntegerMenu
                         {
-                            let mut items = Vec::new();
-
-                            for i in (v4l2_ctrl.minimum..=v4l2_ctrl.maximum)
-                                .step_by(v4l2_ctrl.step as usize)
-                            {
-                                let mut v4l2_menu = v4l2_querymenu {
-                                    id: v4l2_ctrl.id,
-                                    index: i as u32,
-                                    ..mem::zeroed()
-                                };
-                                let res = v4l2::ioctl(
-                                    self.handle().fd(),
-                                    v4l2::vidioc::VIDIOC_QUERYMENU,
-                                    &mut v4l2_menu as *mut _ as *mut std::os::raw::c_void,
-                                );
-
-                                // BEWARE OF DRAGONS!
-                                // The API docs [1] state VIDIOC_QUERYMENU should may return EINVAL
-                                // for some indices between minimum and maximum when an item is not
-                                // supported by a driver.
-                                //
-                                // I have no idea why it is advertised in the first place then, but
-                                // have seen this happen with a Logitech C920 HD Pro webcam.
-                                // In case of errors, let's just skip the offending index.
-                                //
-                                // [1] https://github.com/torvalds/linux/blob/master/Documentation/userspace-api/media/v4l/vidioc-queryctrl.rst#description
-                                if res.is_err() {
-                                    continue;
-                                }
-
-                                let item =
-                                    control::MenuItem::try_from((control.typ, v4l2_menu)).unwrap();
-                                items.push((v4l2_menu.index, item));
-                            }
-
-                            control.items = Some(items);
+                            control.items = Some(self.query_menu_items(&v4l2_ctrl, control.typ));
                         }
 
                         controls.push(control);
@@ -167,12 +284,135 @@ impl Device {
         Ok(controls)
     }
 
+    /// Returns the description of a single control
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the device does not expose the control.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Control identifier, e.g. `V4L2_CID_BRIGHTNESS`
+    pub fn query_control(&self, id: u32) -> io::Result<Description> {
+        unsafe {
+            let mut v4l2_ctrl = v4l2_query_ext_ctrl {
+                id,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_QUERY_EXT_CTRL,
+                &mut v4l2_ctrl as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            let mut control = Description::from(v4l2_ctrl);
+            if control.typ == control::Type::Menu || control.typ == control::Type::IntegerMenu {
+                control.items = Some(self.query_menu_items(&v4l2_ctrl, control.typ));
+            }
+
+            Ok(control)
+        }
+    }
+
+    /// Returns the items of a menu control along with their indices
+    ///
+    /// Items are [`control::MenuItem::Name`] labels for `V4L2_CTRL_TYPE_MENU` and
+    /// [`control::MenuItem::Value`] integers for `V4L2_CTRL_TYPE_INTEGER_MENU`. Drivers may
+    /// leave out indices between the minimum and maximum of the control, so the indices are not
+    /// necessarily contiguous. Fails with [`io::ErrorKind::InvalidInput`] if the device does not
+    /// expose the control or if it is not a menu.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Control identifier, e.g. `V4L2_CID_EXPOSURE_AUTO`
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::device::Device;
+    /// use v4l::v4l_sys::V4L2_CID_POWER_LINE_FREQUENCY;
+    ///
+    /// let dev = Device::new(0).unwrap();
+    /// for (index, item) in dev.enum_menu(V4L2_CID_POWER_LINE_FREQUENCY).unwrap() {
+    ///     println!("{}: {}", index, item);
+    /// }
+    /// ```
+    pub fn enum_menu(&self, id: u32) -> io::Result<Vec<(u32, control::MenuItem)>> {
+        match self.query_control(id)?.items {
+            Some(items) => Ok(items),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("control {:#x} is not a menu", id),
+            )),
+        }
+    }
+
+    fn query_menu_items(
+        &self,
+        v4l2_ctrl: &v4l2_query_ext_ctrl,
+        typ: control::Type,
+    ) -> Vec<(u32, control::MenuItem)> {
+        let mut items = Vec::new();
+
+        for i in (v4l2_ctrl.minimum..=v4l2_ctrl.maximum).step_by(v4l2_ctrl.step as usize) {
+            let mut v4l2_menu = v4l2_querymenu {
+                id: v4l2_ctrl.id,
+                index: i as u32,
+                ..unsafe { mem::zeroed() }
+            };
+            let res = unsafe {
+                v4l2::ioctl(
+                    self.handle().fd(),
+                    v4l2::vidioc::VIDIOC_QUERYMENU,
+                    &mut v4l2_menu as *mut _ as *mut std::os::raw::c_void,
+                )
+            };
+
+            // BEWARE OF DRAGONS!
+            // The API docs [1] state VIDIOC_QUERYMENU should may return EINVAL
+            // for some indices between minimum and maximum when an item is not
+            // supported by a driver.
+            //
+            // I have no idea why it is advertised in the first place then, but
+            // have seen this happen with a Logitech C920 HD Pro webcam.
+            // In case of errors, let's just skip the offending index.
+            //
+            // [1] https://github.com/torvalds/linux/blob/master/Documentation/userspace-api/media/v4l/vidioc-queryctrl.rst#description
+            if res.is_err() {
+                continue;
+            }
+
+            let item = control::MenuItem::try_from((typ, v4l2_menu)).unwrap();
+            items.push((v4l2_menu.index, item));
+        }
+
+        items
+    }
+
+    /// Returns the description of a single control, failing with [`io::ErrorKind::Unsupported`]
+    /// if the device does not expose it
+    pub(crate) fn probe_control(&self, id: u32, name: &str) -> io::Result<Description> {
+        match self.query_control(id) {
+            Ok(desc) => Ok(desc),
+            Err(e) if e.kind() == io::ErrorKind::InvalidInput => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} control is not supported by the device", name),
+            )),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Returns the current control value from its [`Description`]
     ///
+    /// Controls carrying a payload (strings, arrays and other compound types) are read into a
+    /// buffer sized according to the description.
+    ///
     /// # Arguments
     ///
     /// * `desc` - Control description
     pub fn control(&self, desc: &Description) -> io::Result<Control> {
+        if desc.flags.contains(control::Flags::HAS_PAYLOAD) {
+            return self.payload_control(desc);
+        }
+
         unsafe {
             // query the actual control value
             let mut v4l2_ctrl = v4l2_ext_control {
@@ -190,13 +430,89 @@ impl Device {
                 &mut v4l2_ctrls as *mut _ as *mut std::os::raw::c_void,
             )?;
 
-            let value = match desc.typ {
+            Ok(Control {
+                id: desc.id,
+                value: Self::scalar_value(desc.typ, &v4l2_ctrl)?,
+            })
+        }
+    }
+
+    /// Returns the current values of multiple controls from their [`Description`]s
+    ///
+    /// Scalar controls are read in a single `VIDIOC_G_EXT_CTRLS` call. Controls carrying a
+    /// payload need a buffer of their own, so they are split off and read one by one. This way,
+    /// a compound control does not make the read of the whole batch fail.
+    ///
+    /// The returned controls are in the same order as the descriptions.
+    ///
+    /// # Arguments
+    ///
+    /// * `descs` - Control descriptions
+    pub fn controls(&self, descs: &[Description]) -> io::Result<Vec<Control>> {
+        let (payload, scalar): (Vec<&Description>, Vec<&Description>) = descs
+            .iter()
+            .partition(|desc| desc.flags.contains(control::Flags::HAS_PAYLOAD));
+
+        let mut values = Vec::new();
+        if !scalar.is_empty() {
+            unsafe {
+                let mut control_list: Vec<v4l2_ext_control> = scalar
+                    .iter()
+                    .map(|desc| v4l2_ext_control {
+                        id: desc.id,
+                        ..mem::zeroed()
+                    })
+                    .collect();
+                // which = V4L2_CTRL_WHICH_CUR_VAL (0) allows mixing control classes
+                let mut v4l2_ctrls = v4l2_ext_controls {
+                    count: control_list.len() as u32,
+                    controls: control_list.as_mut_ptr(),
+                    ..mem::zeroed()
+                };
+                v4l2::ioctl(
+                    self.handle().fd(),
+                    v4l2::vidioc::VIDIOC_G_EXT_CTRLS,
+                    &mut v4l2_ctrls as *mut _ as *mut std::os::raw::c_void,
+                )?;
+
+                for (desc, v4l2_ctrl) in scalar.iter().zip(control_list.iter()) {
+                    values.push(Control {
+                        id: desc.id,
+                        value: Self::scalar_value(desc.typ, v4l2_ctrl)?,
+                    });
+                }
+            }
+        }
+
+        for desc in payload {
+            values.push(self.payload_control(desc)?);
+        }
+
+        // restore the order of the descriptions
+        let mut controls = Vec::with_capacity(values.len());
+        for desc in descs {
+            let pos = values.iter().position(|ctrl| ctrl.id == desc.id).unwrap();
+            controls.push(values.swap_remove(pos));
+        }
+
+        Ok(controls)
+    }
+
+    fn scalar_value(
+        typ: control::Type,
+        v4l2_ctrl: &v4l2_ext_control,
+    ) -> io::Result<control::Value> {
+        unsafe {
+            let value = match typ {
                 control::Type::Integer64 => {
                     control::Value::Integer(v4l2_ctrl.__bindgen_anon_1.value64)
                 }
-                control::Type::Integer | control::Type::Menu => {
+                control::Type::Integer | control::Type::Menu | control::Type::IntegerMenu => {
                     control::Value::Integer(v4l2_ctrl.__bindgen_anon_1.value as i64)
                 }
+                control::Type::Bitmask => {
+                    control::Value::Integer(v4l2_ctrl.__bindgen_anon_1.value as u32 as i64)
+                }
                 control::Type::Boolean => {
                     control::Value::Boolean(v4l2_ctrl.__bindgen_anon_1.value == 1)
                 }
@@ -208,7 +524,135 @@ impl Device {
                 }
             };
 
-            Ok(Control { id: desc.id, value })
+            Ok(value)
+        }
+    }
+
+    fn payload_control(&self, desc: &Description) -> io::Result<Control> {
+        let bytes = desc.elem_size as usize * desc.elems as usize;
+        let value = match desc.typ {
+            control::Type::String => {
+                let buf = self.read_payload::<u8>(desc.id, bytes)?;
+                let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+                control::Value::String(String::from_utf8_lossy(&buf[..len]).into_owned())
+            }
+            control::Type::U8 => {
+                control::Value::CompoundU8(self.read_payload::<u8>(desc.id, desc.elems as usize)?)
+            }
+            control::Type::U16 => {
+                control::Value::CompoundU16(self.read_payload::<u16>(desc.id, desc.elems as usize)?)
+            }
+            control::Type::U32 => {
+                control::Value::CompoundU32(self.read_payload::<u32>(desc.id, desc.elems as usize)?)
+            }
+            // types unknown to us are most likely vendor-specific blobs
+            control::Type::Unknown(_) => {
+                control::Value::Raw(self.read_payload::<u8>(desc.id, bytes)?)
+            }
+            _ => control::Value::CompoundPtr(self.read_payload::<u8>(desc.id, bytes)?),
+        };
+
+        Ok(Control { id: desc.id, value })
+    }
+
+    fn read_payload<T: Copy + Default>(&self, id: u32, len: usize) -> io::Result<Vec<T>> {
+        let mut buf = vec![T::default(); len];
+        unsafe {
+            let mut v4l2_ctrl = v4l2_ext_control {
+                id,
+                size: (len * mem::size_of::<T>()) as u32,
+                ..mem::zeroed()
+            };
+            v4l2_ctrl.__bindgen_anon_1.ptr = buf.as_mut_ptr() as *mut std::os::raw::c_void;
+            let mut v4l2_ctrls = v4l2_ext_controls {
+                count: 1,
+                controls: &mut v4l2_ctrl,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle().fd(),
+                v4l2::vidioc::VIDIOC_G_EXT_CTRLS,
+                &mut v4l2_ctrls as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        Ok(buf)
+    }
+
+    /// Waits until the value of a control satisfies a predicate
+    ///
+    /// The control is polled every 20 milliseconds, see
+    /// [`Device::wait_for_control_with_interval`]. Returns the first value satisfying the
+    /// predicate or fails with [`io::ErrorKind::TimedOut`].
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Control identifier, e.g. `V4L2_CID_AUTO_FOCUS_STATUS`
+    /// * `predicate` - Condition the control value has to fulfill
+    /// * `timeout` - Maximum time to wait
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use v4l::control::{focus, Value};
+    /// use v4l::device::Device;
+    /// use v4l::v4l_sys::V4L2_CID_AUTO_FOCUS_STATUS;
+    ///
+    /// let dev = Device::new(0).unwrap();
+    /// focus::trigger_auto_focus(&dev).unwrap();
+    /// dev.wait_for_control(
+    ///     V4L2_CID_AUTO_FOCUS_STATUS,
+    ///     |value| match value {
+    ///         Value::Integer(status) => {
+    ///             focus::Status::from(*status as u32).contains(focus::Status::REACHED)
+    ///         }
+    ///         _ => false,
+    ///     },
+    ///     Duration::from_secs(2),
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn wait_for_control(
+        &self,
+        id: u32,
+        predicate: impl Fn(&control::Value) -> bool,
+        timeout: Duration,
+    ) -> io::Result<control::Value> {
+        self.wait_for_control_with_interval(id, predicate, timeout, Duration::from_millis(20))
+    }
+
+    /// Waits until the value of a control satisfies a predicate, polling at the given interval
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Control identifier
+    /// * `predicate` - Condition the control value has to fulfill
+    /// * `timeout` - Maximum time to wait
+    /// * `interval` - Time between two reads of the control
+    pub fn wait_for_control_with_interval(
+        &self,
+        id: u32,
+        predicate: impl Fn(&control::Value) -> bool,
+        timeout: Duration,
+        interval: Duration,
+    ) -> io::Result<control::Value> {
+        let desc = self.query_control(id)?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let value = self.control(&desc)?.value;
+            if predicate(&value) {
+                return Ok(value);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("control {:#x} did not reach the expected value", id),
+                ));
+            }
+            thread::sleep(interval.min(deadline - now));
         }
     }
 
@@ -223,12 +667,18 @@ impl Device {
 
     /// Modifies the control values atomically
     ///
+    /// All controls are passed to the driver in a single `VIDIOC_S_EXT_CTRLS` call, so either all
+    /// of them are applied or none. If the driver reports which control it rejected, the
+    /// returned error wraps a [`control::RejectedControl`].
+    ///
     /// # Arguments
     ///
     /// * `ctrls` - Vec of the controls to be set
     pub fn set_controls(&self, ctrls: Vec<Control>) -> io::Result<()> {
         unsafe {
             let mut control_list: Vec<v4l2_ext_control> = vec![];
+            // NUL-terminated copies of string values, must outlive the ioctl
+            let mut strings: Vec<Vec<u8>> = vec![];
             let mut class: Option<u32> = None;
 
             if ctrls.is_empty() {
@@ -268,8 +718,14 @@ impl Device {
                         control.size = 0;
                     }
                     control::Value::String(ref val) => {
-                        control.__bindgen_anon_1.string = val.as_ptr() as *mut std::os::raw::c_char;
-                        control.size = val.len() as u32;
+                        // the driver expects the terminating NUL to be included in the size
+                        let mut string = Vec::with_capacity(val.len() + 1);
+                        string.extend_from_slice(val.as_bytes());
+                        string.push(0);
+                        control.__bindgen_anon_1.string =
+                            string.as_mut_ptr() as *mut std::os::raw::c_char;
+                        control.size = string.len() as u32;
+                        strings.push(string);
                     }
                     control::Value::CompoundU8(ref val) => {
                         control.__bindgen_anon_1.p_u8 = val.as_ptr() as *mut u8;
@@ -283,7 +739,7 @@ impl Device {
                         control.__bindgen_anon_1.p_u32 = val.as_ptr() as *mut u32;
                         control.size = (val.len() * std::mem::size_of::<u32>()) as u32;
                     }
-                    control::Value::CompoundPtr(ref val) => {
+                    control::Value::CompoundPtr(ref val) | control::Value::Raw(ref val) => {
                         control.__bindgen_anon_1.ptr = val.as_ptr() as *mut std::os::raw::c_void;
                         control.size = (val.len() * std::mem::size_of::<u8>()) as u32;
                     }
@@ -312,8 +768,112 @@ impl Device {
                 v4l2::vidioc::VIDIOC_S_EXT_CTRLS,
                 &mut controls as *mut _ as *mut std::os::raw::c_void,
             )
+            .map_err(|error| {
+                // an index equal to the count means the batch failed as a whole
+                let index = controls.error_idx as usize;
+                match control_list.get(index) {
+                    Some(control) => io::Error::new(
+                        error.kind(),
+                        control::RejectedControl {
+                            index,
+                            id: control.id,
+                            error,
+                        },
+                    ),
+                    None => error,
+                }
+            })
         }
     }
+
+    /// Returns the pixel rate of the sensor in pixels per second
+    ///
+    /// This is the read-only `V4L2_CID_PIXEL_RATE` control of the image process class,
+    /// exposed by raw sensors to allow for exact exposure time calculations.
+    pub fn pixel_rate(&self) -> io::Result<i64> {
+        let desc = self.probe_control(V4L2_CID_PIXEL_RATE, "V4L2_CID_PIXEL_RATE")?;
+        match self.control(&desc)?.value {
+            control::Value::Integer(rate) => Ok(rate),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unexpected V4L2_CID_PIXEL_RATE value type",
+            )),
+        }
+    }
+
+    /// Returns the link frequencies supported by the sensor in Hz
+    ///
+    /// `V4L2_CID_LINK_FREQ` is an integer menu, so each frequency is paired with its menu
+    /// index. The index is what has to be passed to [`Device::set_control`] to select a
+    /// frequency (if the control is writable at all).
+    pub fn link_freq_options(&self) -> io::Result<Vec<(u32, i64)>> {
+        let desc = self.probe_control(V4L2_CID_LINK_FREQ, "V4L2_CID_LINK_FREQ")?;
+        Ok(desc
+            .items
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(index, item)| match item {
+                control::MenuItem::Value(freq) => Some((index, freq)),
+                control::MenuItem::Name(_) => None,
+            })
+            .collect())
+    }
+
+    /// Subscribes to an event type
+    ///
+    /// Pending events are signaled as exceptional condition (`POLLPRI`) on the file descriptor.
+    ///
+    /// # Arguments
+    ///
+    /// * `typ` - Event type
+    /// * `id` - Event source, e.g. a control id for [`event::Type::Ctrl`] (0 for most types)
+    pub fn subscribe_event(&self, typ: event::Type, id: u32) -> io::Result<()> {
+        event::subscribe(self.handle().fd(), typ, id)
+    }
+
+    /// Unsubscribes from an event type
+    ///
+    /// # Arguments
+    ///
+    /// * `typ` - Event type, [`event::Type::All`] to unsubscribe from all events
+    /// * `id` - Event source
+    pub fn unsubscribe_event(&self, typ: event::Type, id: u32) -> io::Result<()> {
+        event::unsubscribe(self.handle().fd(), typ, id)
+    }
+
+    /// Returns the oldest pending event or `None` if there is none
+    pub fn dequeue_event(&self) -> io::Result<Option<Event>> {
+        event::dequeue(self.handle().fd())
+    }
+
+    /// Returns an iterator which waits for events to arrive
+    ///
+    /// Only events of subscribed types are delivered. To follow controls which are changed by
+    /// other processes or by the driver, subscribe to [`event::Type::Ctrl`] with the control id
+    /// first, the events then carry the new state as [`event::Payload::Ctrl`].
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Iteration ends once no event arrived for this long, `None` waits forever
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::event::{Payload, Type};
+    /// use v4l::prelude::*;
+    /// use v4l::v4l_sys::V4L2_CID_EXPOSURE_ABSOLUTE;
+    ///
+    /// let dev = Device::new(0).unwrap();
+    /// dev.subscribe_event(Type::Ctrl, V4L2_CID_EXPOSURE_ABSOLUTE).unwrap();
+    /// for event in dev.events(None) {
+    ///     if let Payload::Ctrl(ctrl) = event.unwrap().payload {
+    ///         println!("exposure is now {}", ctrl.value);
+    ///     }
+    /// }
+    /// ```
+    pub fn events(&self, timeout: Option<Duration>) -> event::Events<'_> {
+        event::Events::new(self, timeout)
+    }
 }
 
 impl io::Read for Device {
@@ -355,16 +915,97 @@ impl io::Write for Device {
     }
 }
 
+/// Gives access to the file descriptor, e.g. to register it with `AsyncFd` or mio
+///
+/// The descriptor is still owned by the device and closed once the device and all streams
+/// created from it are dropped. It must not be closed by the caller.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::os::unix::io::AsRawFd;
+/// use v4l::device::Device;
+///
+/// let dev = Device::with_path_nonblocking("/dev/video0").unwrap();
+/// println!("fd: {}", dev.as_raw_fd());
+/// ```
+impl AsRawFd for Device {
+    fn as_raw_fd(&self) -> RawFd {
+        self.handle.fd()
+    }
+}
+
+impl AsFd for Device {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.handle.as_fd()
+    }
+}
+
+/// Video device node found by [`enumerate`]
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    /// Device node path, e.g. /dev/video0
+    pub path: PathBuf,
+    /// Driver name, e.g. uvc for usb video class devices
+    pub driver: String,
+    /// Card name
+    pub card: String,
+    /// Capability flags of the device node
+    pub capabilities: capability::Flags,
+}
+
+/// Returns information about all video device nodes of the system
+///
+/// Each /dev/video* node is opened and queried. Nodes which cannot be opened (e.g. missing
+/// permissions) or which do not answer VIDIOC_QUERYCAP are skipped. The result is sorted by
+/// node index.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::device;
+/// for info in device::enumerate().unwrap() {
+///     println!("{}: {} ({})", info.path.display(), info.card, info.driver);
+/// }
+/// ```
+pub fn enumerate() -> io::Result<Vec<DeviceInfo>> {
+    // fail early if the device directory itself is not accessible
+    std::fs::read_dir("/dev")?;
+
+    let mut devices: Vec<DeviceInfo> = context::enum_devices()
+        .iter()
+        .filter_map(|node| {
+            let dev = Device::with_path(node.path()).ok()?;
+            let caps = dev.query_caps().ok()?;
+            Some(DeviceInfo {
+                path: node.path().to_path_buf(),
+                driver: caps.driver,
+                card: caps.card,
+                capabilities: caps.capabilities,
+            })
+        })
+        .collect();
+
+    // /dev/video10 must come after /dev/video2
+    devices.sort_by(|a, b| {
+        let a = a.path.as_os_str();
+        let b = b.path.as_os_str();
+        (a.len(), a).cmp(&(b.len(), b))
+    });
+    Ok(devices)
+}
+
 /// Device handle for low-level access.
 ///
 /// Acquiring a handle facilitates (possibly mutating) interactions with the device.
 pub struct Handle {
     fd: std::os::raw::c_int,
+    nonblocking: bool,
 }
 
 impl Handle {
-    fn new(fd: std::os::raw::c_int) -> Self {
-        Self { fd }
+    fn new(fd: std::os::raw::c_int, nonblocking: bool) -> Self {
+        Self { fd, nonblocking }
     }
 
     /// Returns the raw file descriptor
@@ -372,6 +1013,11 @@ impl Handle {
         self.fd
     }
 
+    /// Returns true if streams must not wait for buffers, see [`Device::with_path_nonblocking`]
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+
     /// Polls the file descriptor for I/O events
     ///
     /// # Arguments
@@ -405,6 +1051,19 @@ impl Handle {
     }
 }
 
+impl AsRawFd for Handle {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl AsFd for Handle {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // the descriptor stays open until the handle is dropped
+        unsafe { BorrowedFd::borrow_raw(self.fd) }
+    }
+}
+
 impl Drop for Handle {
     fn drop(&mut self) {
         v4l2::close(self.fd).unwrap();
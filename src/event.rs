@@ -0,0 +1,316 @@
+use std::{convert::TryInto, fmt, io, mem, time::Duration};
+
+use crate::control;
+use crate::device::Device;
+use crate::v4l2;
+use crate::v4l_sys::*;
+
+/// Event type
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Type {
+    /// All events (only valid for unsubscribing)
+    All,
+    /// Vertical sync
+    Vsync,
+    /// End of stream
+    Eos,
+    /// Control value or properties changed
+    Ctrl,
+    /// Frame exposure started
+    FrameSync,
+    /// Source parameters (e.g. the resolution) changed
+    SourceChange,
+    /// Motion detection state changed
+    MotionDet,
+
+    Unknown(u32),
+}
+
+impl From<u32> for Type {
+    fn from(repr: u32) -> Self {
+        match repr {
+            0 => Self::All,
+            1 => Self::Vsync,
+            2 => Self::Eos,
+            3 => Self::Ctrl,
+            4 => Self::FrameSync,
+            5 => Self::SourceChange,
+            6 => Self::MotionDet,
+            repr => Self::Unknown(repr),
+        }
+    }
+}
+
+impl From<Type> for u32 {
+    fn from(typ: Type) -> Self {
+        match typ {
+            Type::All => 0,
+            Type::Vsync => 1,
+            Type::Eos => 2,
+            Type::Ctrl => 3,
+            Type::FrameSync => 4,
+            Type::SourceChange => 5,
+            Type::MotionDet => 6,
+            Type::Unknown(repr) => repr,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+    pub struct SourceChanges: u32 {
+        /// The resolution of the source changed
+        const RESOLUTION    = 0x0001;
+    }
+}
+
+impl From<u32> for SourceChanges {
+    fn from(changes: u32) -> Self {
+        Self::from_bits_retain(changes)
+    }
+}
+
+impl From<SourceChanges> for u32 {
+    fn from(changes: SourceChanges) -> Self {
+        changes.bits()
+    }
+}
+
+impl fmt::Display for SourceChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+    pub struct CtrlChanges: u32 {
+        /// The value of the control changed
+        const VALUE         = 0x0001;
+        /// The flags of the control changed
+        const FLAGS         = 0x0002;
+        /// The range (minimum, maximum, step or default) of the control changed
+        const RANGE         = 0x0004;
+        /// The dimensions of an array control changed
+        const DIMENSIONS    = 0x0008;
+    }
+}
+
+impl From<u32> for CtrlChanges {
+    fn from(changes: u32) -> Self {
+        Self::from_bits_retain(changes)
+    }
+}
+
+impl From<CtrlChanges> for u32 {
+    fn from(changes: CtrlChanges) -> Self {
+        changes.bits()
+    }
+}
+
+impl fmt::Display for CtrlChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// State of a control after it changed
+///
+/// The event always carries the complete state, `changes` tells which parts are new.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CtrlEvent {
+    /// What changed
+    pub changes: CtrlChanges,
+    /// Type of the control
+    pub typ: control::Type,
+    /// Current value (not valid for payload controls)
+    pub value: i64,
+    /// Current flags
+    pub flags: control::Flags,
+    /// Minimum value, inclusive
+    pub minimum: i32,
+    /// Maximum value, inclusive
+    pub maximum: i32,
+    /// Step size
+    pub step: i32,
+    /// Default value
+    pub default: i32,
+}
+
+impl From<v4l2_event_ctrl> for CtrlEvent {
+    fn from(ctrl: v4l2_event_ctrl) -> Self {
+        let typ = control::Type::from(ctrl.type_);
+        let value = unsafe {
+            match typ {
+                control::Type::Integer64 => ctrl.__bindgen_anon_1.value64,
+                _ => ctrl.__bindgen_anon_1.value as i64,
+            }
+        };
+
+        CtrlEvent {
+            changes: CtrlChanges::from(ctrl.changes),
+            typ,
+            value,
+            flags: control::Flags::from(ctrl.flags),
+            minimum: ctrl.minimum,
+            maximum: ctrl.maximum,
+            step: ctrl.step,
+            default: ctrl.default_value,
+        }
+    }
+}
+
+/// Type specific event data
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Payload {
+    /// Changed source parameters
+    SourceChange(SourceChanges),
+    /// Sequence number of the frame being exposed
+    FrameSync(u32),
+    /// New state of a control, the control id is the event id
+    Ctrl(CtrlEvent),
+    /// Data of other event types is not decoded
+    Other,
+}
+
+/// Event dequeued from a device
+#[derive(Debug, Copy, Clone)]
+pub struct Event {
+    /// Event type
+    pub typ: Type,
+    /// Event source, e.g. the control id for control events or the input for source changes
+    pub id: u32,
+    /// Number of events still pending
+    pub pending: u32,
+    /// Event sequence number, counting the events of the device
+    pub sequence: u32,
+    /// Type specific data
+    pub payload: Payload,
+}
+
+impl From<v4l2_event> for Event {
+    fn from(event: v4l2_event) -> Self {
+        let typ = Type::from(event.type_);
+        let payload = unsafe {
+            match typ {
+                Type::SourceChange => {
+                    Payload::SourceChange(SourceChanges::from(event.u.src_change.changes))
+                }
+                Type::FrameSync => Payload::FrameSync(event.u.frame_sync.frame_sequence),
+                Type::Ctrl => Payload::Ctrl(CtrlEvent::from(event.u.ctrl)),
+                _ => Payload::Other,
+            }
+        };
+
+        Event {
+            typ,
+            id: event.id,
+            pending: event.pending,
+            sequence: event.sequence,
+            payload,
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (id: {}, sequence: {})",
+            self.typ, self.id, self.sequence
+        )
+    }
+}
+
+pub(crate) fn subscribe(fd: std::os::raw::c_int, typ: Type, id: u32) -> io::Result<()> {
+    unsafe {
+        let mut v4l2_sub = v4l2_event_subscription {
+            type_: typ.into(),
+            id,
+            ..mem::zeroed()
+        };
+        v4l2::ioctl(
+            fd,
+            v4l2::vidioc::VIDIOC_SUBSCRIBE_EVENT,
+            &mut v4l2_sub as *mut _ as *mut std::os::raw::c_void,
+        )
+    }
+}
+
+pub(crate) fn unsubscribe(fd: std::os::raw::c_int, typ: Type, id: u32) -> io::Result<()> {
+    unsafe {
+        let mut v4l2_sub = v4l2_event_subscription {
+            type_: typ.into(),
+            id,
+            ..mem::zeroed()
+        };
+        v4l2::ioctl(
+            fd,
+            v4l2::vidioc::VIDIOC_UNSUBSCRIBE_EVENT,
+            &mut v4l2_sub as *mut _ as *mut std::os::raw::c_void,
+        )
+    }
+}
+
+/// Returns `None` if no event is pending
+pub(crate) fn dequeue(fd: std::os::raw::c_int) -> io::Result<Option<Event>> {
+    unsafe {
+        let mut v4l2_event: v4l2_event = mem::zeroed();
+        match v4l2::ioctl(
+            fd,
+            v4l2::vidioc::VIDIOC_DQEVENT,
+            &mut v4l2_event as *mut _ as *mut std::os::raw::c_void,
+        ) {
+            Ok(_) => Ok(Some(Event::from(v4l2_event))),
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Blocking iterator over the events of a device
+///
+/// Created by [`Device::events`]. Yields events as they arrive and ends once no event arrived
+/// within the timeout.
+pub struct Events<'a> {
+    dev: &'a Device,
+    timeout: Option<Duration>,
+}
+
+impl<'a> Events<'a> {
+    pub(crate) fn new(dev: &'a Device, timeout: Option<Duration>) -> Self {
+        Events { dev, timeout }
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout.as_millis().try_into().unwrap_or(i32::MAX),
+            None => -1,
+        };
+
+        loop {
+            match dequeue(self.dev.handle().fd()) {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+
+            // pending events are signaled as exceptional condition
+            match self.dev.handle().poll(libc::POLLPRI, timeout) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
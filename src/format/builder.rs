@@ -0,0 +1,130 @@
+use std::io;
+
+use crate::buffer::Type;
+use crate::device::Device;
+use crate::format::{FieldOrder, Format, FourCC};
+use crate::video::{Capture, Output};
+
+/// Negotiates a format with the driver
+///
+/// Only the properties set on the builder are changed, all others are taken from the format
+/// currently in use. The driver adjusts the request to what the hardware supports, so the
+/// format returned by [`FormatBuilder::apply`] should be used to allocate buffers.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::format::{FieldOrder, FormatBuilder};
+/// use v4l::prelude::*;
+/// use v4l::FourCC;
+///
+/// let dev = Device::new(0).unwrap();
+/// let fmt = FormatBuilder::new(&dev, Type::VideoCapture)
+///     .fourcc(FourCC::new(b"YUYV"))
+///     .size(1280, 720)
+///     .field(FieldOrder::Progressive)
+///     .apply()
+///     .unwrap();
+/// println!("negotiated {}x{} {}", fmt.width, fmt.height, fmt.fourcc);
+/// ```
+pub struct FormatBuilder<'d> {
+    dev: &'d Device,
+    buf_type: Type,
+    fourcc: Option<FourCC>,
+    size: Option<(u32, u32)>,
+    field: Option<FieldOrder>,
+}
+
+impl<'d> FormatBuilder<'d> {
+    /// Returns a builder which keeps the current format unless told otherwise
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Device to configure
+    /// * `buf_type` - Type of the buffers, [`Type::VideoCapture`] or [`Type::VideoOutput`]
+    pub fn new(dev: &'d Device, buf_type: Type) -> Self {
+        FormatBuilder {
+            dev,
+            buf_type,
+            fourcc: None,
+            size: None,
+            field: None,
+        }
+    }
+
+    /// Sets the pixelformat
+    ///
+    /// # Arguments
+    ///
+    /// * `fourcc` - Four character code of the pixelformat
+    pub fn fourcc(mut self, fourcc: FourCC) -> Self {
+        self.fourcc = Some(fourcc);
+        self
+    }
+
+    /// Sets the frame size
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width in pixels
+    /// * `height` - Height in pixels
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    /// Sets the field order
+    ///
+    /// Interlaced sources (e.g. analog capture cards) deliver fields instead of frames, see
+    /// [`FieldOrder`]. [`FieldOrder::Any`] lets the driver choose.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - Field order
+    pub fn field(mut self, field: FieldOrder) -> Self {
+        self.field = Some(field);
+        self
+    }
+
+    /// Issues `VIDIOC_S_FMT` and returns the format accepted by the driver
+    ///
+    /// Fails with [`io::ErrorKind::Unsupported`] for buffer types other than video capture and
+    /// output.
+    pub fn apply(self) -> io::Result<Format> {
+        let mut fmt = match self.buf_type {
+            Type::VideoCapture => Capture::format(self.dev)?,
+            Type::VideoOutput => Output::format(self.dev)?,
+            _ => return Err(self.unsupported()),
+        };
+
+        if let Some(fourcc) = self.fourcc {
+            fmt.fourcc = fourcc;
+        }
+        if let Some((width, height)) = self.size {
+            fmt.width = width;
+            fmt.height = height;
+        }
+        if let Some(field) = self.field {
+            fmt.field_order = field;
+        }
+        if self.fourcc.is_some() || self.size.is_some() {
+            // let the driver compute the layout for the new format
+            fmt.stride = 0;
+            fmt.size = 0;
+        }
+
+        match self.buf_type {
+            Type::VideoCapture => Capture::set_format(self.dev, &fmt),
+            Type::VideoOutput => Output::set_format(self.dev, &fmt),
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    fn unsupported(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("cannot set the format of {:?} buffers", self.buf_type),
+        )
+    }
+}
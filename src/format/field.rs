@@ -1,7 +1,7 @@
 use std::convert::TryFrom;
 use std::fmt;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u32)]
 /// Represents how fields are interlaced (if they are)
 pub enum FieldOrder {
@@ -1,4 +1,5 @@
-use std::{fmt, str};
+use std::convert::TryFrom;
+use std::{error, fmt, str};
 
 #[derive(Debug, Default, Copy, Clone, Eq)]
 /// Four character code representing a pixelformat
@@ -7,6 +8,33 @@ pub struct FourCC {
 }
 
 impl FourCC {
+    /// Motion-JPEG
+    pub const MJPG: FourCC = FourCC::new(b"MJPG");
+    /// Packed YUV 4:2:2, Y0 U Y1 V
+    pub const YUYV: FourCC = FourCC::new(b"YUYV");
+    /// Packed YUV 4:2:2, U Y0 V Y1
+    pub const UYVY: FourCC = FourCC::new(b"UYVY");
+    /// Semi-planar YUV 4:2:0, Y plane followed by interleaved U/V
+    pub const NV12: FourCC = FourCC::new(b"NV12");
+    /// Semi-planar YUV 4:2:0, Y plane followed by interleaved V/U
+    pub const NV21: FourCC = FourCC::new(b"NV21");
+    /// Planar YUV 4:2:0 (I420)
+    pub const YU12: FourCC = FourCC::new(b"YU12");
+    /// Planar YVU 4:2:0
+    pub const YV12: FourCC = FourCC::new(b"YV12");
+    /// Packed RGB, 8 bits per channel
+    pub const RGB3: FourCC = FourCC::new(b"RGB3");
+    /// Packed BGR, 8 bits per channel
+    pub const BGR3: FourCC = FourCC::new(b"BGR3");
+    /// Packed RGBA, 8 bits per channel
+    pub const AB24: FourCC = FourCC::new(b"AB24");
+    /// 8 bit greyscale
+    pub const GREY: FourCC = FourCC::new(b"GREY");
+    /// H.264 elementary stream with start codes
+    pub const H264: FourCC = FourCC::new(b"H264");
+    /// HEVC elementary stream with start codes
+    pub const HEVC: FourCC = FourCC::new(b"HEVC");
+
     #[allow(clippy::trivially_copy_pass_by_ref)]
     /// Returns a pixelformat as four character code
     ///
@@ -20,7 +48,7 @@ impl FourCC {
     /// use v4l::format::FourCC;
     /// let fourcc = FourCC::new(b"YUYV");
     /// ```
-    pub fn new(repr: &[u8; 4]) -> FourCC {
+    pub const fn new(repr: &[u8; 4]) -> FourCC {
         FourCC { repr: *repr }
     }
 
@@ -48,6 +76,43 @@ impl fmt::Display for FourCC {
     }
 }
 
+/// Parses a four character code
+///
+/// Exactly four ASCII characters are expected, codes with trailing spaces must be spelled with
+/// them (e.g. `"Y10 "`).
+///
+/// # Example
+///
+/// ```
+/// use v4l::format::FourCC;
+/// let fourcc: FourCC = "YUYV".parse().unwrap();
+/// assert_eq!(FourCC::YUYV, fourcc);
+/// ```
+impl str::FromStr for FourCC {
+    type Err = ParseFourCCError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match <[u8; 4]>::try_from(s.as_bytes()) {
+            Ok(repr) if repr.iter().all(|b| b.is_ascii_graphic() || *b == b' ') => {
+                Ok(FourCC { repr })
+            }
+            _ => Err(ParseFourCCError(s.to_string())),
+        }
+    }
+}
+
+/// Error returned when a string is not a valid four character code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFourCCError(pub String);
+
+impl fmt::Display for ParseFourCCError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid four character code: {:?}", self.0)
+    }
+}
+
+impl error::Error for ParseFourCCError {}
+
 impl PartialEq for FourCC {
     fn eq(&self, other: &FourCC) -> bool {
         self.repr.iter().zip(other.repr.iter()).all(|(a, b)| a == b)
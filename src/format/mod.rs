@@ -2,6 +2,9 @@ use std::{convert::TryFrom, fmt, mem};
 
 use crate::v4l_sys::*;
 
+pub mod builder;
+pub use builder::FormatBuilder;
+
 pub mod colorspace;
 pub use colorspace::Colorspace;
 
@@ -136,6 +139,29 @@ impl From<v4l2_pix_format> for Format {
     }
 }
 
+// Multi-planar formats are described by a single Format: the stride is the one of the first
+// plane and the size is the sum of all plane sizes.
+impl From<v4l2_pix_format_mplane> for Format {
+    fn from(fmt: v4l2_pix_format_mplane) -> Self {
+        let plane_fmt = fmt.plane_fmt;
+        let planes = &plane_fmt[..(fmt.num_planes as usize).min(plane_fmt.len())];
+        Self {
+            width: fmt.width,
+            height: fmt.height,
+            fourcc: FourCC::from(fmt.pixelformat),
+            field_order: FieldOrder::try_from(fmt.field).expect("Invalid field order"),
+            stride: planes.first().map_or(0, |plane| plane.bytesperline),
+            size: planes.iter().map(|plane| plane.sizeimage).sum(),
+            flags: Flags::from(u32::from(fmt.flags)),
+            colorspace: Colorspace::try_from(fmt.colorspace).expect("Invalid colorspace"),
+            quantization: Quantization::try_from(u32::from(fmt.quantization))
+                .expect("Invalid quantization"),
+            transfer: TransferFunction::try_from(u32::from(fmt.xfer_func))
+                .expect("Invalid transfer function"),
+        }
+    }
+}
+
 impl From<Format> for v4l2_pix_format {
     fn from(format: Format) -> Self {
         Self {
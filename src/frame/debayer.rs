@@ -0,0 +1,339 @@
+//! Demosaicing of raw Bayer frames
+//!
+//! Raw sensors output a single color sample per pixel, arranged in a 2x2 pattern. The
+//! [`demosaic`] function interpolates the missing samples (bilinear) and writes RGB24 frames.
+//!
+//! Supported are the unpacked formats with 8 bits per sample as well as 10, 12 and 16 bits per
+//! sample stored in little endian 16 bit words. The MIPI packed formats are not supported.
+
+use std::{fmt, io};
+
+use crate::format::FourCC;
+
+/// Order of the color samples in the top left 2x2 block of a frame
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BayerPattern {
+    Rggb,
+    Bggr,
+    Grbg,
+    Gbrg,
+}
+
+impl fmt::Display for BayerPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Number of significant bits per sample
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BitDepth {
+    Bits8,
+    Bits10,
+    Bits12,
+    Bits16,
+}
+
+impl BitDepth {
+    /// Returns the number of significant bits
+    pub fn bits(&self) -> u32 {
+        match self {
+            BitDepth::Bits8 => 8,
+            BitDepth::Bits10 => 10,
+            BitDepth::Bits12 => 12,
+            BitDepth::Bits16 => 16,
+        }
+    }
+
+    /// Returns the number of bytes a single sample occupies in the frame
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            BitDepth::Bits8 => 1,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for BitDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bit", self.bits())
+    }
+}
+
+/// Returns the Bayer pattern and bit depth of a raw pixelformat
+///
+/// Returns `None` for non-Bayer and packed Bayer formats.
+///
+/// # Arguments
+///
+/// * `fourcc` - Pixelformat
+///
+/// # Example
+///
+/// ```
+/// use v4l::format::FourCC;
+/// use v4l::frame::debayer::{fourcc_to_bayer, BayerPattern, BitDepth};
+///
+/// let (pattern, depth) = fourcc_to_bayer(FourCC::new(b"RG10")).unwrap();
+/// assert_eq!(pattern, BayerPattern::Rggb);
+/// assert_eq!(depth, BitDepth::Bits10);
+/// ```
+pub fn fourcc_to_bayer(fourcc: FourCC) -> Option<(BayerPattern, BitDepth)> {
+    use BayerPattern::*;
+    use BitDepth::*;
+
+    match &fourcc.repr {
+        b"RGGB" => Some((Rggb, Bits8)),
+        b"BA81" => Some((Bggr, Bits8)),
+        b"GRBG" => Some((Grbg, Bits8)),
+        b"GBRG" => Some((Gbrg, Bits8)),
+        b"RG10" => Some((Rggb, Bits10)),
+        b"BG10" => Some((Bggr, Bits10)),
+        b"BA10" => Some((Grbg, Bits10)),
+        b"GB10" => Some((Gbrg, Bits10)),
+        b"RG12" => Some((Rggb, Bits12)),
+        b"BG12" => Some((Bggr, Bits12)),
+        b"BA12" => Some((Grbg, Bits12)),
+        b"GB12" => Some((Gbrg, Bits12)),
+        b"RG16" => Some((Rggb, Bits16)),
+        b"BYR2" => Some((Bggr, Bits16)),
+        b"GR16" => Some((Grbg, Bits16)),
+        b"GB16" => Some((Gbrg, Bits16)),
+        _ => None,
+    }
+}
+
+const RED: usize = 0;
+const GREEN: usize = 1;
+const BLUE: usize = 2;
+
+impl BayerPattern {
+    /// Returns the color channel of the pixel at the given position
+    fn channel(&self, x: usize, y: usize) -> usize {
+        let block = match self {
+            BayerPattern::Rggb => [RED, GREEN, GREEN, BLUE],
+            BayerPattern::Bggr => [BLUE, GREEN, GREEN, RED],
+            BayerPattern::Grbg => [GREEN, RED, BLUE, GREEN],
+            BayerPattern::Gbrg => [GREEN, BLUE, RED, GREEN],
+        };
+        block[(y & 1) * 2 + (x & 1)]
+    }
+}
+
+/// Unpacks the samples of a frame, dropping any bits beyond the bit depth
+fn unpack(src: &[u8], len: usize, depth: BitDepth) -> Vec<u16> {
+    match depth {
+        BitDepth::Bits8 => src[..len].iter().map(|&s| u16::from(s)).collect(),
+        _ => {
+            let mask = (u32::MAX >> (32 - depth.bits())) as u16;
+            src[..len * 2]
+                .chunks_exact(2)
+                .map(|s| u16::from_le_bytes([s[0], s[1]]) & mask)
+                .collect()
+        }
+    }
+}
+
+/// Mirrors a coordinate at the frame borders
+///
+/// Mirroring by one pixel keeps the position within the Bayer pattern intact, so edge pixels
+/// are interpolated from samples of the correct color.
+fn reflect(pos: isize, len: usize) -> usize {
+    if pos < 0 {
+        1
+    } else if pos as usize >= len {
+        len - 2
+    } else {
+        pos as usize
+    }
+}
+
+/// Converts a raw Bayer frame to RGB24 using bilinear interpolation
+///
+/// Every missing color sample is the mean of the neighbouring samples of that color in the
+/// surrounding 3x3 block. The frame is mirrored at its borders, so edge pixels are interpolated
+/// as well. Samples with more than 8 bits are scaled down after interpolation.
+///
+/// # Arguments
+///
+/// * `src` - Raw frame, without any padding at the end of the lines
+/// * `width` - Width of the frame in pixels, at least 2
+/// * `height` - Height of the frame in pixels, at least 2
+/// * `pattern` - Bayer pattern of the frame
+/// * `depth` - Bit depth of the samples
+/// * `dst` - Output buffer, at least `width * height * 3` bytes
+///
+/// # Example
+///
+/// ```
+/// use v4l::frame::debayer::{demosaic, BayerPattern, BitDepth};
+///
+/// let src = vec![0u8; 4 * 4];
+/// let mut dst = vec![0u8; 4 * 4 * 3];
+/// demosaic(&src, 4, 4, BayerPattern::Rggb, BitDepth::Bits8, &mut dst).unwrap();
+/// ```
+pub fn demosaic(
+    src: &[u8],
+    width: u32,
+    height: u32,
+    pattern: BayerPattern,
+    depth: BitDepth,
+    dst: &mut [u8],
+) -> io::Result<()> {
+    let (width, height) = (width as usize, height as usize);
+    if width < 2 || height < 2 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame size {}x{} is too small", width, height),
+        ));
+    }
+    let pixels = width * height;
+    if src.len() < pixels * depth.bytes_per_sample() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "source buffer too small for {}x{} ({}): {} bytes",
+                width,
+                height,
+                depth,
+                src.len()
+            ),
+        ));
+    }
+    if dst.len() < pixels * 3 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "destination buffer too small for {}x{} RGB24: {} bytes",
+                width,
+                height,
+                dst.len()
+            ),
+        ));
+    }
+
+    let samples = unpack(src, pixels, depth);
+    let shift = depth.bits() - 8;
+
+    for y in 0..height {
+        for x in 0..width {
+            let own = pattern.channel(x, y);
+            let mut sum = [0u32; 3];
+            let mut count = [0u32; 3];
+
+            for dy in -1..=1isize {
+                for dx in -1..=1isize {
+                    let sx = reflect(x as isize + dx, width);
+                    let sy = reflect(y as isize + dy, height);
+                    let channel = pattern.channel(sx, sy);
+                    // the own sample is exact, no need to mix in the neighbours
+                    if channel == own && (dx != 0 || dy != 0) {
+                        continue;
+                    }
+                    sum[channel] += u32::from(samples[sy * width + sx]);
+                    count[channel] += 1;
+                }
+            }
+
+            let out = &mut dst[(y * width + x) * 3..][..3];
+            for channel in 0..3 {
+                let mean = (sum[channel] + count[channel] / 2) / count[channel];
+                out[channel] = (mean >> shift).min(255) as u8;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a frame in which each color channel has a constant value
+    fn flat(width: usize, height: usize, pattern: BayerPattern, rgb: [u16; 3]) -> Vec<u16> {
+        let mut frame = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                frame.push(rgb[pattern.channel(x, y)]);
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn test_fourcc_to_bayer() {
+        assert_eq!(
+            fourcc_to_bayer(FourCC::new(b"BA81")),
+            Some((BayerPattern::Bggr, BitDepth::Bits8))
+        );
+        assert_eq!(
+            fourcc_to_bayer(FourCC::new(b"BA12")),
+            Some((BayerPattern::Grbg, BitDepth::Bits12))
+        );
+        assert_eq!(fourcc_to_bayer(FourCC::new(b"YUYV")), None);
+    }
+
+    #[test]
+    fn test_demosaic_flat() {
+        for pattern in [
+            BayerPattern::Rggb,
+            BayerPattern::Bggr,
+            BayerPattern::Grbg,
+            BayerPattern::Gbrg,
+        ] {
+            let src: Vec<u8> = flat(6, 4, pattern, [200, 100, 50])
+                .into_iter()
+                .map(|s| s as u8)
+                .collect();
+            let mut dst = vec![0; 6 * 4 * 3];
+            demosaic(&src, 6, 4, pattern, BitDepth::Bits8, &mut dst).unwrap();
+            // edge pixels included
+            for rgb in dst.chunks_exact(3) {
+                assert_eq!(rgb, [200, 100, 50], "{}", pattern);
+            }
+        }
+    }
+
+    #[test]
+    fn test_demosaic_10bit() {
+        let mut src: Vec<u8> = flat(4, 4, BayerPattern::Rggb, [1023, 512, 4])
+            .into_iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        // garbage in the unused high bits is ignored
+        src[1] |= 0xf0;
+        let mut dst = vec![0; 4 * 4 * 3];
+        demosaic(&src, 4, 4, BayerPattern::Rggb, BitDepth::Bits10, &mut dst).unwrap();
+        for rgb in dst.chunks_exact(3) {
+            assert_eq!(rgb, [255, 128, 1]);
+        }
+    }
+
+    #[test]
+    fn test_demosaic_interpolation() {
+        // a single bright red sample spreads to its green and blue neighbours
+        let mut src = vec![0u8; 4 * 4];
+        src[2 * 4 + 2] = 200;
+        let mut dst = vec![0; 4 * 4 * 3];
+        demosaic(&src, 4, 4, BayerPattern::Rggb, BitDepth::Bits8, &mut dst).unwrap();
+
+        let red = |x: usize, y: usize| dst[(y * 4 + x) * 3];
+        assert_eq!(red(2, 2), 200);
+        // green pixels with two red neighbours
+        assert_eq!(red(1, 2), 100);
+        assert_eq!(red(2, 1), 100);
+        // blue pixel with four red neighbours
+        assert_eq!(red(1, 1), 50);
+        // the right border mirrors the sample
+        assert_eq!(red(3, 2), 200);
+    }
+
+    #[test]
+    fn test_demosaic_short_buffer() {
+        let src = vec![0u8; 4 * 4];
+        let mut dst = vec![0; 4 * 4 * 3];
+        let err = demosaic(&src, 4, 4, BayerPattern::Rggb, BitDepth::Bits16, &mut dst).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,6 @@
+//! Processing of captured frame data
+//!
+//! The helpers in this module work on plain byte slices, so they can be used with any stream
+//! implementation (or with recorded frames).
+
+pub mod debayer;
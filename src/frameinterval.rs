@@ -22,9 +22,15 @@ impl fmt::Display for FrameInterval {
 }
 
 #[derive(Debug)]
+/// Frame intervals supported for a pixel format and frame size
 pub enum FrameIntervalEnum {
+    /// A single supported frame interval, drivers report one entry per interval
     Discrete(Fraction),
+    /// A range of supported frame intervals, reported as the only entry
     Stepwise(Stepwise),
+    /// A range of supported frame intervals with the smallest possible step, reported as the
+    /// only entry
+    Continuous(Stepwise),
 }
 
 impl fmt::Display for FrameIntervalEnum {
@@ -32,6 +38,7 @@ impl fmt::Display for FrameIntervalEnum {
         match self {
             FrameIntervalEnum::Discrete(val) => write!(f, "Discrete({})", val)?,
             FrameIntervalEnum::Stepwise(val) => write!(f, "Stepwise({})", val)?,
+            FrameIntervalEnum::Continuous(val) => write!(f, "Continuous({})", val)?,
         }
 
         Ok(())
@@ -48,14 +55,12 @@ impl TryFrom<v4l2_frmivalenum> for FrameIntervalEnum {
                 v4l_sys::v4l2_frmivaltypes_V4L2_FRMIVAL_TYPE_DISCRETE => Ok(
                     FrameIntervalEnum::Discrete(Fraction::from(desc.__bindgen_anon_1.discrete)),
                 ),
-                v4l_sys::v4l2_frmivaltypes_V4L2_FRMIVAL_TYPE_CONTINUOUS
-                | v4l_sys::v4l2_frmivaltypes_V4L2_FRMIVAL_TYPE_STEPWISE => Ok({
-                    FrameIntervalEnum::Stepwise(Stepwise {
-                        min: Fraction::from(desc.__bindgen_anon_1.stepwise.min),
-                        max: Fraction::from(desc.__bindgen_anon_1.stepwise.max),
-                        step: Fraction::from(desc.__bindgen_anon_1.stepwise.step),
-                    })
-                }),
+                v4l_sys::v4l2_frmivaltypes_V4L2_FRMIVAL_TYPE_STEPWISE => Ok(
+                    FrameIntervalEnum::Stepwise(Stepwise::from(desc.__bindgen_anon_1.stepwise)),
+                ),
+                v4l_sys::v4l2_frmivaltypes_V4L2_FRMIVAL_TYPE_CONTINUOUS => Ok(
+                    FrameIntervalEnum::Continuous(Stepwise::from(desc.__bindgen_anon_1.stepwise)),
+                ),
                 typ => Err(format!("Unknown frame size type: {}", typ)),
             }
         }
@@ -72,6 +77,16 @@ pub struct Stepwise {
     pub step: Fraction,
 }
 
+impl From<v4l2_frmival_stepwise> for Stepwise {
+    fn from(stepwise: v4l2_frmival_stepwise) -> Self {
+        Stepwise {
+            min: Fraction::from(stepwise.min),
+            max: Fraction::from(stepwise.max),
+            step: Fraction::from(stepwise.step),
+        }
+    }
+}
+
 impl fmt::Display for Stepwise {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} - {} with step {}", self.min, self.max, self.step)?;
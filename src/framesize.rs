@@ -22,15 +22,19 @@ impl fmt::Display for FrameSize {
 
 #[derive(Debug)]
 pub enum FrameSizeEnum {
+    /// A single supported size
     Discrete(Discrete),
+    /// A range of sizes, the width and height must be a multiple of the step sizes
     Stepwise(Stepwise),
+    /// A range of sizes with a step size of one pixel
+    Continuous(Stepwise),
 }
 
 impl FrameSizeEnum {
     pub fn to_discrete(self) -> impl IntoIterator<Item = Discrete> {
         match self {
             Self::Discrete(discrete) => vec![discrete],
-            Self::Stepwise(stepwise) => {
+            Self::Stepwise(stepwise) | Self::Continuous(stepwise) => {
                 let mut discrete = Vec::new();
 
                 for width in
@@ -54,6 +58,7 @@ impl fmt::Display for FrameSizeEnum {
         match self {
             FrameSizeEnum::Discrete(val) => write!(f, "Discrete({})", val)?,
             FrameSizeEnum::Stepwise(val) => write!(f, "Stepwise({})", val)?,
+            FrameSizeEnum::Continuous(val) => write!(f, "Continuous({})", val)?,
         }
 
         Ok(())
@@ -73,17 +78,12 @@ impl TryFrom<v4l2_frmsizeenum> for FrameSizeEnum {
                         height: desc.__bindgen_anon_1.discrete.height,
                     })
                 }),
-                v4l_sys::v4l2_frmsizetypes_V4L2_FRMSIZE_TYPE_STEPWISE
-                | v4l_sys::v4l2_frmsizetypes_V4L2_FRMSIZE_TYPE_CONTINUOUS => Ok({
-                    FrameSizeEnum::Stepwise(Stepwise {
-                        min_width: desc.__bindgen_anon_1.stepwise.min_width,
-                        max_width: desc.__bindgen_anon_1.stepwise.max_width,
-                        step_width: desc.__bindgen_anon_1.stepwise.step_width,
-                        min_height: desc.__bindgen_anon_1.stepwise.min_height,
-                        max_height: desc.__bindgen_anon_1.stepwise.max_height,
-                        step_height: desc.__bindgen_anon_1.stepwise.step_height,
-                    })
-                }),
+                v4l_sys::v4l2_frmsizetypes_V4L2_FRMSIZE_TYPE_STEPWISE => Ok(
+                    FrameSizeEnum::Stepwise(Stepwise::from(desc.__bindgen_anon_1.stepwise)),
+                ),
+                v4l_sys::v4l2_frmsizetypes_V4L2_FRMSIZE_TYPE_CONTINUOUS => Ok(
+                    FrameSizeEnum::Continuous(Stepwise::from(desc.__bindgen_anon_1.stepwise)),
+                ),
                 typ => Err(format!("Unknown frame size type: {}", typ)),
             }
         }
@@ -121,6 +121,19 @@ pub struct Stepwise {
     pub step_height: u32,
 }
 
+impl From<v4l2_frmsize_stepwise> for Stepwise {
+    fn from(stepwise: v4l2_frmsize_stepwise) -> Self {
+        Stepwise {
+            min_width: stepwise.min_width,
+            max_width: stepwise.max_width,
+            step_width: stepwise.step_width,
+            min_height: stepwise.min_height,
+            max_height: stepwise.max_height,
+            step_height: stepwise.step_height,
+        }
+    }
+}
+
 impl fmt::Display for Stepwise {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
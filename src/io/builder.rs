@@ -0,0 +1,387 @@
+use std::io;
+use std::time::Duration;
+
+use crate::buffer::{Metadata, Type};
+use crate::capability::Flags as CapabilityFlags;
+use crate::device::Device;
+use crate::fraction::Fraction;
+use crate::io::traits::{CaptureStream, Stream as StreamTrait};
+use crate::io::{mmap, read, userptr};
+use crate::memory::Memory;
+use crate::video::{Capture, Output};
+
+/// Stream with a memory type chosen at runtime
+///
+/// Generic code can use this instead of branching on the concrete stream types. An enum is used
+/// rather than a trait object since the async stream trait is not object safe.
+pub enum AnyStream<'a> {
+    Mmap(mmap::Stream<'a>),
+    UserPtr(userptr::Stream),
+    /// Read/write I/O, used for devices without streaming I/O
+    Read(read::Stream),
+}
+
+impl<'a> AnyStream<'a> {
+    /// Returns the memory type of the buffers, `None` for read/write I/O
+    pub fn memory(&self) -> Option<Memory> {
+        match self {
+            AnyStream::Mmap(_) => Some(Memory::Mmap),
+            AnyStream::UserPtr(_) => Some(Memory::UserPtr),
+            AnyStream::Read(_) => None,
+        }
+    }
+
+    /// Returns the number of buffers granted by the driver
+    pub fn buffer_count(&self) -> usize {
+        match self {
+            AnyStream::Mmap(stream) => stream.buffer_count(),
+            AnyStream::UserPtr(stream) => stream.buffer_count(),
+            AnyStream::Read(_) => 1,
+        }
+    }
+}
+
+impl<'a> StreamTrait for AnyStream<'a> {
+    type Item = [u8];
+
+    fn start(&mut self) -> io::Result<()> {
+        match self {
+            AnyStream::Mmap(stream) => stream.start(),
+            AnyStream::UserPtr(stream) => stream.start(),
+            AnyStream::Read(stream) => stream.start(),
+        }
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        match self {
+            AnyStream::Mmap(stream) => stream.stop(),
+            AnyStream::UserPtr(stream) => stream.stop(),
+            AnyStream::Read(stream) => stream.stop(),
+        }
+    }
+}
+
+impl<'a, 'b> CaptureStream<'b> for AnyStream<'a> {
+    fn queue(&mut self, index: usize) -> io::Result<()> {
+        match self {
+            AnyStream::Mmap(stream) => CaptureStream::queue(stream, index),
+            AnyStream::UserPtr(stream) => CaptureStream::queue(stream, index),
+            AnyStream::Read(stream) => CaptureStream::queue(stream, index),
+        }
+    }
+
+    fn dequeue(&mut self) -> io::Result<usize> {
+        match self {
+            AnyStream::Mmap(stream) => CaptureStream::dequeue(stream),
+            AnyStream::UserPtr(stream) => CaptureStream::dequeue(stream),
+            AnyStream::Read(stream) => CaptureStream::dequeue(stream),
+        }
+    }
+
+    fn next(&'b mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        match self {
+            AnyStream::Mmap(stream) => CaptureStream::next(stream),
+            AnyStream::UserPtr(stream) => CaptureStream::next(stream),
+            AnyStream::Read(stream) => CaptureStream::next(stream),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<'a, 'b> crate::io::traits::AsyncCaptureStream<'b> for AnyStream<'a> {
+    async fn ready(&self) -> io::Result<()> {
+        use crate::io::traits::AsyncCaptureStream;
+        match self {
+            AnyStream::Mmap(stream) => AsyncCaptureStream::ready(stream).await,
+            AnyStream::UserPtr(stream) => AsyncCaptureStream::ready(stream).await,
+            AnyStream::Read(stream) => AsyncCaptureStream::ready(stream).await,
+        }
+    }
+
+    async fn poll_dequeue(&mut self) -> io::Result<usize> {
+        use crate::io::traits::AsyncCaptureStream;
+        match self {
+            AnyStream::Mmap(stream) => AsyncCaptureStream::poll_dequeue(stream).await,
+            AnyStream::UserPtr(stream) => AsyncCaptureStream::poll_dequeue(stream).await,
+            AnyStream::Read(stream) => AsyncCaptureStream::poll_dequeue(stream).await,
+        }
+    }
+
+    async fn poll_next(&'b mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        use crate::io::traits::AsyncCaptureStream;
+        match self {
+            AnyStream::Mmap(stream) => AsyncCaptureStream::poll_next(stream).await,
+            AnyStream::UserPtr(stream) => AsyncCaptureStream::poll_next(stream).await,
+            AnyStream::Read(stream) => AsyncCaptureStream::poll_next(stream).await,
+        }
+    }
+}
+
+/// Latency budget of a capture pipeline
+///
+/// Once the driver has filled a buffer, the frame may wait in the queue while the application
+/// is still busy with the frames before it. With `n` buffers, a frame is at most `n` frame
+/// intervals old when the application gets it. More buffers add slack against processing
+/// jitter, so the largest count which stays within the budget is chosen. At least two buffers
+/// are used (one is filled by the driver while the application holds the other), so budgets
+/// below two frame intervals cannot be met.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LatencyTarget {
+    latency: Duration,
+    max_buffers: u32,
+}
+
+impl LatencyTarget {
+    /// Returns a target for the given latency, using at most 32 buffers
+    ///
+    /// # Arguments
+    ///
+    /// * `latency` - Maximum age of a frame when it is handed to the application
+    pub fn new(latency: Duration) -> Self {
+        LatencyTarget {
+            latency,
+            max_buffers: 32,
+        }
+    }
+
+    /// Limits the number of buffers, e.g. to bound memory usage at high frame rates
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Maximum number of buffers
+    pub fn with_max_buffers(mut self, count: u32) -> Self {
+        self.max_buffers = count.max(2);
+        self
+    }
+
+    /// Returns the latency budget
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Returns the number of buffers meeting the target
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Frame interval in seconds
+    pub fn buffer_count(&self, interval: Fraction) -> u32 {
+        if interval.numerator == 0 || interval.denominator == 0 {
+            // unknown frame rate, be conservative
+            return 2;
+        }
+
+        // latency / (numerator / denominator), in integer nanoseconds
+        let frames = self.latency.as_nanos() * u128::from(interval.denominator)
+            / (u128::from(interval.numerator) * 1_000_000_000);
+        frames.clamp(2, u128::from(self.max_buffers)) as u32
+    }
+}
+
+/// Creates streams with a memory type chosen at runtime
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::io::builder::StreamBuilder;
+/// use v4l::io::traits::CaptureStream;
+/// use v4l::memory::Memory;
+/// use v4l::prelude::*;
+///
+/// let dev = Device::new(0).unwrap();
+/// let mut stream = StreamBuilder::new(&dev, Type::VideoCapture)
+///     .memory(Memory::UserPtr)
+///     .buffers(4)
+///     .build()
+///     .unwrap();
+/// let (buf, meta) = stream.next().unwrap();
+/// ```
+pub struct StreamBuilder<'d> {
+    dev: &'d Device,
+    buf_type: Type,
+    memory: Memory,
+    count: u32,
+    latency: Option<LatencyTarget>,
+    alignment: Option<usize>,
+    max_total_bytes: Option<usize>,
+}
+
+impl<'d> StreamBuilder<'d> {
+    /// Returns a builder for memory mapped streams with four buffers
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Device to stream from
+    /// * `buf_type` - Type of the buffers
+    pub fn new(dev: &'d Device, buf_type: Type) -> Self {
+        StreamBuilder {
+            dev,
+            buf_type,
+            memory: Memory::Mmap,
+            count: 4,
+            latency: None,
+            alignment: userptr::default_alignment(),
+            max_total_bytes: dev.max_total_buffer_bytes(),
+        }
+    }
+
+    /// Sets the memory type of the buffers
+    ///
+    /// # Arguments
+    ///
+    /// * `memory` - Memory type
+    pub fn memory(mut self, memory: Memory) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Sets the number of buffers
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of buffers to request from the driver
+    pub fn buffers(mut self, count: u32) -> Self {
+        self.count = count;
+        self.latency = None;
+        self
+    }
+
+    /// Sets the alignment of user allocated buffers
+    ///
+    /// Only applies to [`Memory::UserPtr`], the driver places memory mapped buffers. The
+    /// default is [`userptr::default_alignment`].
+    ///
+    /// # Arguments
+    ///
+    /// * `alignment` - Alignment in bytes, must be a power of two. `None` for no particular one
+    pub fn alignment(mut self, alignment: Option<usize>) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Sets the memory budget for all buffers of the stream
+    ///
+    /// The number of buffers times the image size of the current format is checked against the
+    /// budget before anything is allocated, building fails with [`io::ErrorKind::OutOfMemory`]
+    /// if it would be exceeded. The default is [`Device::max_total_buffer_bytes`].
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Budget in bytes
+    pub fn max_total_buffer_bytes(mut self, bytes: usize) -> Self {
+        self.max_total_bytes = Some(bytes);
+        self
+    }
+
+    /// Derives the number of buffers from a latency budget
+    ///
+    /// The frame interval is read from the device when the count is resolved, so the frame rate
+    /// should be configured before. Replaces any count set by [`StreamBuilder::buffers`].
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Latency budget
+    pub fn latency(mut self, target: LatencyTarget) -> Self {
+        self.latency = Some(target);
+        self
+    }
+
+    /// Returns the number of buffers which will be requested from the driver
+    ///
+    /// The driver may still adjust the count on allocation.
+    pub fn buffer_count(&self) -> io::Result<u32> {
+        let target = match self.latency {
+            Some(target) => target,
+            None => return Ok(self.count),
+        };
+
+        let interval = match self.buf_type {
+            Type::VideoCapture => Capture::params(self.dev)?.interval,
+            Type::VideoOutput => Output::params(self.dev)?.interval,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                        "cannot determine the frame interval of {:?} buffers",
+                        self.buf_type
+                    ),
+                ))
+            }
+        };
+        Ok(target.buffer_count(interval))
+    }
+
+    /// Allocates the buffers and returns the stream
+    ///
+    /// Devices which do not support streaming I/O but read/write I/O get a [`read::Stream`]
+    /// instead, the memory type and buffer count do not apply to them.
+    ///
+    /// Fails with [`io::ErrorKind::Unsupported`] if the device reports that it does not support
+    /// the memory type, or if there is no stream implementation for it.
+    pub fn build<'a>(self) -> io::Result<AnyStream<'a>> {
+        let caps = self.dev.query_caps()?.capabilities;
+        if matches!(self.buf_type, Type::VideoCapture)
+            && !caps.contains(CapabilityFlags::STREAMING)
+            && caps.contains(CapabilityFlags::READ_WRITE)
+        {
+            return Ok(AnyStream::Read(read::Stream::new(self.dev)?));
+        }
+
+        let supported = self.dev.supported_memory(self.buf_type)?;
+        // an empty list means the driver does not tell, so just try
+        if !supported.is_empty() && !supported.contains(&self.memory) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} buffers are not supported by the device", self.memory),
+            ));
+        }
+
+        let count = self.buffer_count()?;
+        match self.memory {
+            Memory::Mmap => Ok(AnyStream::Mmap(mmap::Stream::with_budget(
+                self.dev,
+                self.buf_type,
+                count,
+                self.max_total_bytes,
+            )?)),
+            Memory::UserPtr => Ok(AnyStream::UserPtr(userptr::Stream::with_budget(
+                self.dev,
+                self.buf_type,
+                count,
+                self.alignment,
+                self.max_total_bytes,
+            )?)),
+            memory => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} streams are not implemented", memory),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_target() {
+        let target = LatencyTarget::new(Duration::from_millis(100));
+        // 30 fps: 3 frames fit into 100ms
+        assert_eq!(target.buffer_count(Fraction::new(1, 30)), 3);
+        // 60 fps
+        assert_eq!(target.buffer_count(Fraction::new(1, 60)), 6);
+        // NTSC rate, 1001/30000 s per frame
+        assert_eq!(target.buffer_count(Fraction::new(1001, 30000)), 2);
+        // the budget cannot be met, but two buffers are required
+        assert_eq!(target.buffer_count(Fraction::new(1, 5)), 2);
+        // unknown interval
+        assert_eq!(target.buffer_count(Fraction::new(0, 0)), 2);
+        // bounded
+        assert_eq!(
+            target
+                .with_max_buffers(4)
+                .buffer_count(Fraction::new(1, 1000)),
+            4
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::{io, mem, sync::Arc};
+
+use crate::buffer;
+use crate::device::Handle;
+use crate::io::mmap::arena::{buffer_desc, planes, MAX_PLANES};
+use crate::memory::Memory;
+use crate::v4l2;
+use crate::v4l_sys::*;
+
+/// Exports a plane of a driver allocated buffer as dma-buf file descriptor
+///
+/// # Arguments
+///
+/// * `fd` - Device file descriptor
+/// * `buf_type` - Type of the buffer
+/// * `index` - Buffer index
+/// * `plane` - Plane index, zero for single-planar buffers
+pub(crate) fn export(
+    fd: RawFd,
+    buf_type: buffer::Type,
+    index: u32,
+    plane: u32,
+) -> io::Result<OwnedFd> {
+    unsafe {
+        let mut v4l2_exp = v4l2_exportbuffer {
+            type_: buf_type as u32,
+            index,
+            plane,
+            flags: (libc::O_CLOEXEC | libc::O_RDWR) as u32,
+            ..mem::zeroed()
+        };
+        v4l2::ioctl(
+            fd,
+            v4l2::vidioc::VIDIOC_EXPBUF,
+            &mut v4l2_exp as *mut _ as *mut std::os::raw::c_void,
+        )?;
+
+        Ok(OwnedFd::from_raw_fd(v4l2_exp.fd))
+    }
+}
+
+/// Manage driver allocated buffers exported as dma-buf file descriptors
+///
+/// The buffers are allocated by the driver (just like mapped buffers), but instead of mapping
+/// them into this process, each plane is exported as dma-buf. The file descriptors can be
+/// imported by other devices (e.g. a GPU or a hardware encoder) for zero-copy processing.
+///
+/// All file descriptors are closed and all buffers are released in the Drop impl. Importers
+/// keep their own reference to the memory, but the raw file descriptors handed out by this
+/// arena must not be used after it has been dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::io::dmabuf::Arena;
+/// use v4l::prelude::*;
+///
+/// let dev = Device::new(0).unwrap();
+/// let mut arena = Arena::new(dev.handle(), Type::VideoCapture);
+/// let count = arena.allocate(4).unwrap();
+/// for index in 0..count as usize {
+///     println!("buffer {}: fds {:?}", index, arena.fds(index).unwrap());
+/// }
+/// ```
+pub struct Arena {
+    handle: Arc<Handle>,
+    /// Exported planes of each buffer along with their sizes in bytes
+    bufs: Vec<Vec<(OwnedFd, usize)>>,
+    pub buf_type: buffer::Type,
+    /// Memory budget checked before allocating buffers
+    pub max_total_bytes: Option<usize>,
+}
+
+impl Arena {
+    /// Returns a new buffer manager instance
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - Device handle to get its file descriptor
+    /// * `buf_type` - Type of the buffers
+    pub fn new(handle: Arc<Handle>, buf_type: buffer::Type) -> Self {
+        Arena {
+            handle,
+            bufs: Vec::new(),
+            buf_type,
+            max_total_bytes: None,
+        }
+    }
+
+    fn requestbuffers_desc(&self) -> v4l2_requestbuffers {
+        v4l2_requestbuffers {
+            type_: self.buf_type as u32,
+            memory: Memory::Mmap as u32,
+            ..unsafe { mem::zeroed() }
+        }
+    }
+
+    /// Allocates buffers and exports all of their planes
+    ///
+    /// Returns the number of buffers actually allocated by the driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of buffers to request
+    pub fn allocate(&mut self, count: u32) -> io::Result<u32> {
+        let mut v4l2_reqbufs = v4l2_requestbuffers {
+            count,
+            ..self.requestbuffers_desc()
+        };
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_REQBUFS,
+                &mut v4l2_reqbufs as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+        crate::io::check_granted_buffers(count, v4l2_reqbufs.count)?;
+
+        for index in 0..v4l2_reqbufs.count {
+            let sizes = self.plane_sizes(index)?;
+            if index == 0 {
+                let size = sizes.iter().sum();
+                if let Err(e) =
+                    crate::io::check_buffer_budget(v4l2_reqbufs.count, size, self.max_total_bytes)
+                {
+                    // do not keep the buffers around if they are not wanted
+                    self.release()?;
+                    return Err(e);
+                }
+            }
+
+            let mut planes = Vec::with_capacity(sizes.len());
+            for (plane, size) in sizes.into_iter().enumerate() {
+                let fd = export(self.handle.fd(), self.buf_type, index, plane as u32)?;
+                planes.push((fd, size));
+            }
+            self.bufs.push(planes);
+        }
+
+        Ok(v4l2_reqbufs.count)
+    }
+
+    /// Returns the size of each plane of a buffer
+    fn plane_sizes(&self, index: u32) -> io::Result<Vec<usize>> {
+        let mut planes = planes();
+        let mut v4l2_buf = v4l2_buffer {
+            index,
+            ..buffer_desc(self.buf_type, &mut planes)
+        };
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_QUERYBUF,
+                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        if self.buf_type.is_multiplanar() {
+            Ok(planes[..(v4l2_buf.length as usize).min(MAX_PLANES)]
+                .iter()
+                .map(|plane| plane.length as usize)
+                .collect())
+        } else {
+            Ok(vec![v4l2_buf.length as usize])
+        }
+    }
+
+    /// Returns the number of allocated buffers
+    pub fn len(&self) -> usize {
+        self.bufs.len()
+    }
+
+    /// Returns true if no buffers are allocated
+    pub fn is_empty(&self) -> bool {
+        self.bufs.is_empty()
+    }
+
+    /// Returns the dma-buf file descriptors of all planes of a buffer
+    ///
+    /// Single-planar buffers have exactly one. The descriptors are owned by the arena, use
+    /// `dup` to keep one beyond the lifetime of the arena.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Buffer index
+    pub fn fds(&self, index: usize) -> Option<Vec<RawFd>> {
+        let planes = self.bufs.get(index)?;
+        Some(planes.iter().map(|(fd, _)| fd.as_raw_fd()).collect())
+    }
+
+    /// Returns the sizes of all planes of a buffer in bytes
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Buffer index
+    pub fn sizes(&self, index: usize) -> Option<Vec<usize>> {
+        let planes = self.bufs.get(index)?;
+        Some(planes.iter().map(|(_, size)| *size).collect())
+    }
+
+    /// Closes all file descriptors and frees the buffers
+    pub fn release(&mut self) -> io::Result<()> {
+        // closing our descriptors first, importers hold their own references
+        self.bufs.clear();
+
+        // free all buffers by requesting 0
+        let mut v4l2_reqbufs = v4l2_requestbuffers {
+            count: 0,
+            ..self.requestbuffers_desc()
+        };
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_REQBUFS,
+                &mut v4l2_reqbufs as *mut _ as *mut std::os::raw::c_void,
+            )
+        }
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        if self.bufs.is_empty() {
+            // nothing to do
+            return;
+        }
+
+        if let Err(e) = self.release() {
+            if let Some(code) = e.raw_os_error() {
+                // ENODEV means the file descriptor wrapped in the handle became invalid, most
+                // likely because the device was unplugged or the connection (USB, PCI, ..)
+                // broke down. Handle this case gracefully by ignoring it.
+                if code == 19 {
+                    /* ignore */
+                    return;
+                }
+            }
+
+            panic!("{:?}", e)
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::{error, fmt, io};
+
+/// Reason a stream cannot continue
+///
+/// Carried as the inner error of the [`io::Error`] returned by the stream functions, use
+/// [`StreamError::of`] to tell it apart from other errors.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::io::traits::CaptureStream;
+/// use v4l::io::StreamError;
+/// use v4l::prelude::*;
+///
+/// let dev = Device::new(0).unwrap();
+/// let mut stream = MmapStream::new(&dev, Type::VideoCapture).unwrap();
+///
+/// loop {
+///     match stream.next() {
+///         Ok((buf, _)) => println!("{} bytes", buf.len()),
+///         Err(e) if StreamError::of(&e) == Some(StreamError::DeviceDisconnected) => {
+///             // tear down and wait for the device to show up again
+///             break;
+///         }
+///         Err(e) => panic!("{}", e),
+///     }
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StreamError {
+    /// The device was unplugged or its connection (USB, PCI, ..) broke down
+    ///
+    /// Drivers report this as `ENODEV` (or `ENXIO`). Flaky USB connections often yield `EIO`
+    /// instead, which drivers also return for transient transfer errors, so it is only recognized
+    /// by [`StreamError::of_including_eio`]. The stream and the device should be dropped, the
+    /// device may reappear under a different path.
+    DeviceDisconnected,
+    /// All buffers are held by the caller, so the driver has none left to capture into
+    ///
+    /// Returned in [`crate::io::mmap::Requeue::Manual`] mode, buffers must be requeued before
+    /// the next frame can be fetched.
+    Starved,
+    /// The buffers were released and could not be allocated again, so the stream has none left
+    ///
+    /// Returned by [`crate::io::mmap::Stream::remap`] if mapping a buffer and then restoring the
+    /// buffers failed. The stream cannot capture anymore and should be dropped.
+    BuffersLost,
+}
+
+impl StreamError {
+    /// Returns the stream error an I/O error stands for, if any
+    ///
+    /// Errors which were not translated by a stream (e.g. `ENODEV` returned by another call) are
+    /// recognized as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - Error returned by a stream or device function
+    pub fn of(error: &io::Error) -> Option<StreamError> {
+        if let Some(e) = error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<StreamError>())
+        {
+            return Some(*e);
+        }
+
+        match error.raw_os_error() {
+            Some(libc::ENODEV) | Some(libc::ENXIO) => Some(StreamError::DeviceDisconnected),
+            _ => None,
+        }
+    }
+
+    /// Like [`StreamError::of`], but treats `EIO` as [`StreamError::DeviceDisconnected`] as well
+    ///
+    /// Useful for USB devices whose connection breaks down without the driver noticing the
+    /// unplug. Transient errors are reported as disconnect too, so only use this if the device
+    /// can be reopened cheaply.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - Error returned by a stream or device function
+    pub fn of_including_eio(error: &io::Error) -> Option<StreamError> {
+        match error.raw_os_error() {
+            Some(libc::EIO) => Some(StreamError::DeviceDisconnected),
+            _ => StreamError::of(error),
+        }
+    }
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::DeviceDisconnected => write!(f, "device disconnected"),
+            StreamError::Starved => write!(f, "all buffers are held by the caller"),
+            StreamError::BuffersLost => write!(f, "buffers were released and not restored"),
+        }
+    }
+}
+
+impl error::Error for StreamError {}
+
+/// Wraps errors which indicate that the device is gone into a [`StreamError`]
+///
+/// The OS error is replaced by an [`io::ErrorKind::NotConnected`] error, so
+/// [`io::Error::raw_os_error`] returns `None` for translated errors. Use [`StreamError::of`]
+/// to recognize them.
+pub(crate) fn translate(error: io::Error) -> io::Error {
+    match StreamError::of(&error) {
+        Some(e) if error.get_ref().is_none() => io::Error::new(io::ErrorKind::NotConnected, e),
+        _ => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_of() {
+        let td = vec![
+            (libc::ENODEV, Some(StreamError::DeviceDisconnected)),
+            (libc::ENXIO, Some(StreamError::DeviceDisconnected)),
+            (libc::EIO, None),
+            (libc::EINVAL, None),
+        ];
+        for (code, expected) in td {
+            assert_eq!(
+                expected,
+                StreamError::of(&io::Error::from_raw_os_error(code))
+            );
+        }
+
+        let eio = io::Error::from_raw_os_error(libc::EIO);
+        assert_eq!(
+            Some(StreamError::DeviceDisconnected),
+            StreamError::of_including_eio(&eio)
+        );
+        let starved = io::Error::new(io::ErrorKind::Other, StreamError::Starved);
+        assert_eq!(Some(StreamError::Starved), StreamError::of(&starved));
+        assert_eq!(
+            Some(StreamError::Starved),
+            StreamError::of_including_eio(&starved)
+        );
+        let lost = io::Error::new(io::ErrorKind::Other, StreamError::BuffersLost);
+        assert_eq!(Some(StreamError::BuffersLost), StreamError::of(&lost));
+        assert_eq!(
+            None,
+            StreamError::of(&io::Error::new(io::ErrorKind::Other, "x"))
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        let e = translate(io::Error::from_raw_os_error(libc::ENODEV));
+        assert_eq!(io::ErrorKind::NotConnected, e.kind());
+        assert_eq!(None, e.raw_os_error());
+        assert_eq!(Some(StreamError::DeviceDisconnected), StreamError::of(&e));
+
+        // other errors keep their OS error
+        let e = translate(io::Error::from_raw_os_error(libc::EIO));
+        assert_eq!(Some(libc::EIO), e.raw_os_error());
+
+        // already translated errors are passed through
+        let starved = io::Error::new(io::ErrorKind::Other, StreamError::Starved);
+        let e = translate(starved);
+        assert_eq!(io::ErrorKind::Other, e.kind());
+        assert_eq!(Some(StreamError::Starved), StreamError::of(&e));
+    }
+}
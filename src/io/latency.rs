@@ -0,0 +1,276 @@
+use std::{fmt, io, time::Duration};
+
+use crate::buffer::{Flags, Metadata};
+use crate::io::traits::{CaptureStream, Stream as StreamTrait};
+use crate::timestamp::{self, Timestamp};
+
+/// Histogram of durations with a bounded relative error
+///
+/// Values are recorded in microseconds. Small values are counted exactly, larger ones in
+/// logarithmic buckets subdivided linearly (like HDR histograms), so the relative error of a
+/// reported value stays below 1/64 for any magnitude.
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    count: u64,
+    max: u64,
+}
+
+/// Number of linear sub-buckets per power of two
+const SUB_BUCKETS: u64 = 64;
+
+impl Histogram {
+    /// Returns an empty histogram
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn index(value: u64) -> usize {
+        if value < 2 * SUB_BUCKETS {
+            return value as usize;
+        }
+
+        // number of bits to drop so the value fits into [SUB_BUCKETS, 2 * SUB_BUCKETS)
+        let shift = 63 - value.leading_zeros() as u64 - SUB_BUCKETS.trailing_zeros() as u64;
+        ((shift + 1) * SUB_BUCKETS + (value >> shift) - SUB_BUCKETS) as usize
+    }
+
+    fn value(index: usize) -> u64 {
+        let index = index as u64;
+        if index < 2 * SUB_BUCKETS {
+            return index;
+        }
+
+        let shift = index / SUB_BUCKETS - 1;
+        (index % SUB_BUCKETS + SUB_BUCKETS) << shift
+    }
+
+    /// Adds a value to the histogram
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Duration to record, with microsecond resolution
+    pub fn record(&mut self, value: Duration) {
+        let value = value.as_micros().min(u64::MAX as u128) as u64;
+        let index = Self::index(value);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+
+        self.counts[index] += 1;
+        self.count += 1;
+        self.max = self.max.max(value);
+    }
+
+    /// Returns the number of recorded values
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns true if no values were recorded
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the largest recorded value
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max)
+    }
+
+    /// Returns the value below which the given percentage of the recorded values fall
+    ///
+    /// # Arguments
+    ///
+    /// * `percentile` - Percentile in the range `[0, 100]`
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        let rank = ((percentile / 100.0 * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(Self::value(index).min(self.max));
+            }
+        }
+
+        self.max()
+    }
+
+    /// Removes all recorded values
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Summary of the recorded latencies
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct LatencyReport {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// Number of frames the report is based on
+    pub frames: u64,
+    /// Number of frames which could not be measured because their timestamps are not based on
+    /// `CLOCK_MONOTONIC`
+    pub skipped: u64,
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "p50: {:?}, p90: {:?}, p99: {:?}, max: {:?} ({} frames)",
+            self.p50, self.p90, self.p99, self.max, self.frames
+        )
+    }
+}
+
+/// Measures how old frames are by the time they are delivered
+///
+/// For each frame returned by the wrapped stream, the difference between the current
+/// `CLOCK_MONOTONIC` time and the buffer timestamp is recorded. Only buffers flagged with
+/// [`Flags::TIMESTAMP_MONOTONIC`] are taken into account, other timestamps (e.g. copied from
+/// an output buffer) live in a different clock domain.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::io::latency::LatencyProfiler;
+/// use v4l::io::traits::CaptureStream;
+/// use v4l::prelude::*;
+///
+/// let dev = Device::new(0).unwrap();
+/// let stream = MmapStream::new(&dev, Type::VideoCapture).unwrap();
+/// let mut stream = LatencyProfiler::new(stream);
+///
+/// for _ in 0..100 {
+///     let (_buf, _meta) = stream.next().unwrap();
+/// }
+/// println!("{}", stream.report());
+/// ```
+pub struct LatencyProfiler<S> {
+    stream: S,
+    histogram: Histogram,
+    skipped: u64,
+}
+
+impl<S> LatencyProfiler<S> {
+    /// Returns a profiler wrapping the given stream
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - Capture stream
+    pub fn new(stream: S) -> Self {
+        LatencyProfiler {
+            stream,
+            histogram: Histogram::new(),
+            skipped: 0,
+        }
+    }
+
+    /// Returns the recorded latencies
+    pub fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    /// Returns a summary of the recorded latencies
+    pub fn report(&self) -> LatencyReport {
+        LatencyReport {
+            p50: self.histogram.percentile(50.0),
+            p90: self.histogram.percentile(90.0),
+            p99: self.histogram.percentile(99.0),
+            max: self.histogram.max(),
+            frames: self.histogram.len(),
+            skipped: self.skipped,
+        }
+    }
+
+    /// Removes all recorded latencies
+    pub fn reset(&mut self) {
+        self.histogram.clear();
+        self.skipped = 0;
+    }
+
+    /// Returns the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    fn record(histogram: &mut Histogram, skipped: &mut u64, meta: &Metadata) -> io::Result<()> {
+        if meta.flags & Flags::TIMESTAMP_MASK != Flags::TIMESTAMP_MONOTONIC {
+            *skipped += 1;
+            return Ok(());
+        }
+
+        let now = Timestamp::from(timestamp::clock_gettime(libc::CLOCK_MONOTONIC)?);
+        // a timestamp from the future would be a driver bug, count it as zero latency
+        histogram.record(now.since(meta.timestamp).unwrap_or_default());
+        Ok(())
+    }
+}
+
+impl<S: StreamTrait> StreamTrait for LatencyProfiler<S> {
+    type Item = S::Item;
+
+    fn start(&mut self) -> io::Result<()> {
+        self.stream.start()
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        self.stream.stop()
+    }
+}
+
+impl<'a, S: CaptureStream<'a>> CaptureStream<'a> for LatencyProfiler<S> {
+    fn queue(&mut self, index: usize) -> io::Result<()> {
+        self.stream.queue(index)
+    }
+
+    fn dequeue(&mut self) -> io::Result<usize> {
+        self.stream.dequeue()
+    }
+
+    fn next(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        let (buf, meta) = self.stream.next()?;
+        Self::record(&mut self.histogram, &mut self.skipped, meta)?;
+        Ok((buf, meta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets() {
+        for value in [0, 1, 127, 128, 129, 1000, 65_535, 1_000_000, u64::MAX / 2] {
+            let lower = Histogram::value(Histogram::index(value));
+            assert!(lower <= value, "{} <= {}", lower, value);
+            assert!(
+                value - lower <= value / SUB_BUCKETS,
+                "{} ~ {}",
+                lower,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_histogram_percentile() {
+        let mut hist = Histogram::new();
+        for us in 1..=100 {
+            hist.record(Duration::from_micros(us));
+        }
+        hist.record(Duration::from_millis(10));
+
+        assert_eq!(101, hist.len());
+        assert_eq!(Duration::from_micros(51), hist.percentile(50.0));
+        assert_eq!(Duration::from_micros(91), hist.percentile(90.0));
+        assert_eq!(Duration::from_millis(10), hist.max());
+        // 10ms falls into a bucket of 128us width
+        let p100 = hist.percentile(100.0);
+        assert!(p100 <= Duration::from_millis(10));
+        assert!(p100 > Duration::from_micros(9_800));
+    }
+}
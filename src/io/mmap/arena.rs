@@ -6,14 +6,49 @@ use crate::memory::Memory;
 use crate::v4l2;
 use crate::v4l_sys::*;
 
+/// Maximum number of planes of a multi-planar buffer
+pub(crate) const MAX_PLANES: usize = VIDEO_MAX_PLANES as usize;
+
+/// Plane descriptors passed along with multi-planar buffers
+pub(crate) type Planes = [v4l2_plane; MAX_PLANES];
+
+/// Returns zeroed plane descriptors
+pub(crate) fn planes() -> Planes {
+    unsafe { mem::zeroed() }
+}
+
+/// Returns a buffer descriptor
+///
+/// Multi-planar buffers reference the given plane descriptors, so they must outlive any ioctl
+/// the descriptor is passed to.
+pub(crate) fn buffer_desc(buf_type: buffer::Type, planes: &mut Planes) -> v4l2_buffer {
+    let mut v4l2_buf = v4l2_buffer {
+        type_: buf_type as u32,
+        memory: Memory::Mmap as u32,
+        ..unsafe { mem::zeroed() }
+    };
+    if buf_type.is_multiplanar() {
+        v4l2_buf.m.planes = planes.as_mut_ptr();
+        v4l2_buf.length = MAX_PLANES as u32;
+    }
+    v4l2_buf
+}
+
 /// Manage mapped buffers
 ///
+/// Multi-planar buffers have each plane mapped separately: the first plane is kept in `bufs`,
+/// the other planes in `planes`. Use [`Arena::buffer_planes`] to get all of them.
+///
 /// All buffers are unmapped in the Drop impl.
 /// In case of errors during unmapping, we panic because there is memory corruption going on.
 pub struct Arena<'a> {
     handle: Arc<Handle>,
     pub bufs: Vec<&'a mut [u8]>,
+    /// Planes following the first one of multi-planar buffers (empty for single-planar buffers)
+    pub planes: Vec<Vec<&'a mut [u8]>>,
     pub buf_type: buffer::Type,
+    /// Memory budget checked before allocating buffers
+    pub max_total_bytes: Option<usize>,
 }
 
 impl<'a> Arena<'a> {
@@ -30,15 +65,9 @@ impl<'a> Arena<'a> {
         Arena {
             handle,
             bufs: Vec::new(),
+            planes: Vec::new(),
             buf_type,
-        }
-    }
-
-    fn buffer_desc(&self) -> v4l2_buffer {
-        v4l2_buffer {
-            type_: self.buf_type as u32,
-            memory: Memory::Mmap as u32,
-            ..unsafe { mem::zeroed() }
+            max_total_bytes: None,
         }
     }
 
@@ -51,6 +80,32 @@ impl<'a> Arena<'a> {
     }
 
     pub fn allocate(&mut self, count: u32) -> io::Result<u32> {
+        if self.max_total_bytes.is_some() {
+            // check the budget against the image size before the driver allocates anything
+            let mut v4l2_fmt = v4l2_format {
+                type_: self.buf_type as u32,
+                ..unsafe { mem::zeroed() }
+            };
+            unsafe {
+                v4l2::ioctl(
+                    self.handle.fd(),
+                    v4l2::vidioc::VIDIOC_G_FMT,
+                    &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+                )?;
+            }
+            let size = if self.buf_type.is_multiplanar() {
+                let pix_mp = unsafe { v4l2_fmt.fmt.pix_mp };
+                let plane_fmt = pix_mp.plane_fmt;
+                plane_fmt[..(pix_mp.num_planes as usize).min(MAX_PLANES)]
+                    .iter()
+                    .map(|plane| plane.sizeimage as usize)
+                    .sum()
+            } else {
+                unsafe { v4l2_fmt.fmt.pix.sizeimage as usize }
+            };
+            crate::io::check_buffer_budget(count, size, self.max_total_bytes)?;
+        }
+
         let mut v4l2_reqbufs = v4l2_requestbuffers {
             count,
             ..self.requestbuffers_desc()
@@ -62,43 +117,145 @@ impl<'a> Arena<'a> {
                 &mut v4l2_reqbufs as *mut _ as *mut std::os::raw::c_void,
             )?;
         }
+        crate::io::check_granted_buffers(count, v4l2_reqbufs.count)?;
 
-        for index in 0..v4l2_reqbufs.count {
-            let mut v4l2_buf = v4l2_buffer {
-                index,
-                ..self.buffer_desc()
-            };
-            unsafe {
-                v4l2::ioctl(
-                    self.handle.fd(),
-                    v4l2::vidioc::VIDIOC_QUERYBUF,
-                    &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
-                )?;
+        let (mapped, result) = map_all(v4l2_reqbufs.count, |index| self.map(index));
+        for mut planes in mapped {
+            let buf = planes.remove(0);
+            self.bufs.push(buf);
+            self.planes.push(planes);
+        }
+        if let Err(e) = result {
+            // leave the device in a clean state for a retry, the mapping error is the one worth
+            // reporting
+            let _ = self.release();
+            return Err(e);
+        }
 
-                let ptr = v4l2::mmap(
+        Ok(v4l2_reqbufs.count)
+    }
+
+    /// Maps all planes of a buffer, single-planar buffers have exactly one
+    fn map(&self, index: u32) -> io::Result<Vec<&'a mut [u8]>> {
+        let mut planes = planes();
+        let mut v4l2_buf = v4l2_buffer {
+            index,
+            ..buffer_desc(self.buf_type, &mut planes)
+        };
+        unsafe {
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_QUERYBUF,
+                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        // (offset, length) of each plane
+        let layout: Vec<(u32, u32)> = if self.buf_type.is_multiplanar() {
+            planes[..(v4l2_buf.length as usize).min(MAX_PLANES)]
+                .iter()
+                .map(|plane| (unsafe { plane.m.mem_offset }, plane.length))
+                .collect()
+        } else {
+            vec![(unsafe { v4l2_buf.m.offset }, v4l2_buf.length)]
+        };
+
+        let mut mapped: Vec<&'a mut [u8]> = Vec::with_capacity(layout.len());
+        for (offset, length) in layout {
+            let ptr = unsafe {
+                v4l2::mmap(
                     ptr::null_mut(),
-                    v4l2_buf.length as usize,
+                    length as usize,
                     libc::PROT_READ | libc::PROT_WRITE,
                     libc::MAP_SHARED,
                     self.handle.fd(),
-                    v4l2_buf.m.offset as libc::off_t,
-                )?;
-
-                let slice =
-                    slice::from_raw_parts_mut::<u8>(ptr as *mut u8, v4l2_buf.length as usize);
-                self.bufs.push(slice);
+                    offset as libc::off_t,
+                )
+            };
+            match ptr {
+                Ok(ptr) => mapped.push(unsafe {
+                    slice::from_raw_parts_mut::<u8>(ptr as *mut u8, length as usize)
+                }),
+                Err(e) => {
+                    // do not leak the planes mapped so far
+                    for plane in &mapped {
+                        unsafe {
+                            v4l2::munmap(plane.as_ptr() as *mut core::ffi::c_void, plane.len())?;
+                        }
+                    }
+                    return Err(e);
+                }
             }
         }
+        if mapped.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "driver reported a buffer without planes",
+            ));
+        }
 
-        Ok(v4l2_reqbufs.count)
+        Ok(mapped)
     }
 
-    pub fn release(&mut self) -> io::Result<()> {
-        for buf in &self.bufs {
+    /// Returns all planes of a buffer
+    ///
+    /// Single-planar buffers consist of exactly one plane.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Buffer index
+    pub fn buffer_planes(&self, index: usize) -> Option<Vec<&[u8]>> {
+        let first: &[u8] = self.bufs.get(index)?;
+        let mut planes = vec![first];
+        if let Some(rest) = self.planes.get(index) {
+            planes.extend(rest.iter().map(|plane| &**plane));
+        }
+        Some(planes)
+    }
+
+    fn unmap(&self, index: usize) -> io::Result<()> {
+        let rest = self.planes.get(index).into_iter().flatten();
+        for buf in std::iter::once(&self.bufs[index]).chain(rest) {
             unsafe {
                 v4l2::munmap(buf.as_ptr() as *mut core::ffi::c_void, buf.len())?;
             }
         }
+        Ok(())
+    }
+
+    /// Maps all buffers again using fresh offsets from the driver and unmaps the old mappings
+    ///
+    /// The buffers themselves are not reallocated. Each new mapping is established before the
+    /// old one is dropped. If mapping fails, all buffers are released so no stale mapping is
+    /// left behind.
+    pub fn remap(&mut self) -> io::Result<()> {
+        for index in 0..self.bufs.len() {
+            let mut planes = match self.map(index as u32) {
+                Ok(planes) => planes,
+                Err(e) => {
+                    // the mapping error is the one worth reporting
+                    let _ = self.release();
+                    self.bufs.clear();
+                    self.planes.clear();
+                    return Err(e);
+                }
+            };
+            let buf = mem::replace(&mut self.bufs[index], planes.remove(0));
+            let rest = mem::replace(&mut self.planes[index], planes);
+            for old in std::iter::once(buf).chain(rest) {
+                unsafe {
+                    v4l2::munmap(old.as_ptr() as *mut core::ffi::c_void, old.len())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn release(&mut self) -> io::Result<()> {
+        for index in 0..self.bufs.len() {
+            self.unmap(index)?;
+        }
 
         // free all buffers by requesting 0
         let mut v4l2_reqbufs = v4l2_requestbuffers {
@@ -114,10 +271,25 @@ impl<'a> Arena<'a> {
         }
 
         self.bufs.clear();
+        self.planes.clear();
         Ok(())
     }
 }
 
+/// Maps the buffers `0..count` in order and stops at the first failure
+///
+/// Returns the buffers mapped so far along with the error, so the caller can unmap them.
+fn map_all<T>(count: u32, mut map: impl FnMut(u32) -> io::Result<T>) -> (Vec<T>, io::Result<()>) {
+    let mut mapped = Vec::with_capacity(count as usize);
+    for index in 0..count {
+        match map(index) {
+            Ok(buf) => mapped.push(buf),
+            Err(e) => return (mapped, Err(e)),
+        }
+    }
+    (mapped, Ok(()))
+}
+
 impl<'a> Drop for Arena<'a> {
     fn drop(&mut self) {
         if self.bufs.is_empty() {
@@ -140,3 +312,30 @@ impl<'a> Drop for Arena<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_all() {
+        let (mapped, result) = map_all(4, Ok);
+        assert_eq!(mapped, vec![0, 1, 2, 3]);
+        assert!(result.is_ok());
+
+        // mapping the third buffer fails: the first two must be handed back for unmapping and
+        // no further buffer must be mapped
+        let mut calls = vec![];
+        let (mapped, result) = map_all(4, |index| {
+            calls.push(index);
+            if index == 2 {
+                Err(io::Error::from_raw_os_error(libc::ENOMEM))
+            } else {
+                Ok(index)
+            }
+        });
+        assert_eq!(mapped, vec![0, 1]);
+        assert_eq!(calls, vec![0, 1, 2]);
+        assert_eq!(result.unwrap_err().raw_os_error(), Some(libc::ENOMEM));
+    }
+}
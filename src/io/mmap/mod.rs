@@ -1,4 +1,7 @@
 pub(crate) mod arena;
 
+pub mod output;
+pub use output::OutputStream;
+
 pub mod stream;
-pub use stream::Stream;
+pub use stream::{Frame, FrameOrEvent, Requeue, Stats, Stream};
@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::io;
+use std::time::Duration;
+
+use crate::buffer::Type;
+use crate::device::Device;
+use crate::io::mmap::Stream;
+use crate::io::traits::{OutputStream as OutputStreamTrait, Stream as StreamTrait};
+
+/// Stream of frames written to an output device (e.g. v4l2loopback)
+///
+/// A frame is produced by filling the buffer returned by [`OutputStream::next_mut`] and handing
+/// it to the driver with [`OutputStream::queue`]. All buffers are kept in flight: a buffer is
+/// only reclaimed from the driver once every buffer has been queued. Streaming starts with the
+/// first queued frame.
+///
+/// Only the first plane of multi-planar buffers is exposed.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::io::mmap::OutputStream;
+/// use v4l::prelude::*;
+///
+/// let dev = Device::with_path("/dev/video10").unwrap();
+/// let mut stream = OutputStream::new(&dev).unwrap();
+///
+/// for i in 0..100u8 {
+///     let buf = stream.next_mut().unwrap();
+///     buf.fill(i);
+///     stream.queue().unwrap();
+/// }
+/// ```
+pub struct OutputStream<'a> {
+    stream: Stream<'a>,
+    /// Buffers owned by the application, i.e. not queued to the driver
+    free: VecDeque<usize>,
+    /// Buffer handed out by `next_mut` which has not been queued yet
+    current: Option<usize>,
+    timeout: i32,
+    active: bool,
+}
+
+impl<'a> OutputStream<'a> {
+    /// Returns a stream for frame output with four buffers
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Output device ref to get its file descriptor
+    pub fn new(dev: &Device) -> io::Result<Self> {
+        OutputStream::with_buffers(dev, Type::VideoOutput, 4)
+    }
+
+    /// Returns a stream for frame output
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Output device ref to get its file descriptor
+    /// * `buf_type` - Type of the buffers, usually [`Type::VideoOutput`]
+    /// * `buf_count` - Number of buffers to allocate
+    pub fn with_buffers(dev: &Device, buf_type: Type, buf_count: u32) -> io::Result<Self> {
+        let stream = Stream::with_buffers(dev, buf_type, buf_count)?;
+        let free = (0..stream.buffer_count()).collect();
+
+        Ok(OutputStream {
+            stream,
+            free,
+            current: None,
+            timeout: -1,
+            active: false,
+        })
+    }
+
+    /// Sets the maximum time to wait for the driver to release a buffer
+    ///
+    /// Fails the waiting call with [`io::ErrorKind::TimedOut`]. Durations beyond `i32::MAX`
+    /// milliseconds are saturated.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - Maximum time to wait for a buffer
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.timeout = duration.as_millis().try_into().unwrap_or(i32::MAX);
+        self.stream.set_timeout(duration);
+    }
+
+    /// Returns a buffer to be filled with the next frame
+    ///
+    /// Blocks until the driver releases a buffer if all of them are queued. The buffer is held
+    /// until it is queued, so calling this again returns the same buffer. The whole buffer is
+    /// handed to the driver unless a smaller size is set by [`OutputStream::set_bytesused`].
+    pub fn next_mut(&mut self) -> io::Result<&mut [u8]> {
+        let index = match self.current {
+            Some(index) => index,
+            None => {
+                let index = match self.free.pop_front() {
+                    Some(index) => index,
+                    None => self.reclaim()?,
+                };
+                self.stream.buffer_mut(index).1.bytesused = 0;
+                self.current = Some(index);
+                index
+            }
+        };
+
+        Ok(self.stream.buffer_mut(index).0)
+    }
+
+    /// Sets the number of bytes of the held buffer which contain frame data
+    ///
+    /// Required for compressed formats (e.g. MJPG) whose frames do not fill the buffer. Has no
+    /// effect if no buffer is held.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - Number of bytes, zero for the whole buffer
+    pub fn set_bytesused(&mut self, len: usize) {
+        if let Some(index) = self.current {
+            self.stream.buffer_mut(index).1.bytesused = len as u32;
+        }
+    }
+
+    /// Hands the held buffer to the driver
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if no buffer was obtained by
+    /// [`OutputStream::next_mut`] before.
+    pub fn queue(&mut self) -> io::Result<()> {
+        let index = self.current.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no buffer held, call next_mut first",
+            )
+        })?;
+
+        OutputStreamTrait::queue(&mut self.stream, index)?;
+        self.current = None;
+
+        if !self.active {
+            StreamTrait::start(&mut self.stream)?;
+            self.active = true;
+        }
+        Ok(())
+    }
+
+    fn reclaim(&mut self) -> io::Result<usize> {
+        let handle = self.stream.handle();
+        if !handle.is_nonblocking() && handle.poll(libc::POLLOUT, self.timeout)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF"));
+        }
+        OutputStreamTrait::dequeue(&mut self.stream)
+    }
+}
@@ -1,15 +1,165 @@
 use std::convert::TryInto;
+use std::os::unix::io::OwnedFd;
 use std::time::Duration;
 use std::{io, mem, sync::Arc};
 
-use crate::buffer::{Metadata, Type};
+use crate::buffer::{Flags, Metadata, PlaneMeta, Type};
 use crate::device::{Device, Handle};
-use crate::io::mmap::arena::Arena;
+use crate::event;
+use crate::format::{description::Flags as DescriptionFlags, FieldOrder, Format};
+use crate::io::dmabuf;
+use crate::io::error::{self, StreamError};
+use crate::io::mmap::arena::{self, Arena, Planes};
 use crate::io::traits::{CaptureStream, OutputStream, Stream as StreamTrait};
-use crate::memory::Memory;
+use crate::io::SequenceGap;
+use crate::timestamp::WallClock;
 use crate::v4l2;
 use crate::v4l_sys::*;
 
+/// Number of erroneous frames in a row after which fetching a frame fails in
+/// [`Stream::skip_errors`] mode
+const MAX_SKIPPED_ERRORS: usize = 32;
+
+/// Result of [`Stream::next_or_event`] and [`Stream::poll_next_or_event`]
+pub enum FrameOrEvent<'a> {
+    /// Captured frame
+    Frame(&'a [u8], &'a Metadata),
+    /// The source changed its resolution, carries the format now reported by the driver
+    SourceChanged(Format),
+    /// Any other subscribed event, e.g. a control change (see [`Device::subscribe_event`])
+    Event(event::Event),
+}
+
+/// Captured frame which keeps its buffer dequeued while it is alive
+///
+/// Returned by [`Stream::next_frame`] and [`Stream::poll_next_frame`]. The driver cannot write
+/// to the buffer until the frame is dropped, at which point the buffer is queued again
+/// (`VIDIOC_QBUF`). Errors are ignored on drop, use [`Frame::requeue`] to observe them.
+pub struct Frame<'s, 'a> {
+    stream: &'s mut Stream<'a>,
+    index: usize,
+    queued: bool,
+}
+
+impl<'s, 'a> Frame<'s, 'a> {
+    /// Returns the buffer index of the frame
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the metadata of the frame
+    pub fn meta(&self) -> &Metadata {
+        &self.stream.buf_meta[self.index]
+    }
+
+    /// Returns the frame data without the unused remainder of the buffer
+    ///
+    /// Dereferencing the frame yields the whole buffer, see [`Metadata::valid`].
+    pub fn valid(&self) -> &[u8] {
+        self.meta().valid(self)
+    }
+
+    /// Hands the buffer back to the driver
+    ///
+    /// Same as dropping the frame, but reports errors of `VIDIOC_QBUF`.
+    pub fn requeue(mut self) -> io::Result<()> {
+        self.queued = true;
+        CaptureStream::queue(self.stream, self.index)
+    }
+}
+
+impl<'s, 'a> std::ops::Deref for Frame<'s, 'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.stream.arena.bufs[self.index]
+    }
+}
+
+impl<'s, 'a> Drop for Frame<'s, 'a> {
+    fn drop(&mut self) {
+        if !self.queued {
+            let _ = CaptureStream::queue(self.stream, self.index);
+        }
+    }
+}
+
+/// Frame statistics of a capture stream
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of dequeued frames
+    pub frames: u64,
+    /// Number of frames with less data than the image size of an uncompressed format
+    pub short_frames: u64,
+    /// Number of frames with more data than the image size of an uncompressed format
+    pub long_frames: u64,
+    /// Number of frames flagged with `V4L2_BUF_FLAG_ERROR` by the driver
+    pub error_frames: u64,
+    /// Number of frames skipped by the driver, derived from gaps in the sequence numbers
+    pub dropped_frames: u64,
+}
+
+/// Strategy for handing captured buffers back to the driver
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Requeue {
+    /// The buffer returned by `next` is re-queued on the following call to `next`
+    ///
+    /// Only one buffer is held by the caller at a time and the data must not be used after the
+    /// next frame was fetched, since the driver may overwrite it by then.
+    #[default]
+    Auto,
+    /// Buffers stay dequeued until they are passed to [`Stream::requeue`]
+    ///
+    /// Meant for pipelines which keep working on frames (e.g. in another thread) while further
+    /// frames are captured. Fetching a frame fails with [`StreamError::Starved`] while all
+    /// buffers are held, since the driver has none left to capture into.
+    Manual,
+}
+
+/// Buffers held by the caller in [`Requeue::Manual`] mode
+#[derive(Debug)]
+struct Outstanding(Vec<bool>);
+
+impl Outstanding {
+    fn new(count: usize) -> Self {
+        Outstanding(vec![false; count])
+    }
+
+    fn held(&self, index: usize) -> bool {
+        self.0.get(index).copied().unwrap_or(false)
+    }
+
+    fn hold(&mut self, index: usize) {
+        self.0[index] = true;
+    }
+
+    /// Marks a held buffer as free again, fails if it is not held
+    fn release(&mut self, index: usize) -> io::Result<()> {
+        if !self.held(index) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("buffer {} is not held by the caller", index),
+            ));
+        }
+        self.0[index] = false;
+        Ok(())
+    }
+
+    fn indices(&self) -> Vec<usize> {
+        (0..self.0.len()).filter(|&index| self.0[index]).collect()
+    }
+
+    /// Returns the buffers which may be handed to the driver
+    fn free(&self) -> Vec<usize> {
+        (0..self.0.len()).filter(|&index| !self.0[index]).collect()
+    }
+
+    /// Whether the driver has no buffer left to capture into
+    fn starved(&self) -> bool {
+        self.0.iter().all(|&held| held)
+    }
+}
+
 /// Stream of mapped buffers
 ///
 /// An arena instance is used internally for buffer handling.
@@ -17,9 +167,33 @@ pub struct Stream<'a> {
     handle: Arc<Handle>,
     arena: Arena<'a>,
     arena_index: usize,
+    /// The buffer at `arena_index` is held by the caller and must be requeued
+    holding: bool,
     buf_type: Type,
     buf_meta: Vec<Metadata>,
     timeout: Option<i32>,
+    wallclock: Option<WallClock>,
+    skip_initial: usize,
+    skip_remaining: usize,
+    skip_errors: bool,
+    /// Number of erroneous frames discarded since the last valid one
+    skipped_errors: usize,
+    field: Option<FieldOrder>,
+    top_field_next: bool,
+    source_change: bool,
+    /// Events were signaled before, so pending ones are dequeued before waiting again
+    events: bool,
+    /// Whether the frame size is checked, `expected_size` is only set for uncompressed formats
+    check_size: bool,
+    expected_size: Option<u32>,
+    stats: Stats,
+    sequence: SequenceGap,
+    requeue: Requeue,
+    /// Buffers held by the caller in [`Requeue::Manual`] mode
+    outstanding: Outstanding,
+    /// Number of output buffers which have not been queued since the stream was started
+    #[cfg(feature = "tokio")]
+    unqueued: usize,
 
     active: bool,
 }
@@ -49,20 +223,391 @@ impl<'a> Stream<'a> {
     }
 
     pub fn with_buffers(dev: &Device, buf_type: Type, buf_count: u32) -> io::Result<Self> {
+        Stream::with_budget(dev, buf_type, buf_count, dev.max_total_buffer_bytes())
+    }
+
+    /// Returns a stream whose buffers are checked against the given memory budget
+    ///
+    /// Used by [`crate::io::builder::StreamBuilder`] to override the budget of the device.
+    pub(crate) fn with_budget(
+        dev: &Device,
+        buf_type: Type,
+        buf_count: u32,
+        max_total_bytes: Option<usize>,
+    ) -> io::Result<Self> {
         let mut arena = Arena::new(dev.handle(), buf_type);
+        arena.max_total_bytes = max_total_bytes;
         let count = arena.allocate(buf_count)?;
         let mut buf_meta = Vec::new();
         buf_meta.resize(count as usize, Metadata::default());
 
-        Ok(Stream {
+        let mut stream = Stream {
             handle: dev.handle(),
             arena,
             arena_index: 0,
+            holding: false,
             buf_type,
             buf_meta,
             active: false,
             timeout: None,
-        })
+            wallclock: None,
+            skip_initial: dev.initial_frame_skip(),
+            skip_remaining: 0,
+            skip_errors: false,
+            skipped_errors: 0,
+            field: None,
+            top_field_next: true,
+            source_change: false,
+            events: false,
+            check_size: true,
+            expected_size: None,
+            stats: Stats::default(),
+            sequence: SequenceGap::default(),
+            requeue: Requeue::default(),
+            outstanding: Outstanding::new(count as usize),
+            #[cfg(feature = "tokio")]
+            unqueued: 0,
+        };
+        // the check is best effort, so do not fail if the format cannot be determined
+        stream.expected_size = stream.uncompressed_size().unwrap_or(None);
+
+        Ok(stream)
+    }
+
+    /// Discards the first frames after the stream is started
+    ///
+    /// Several drivers return a zero-filled or partial buffer right after `VIDIOC_STREAMON`.
+    /// The given number of frames is silently dequeued and re-queued each time the stream is
+    /// started, so callers only get to see the frames after that.
+    ///
+    /// The default is taken from [`Device::initial_frame_skip`].
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of frames to discard
+    pub fn skip_initial(mut self, count: usize) -> Self {
+        self.skip_initial = count;
+        self
+    }
+
+    /// Discards frames which the driver flagged as erroneous
+    ///
+    /// Drivers set `V4L2_BUF_FLAG_ERROR` if the data of a buffer may be corrupt, e.g. after a
+    /// transmission error. By default such frames are returned like any other, with the flag set
+    /// in [`Metadata::flags`]. Once enabled, they are silently re-queued and the next frame is
+    /// fetched instead. They are counted in [`Stats::error_frames`] either way.
+    ///
+    /// If 32 frames in a row are erroneous, fetching a frame fails with
+    /// [`io::ErrorKind::InvalidData`] instead of waiting forever for a valid one.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to discard erroneous frames
+    pub fn skip_errors(mut self, enable: bool) -> Self {
+        self.skip_errors = enable;
+        self
+    }
+
+    /// Sets the strategy for handing captured buffers back to the driver
+    ///
+    /// The default is [`Requeue::Auto`]. With [`Requeue::Manual`], every buffer returned by
+    /// `next` or `poll_next` must be passed to [`Stream::requeue`] once the caller is done with
+    /// it, see [`Stream::last_index`].
+    ///
+    /// # Arguments
+    ///
+    /// * `requeue` - Requeue strategy
+    pub fn with_requeue(mut self, requeue: Requeue) -> Self {
+        self.requeue = requeue;
+        self
+    }
+
+    /// Sets the field order of queued output buffers
+    ///
+    /// By default, the `field` of the buffer [`Metadata`] is passed to the driver as-is. Once a
+    /// field order is set, it is used for every queued buffer instead. For
+    /// [`FieldOrder::Alternate`], the buffers are tagged as [`FieldOrder::Top`] and
+    /// [`FieldOrder::Bottom`] in turn, starting with the top field whenever the stream is
+    /// started.
+    ///
+    /// The field order should match the one passed to `set_format`.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - Field order of the output buffers
+    pub fn with_field(mut self, field: FieldOrder) -> Self {
+        self.field = Some(field);
+        self
+    }
+
+    /// Enables wall clock timestamps for captured frames
+    ///
+    /// The offset between `CLOCK_MONOTONIC` (used by the driver) and `CLOCK_REALTIME` is sampled
+    /// when the stream is started and refreshed periodically afterwards. Each dequeued buffer then
+    /// carries its capture time as [`std::time::SystemTime`] in [`Metadata::wall_clock`].
+    pub fn with_wallclock(mut self) -> io::Result<Self> {
+        self.wallclock = Some(WallClock::new()?);
+        Ok(self)
+    }
+
+    /// Subscribes to source change events
+    ///
+    /// Capture cards (e.g. HDMI) report a change of the source resolution through
+    /// `V4L2_EVENT_SOURCE_CHANGE`. The buffers in flight are not suitable for the new resolution
+    /// anymore, so such an event is surfaced by [`Stream::poll_next_or_event`] instead of a
+    /// frame.
+    pub fn with_source_change_events(mut self) -> io::Result<Self> {
+        event::subscribe(self.handle.fd(), event::Type::SourceChange, 0)?;
+        self.source_change = true;
+        Ok(self)
+    }
+
+    /// Renegotiates the format and reallocates all buffers
+    ///
+    /// The stream is stopped first and restarted on the next call to fetch a frame. Returns the
+    /// format actually applied by the driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `fmt` - Desired format, usually the one carried by [`FrameOrEvent::SourceChanged`]
+    pub fn reconfigure(&mut self, fmt: &Format) -> io::Result<Format> {
+        if self.active {
+            StreamTrait::stop(self)?;
+        }
+
+        let count = self.arena.bufs.len() as u32;
+        self.arena.release()?;
+
+        unsafe {
+            let mut v4l2_fmt = v4l2_format {
+                type_: self.buf_type as u32,
+                fmt: if self.buf_type.is_multiplanar() {
+                    // the driver fills in the plane layout for the pixel format
+                    let pix: v4l2_pix_format = (*fmt).into();
+                    v4l2_format__bindgen_ty_1 {
+                        pix_mp: v4l2_pix_format_mplane {
+                            width: pix.width,
+                            height: pix.height,
+                            pixelformat: pix.pixelformat,
+                            field: pix.field,
+                            colorspace: pix.colorspace,
+                            flags: pix.flags as u8,
+                            quantization: pix.quantization as u8,
+                            xfer_func: pix.xfer_func as u8,
+                            ..mem::zeroed()
+                        },
+                    }
+                } else {
+                    v4l2_format__bindgen_ty_1 { pix: (*fmt).into() }
+                },
+            };
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_S_FMT,
+                &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        let count = self.arena.allocate(count)?;
+        self.buf_meta = vec![Metadata::default(); count as usize];
+        self.outstanding = Outstanding::new(count as usize);
+        self.arena_index = 0;
+        self.holding = false;
+        if self.check_size {
+            self.expected_size = self.uncompressed_size()?;
+        }
+
+        self.format()
+    }
+
+    /// Exports a buffer as DMABUF file descriptor
+    ///
+    /// The descriptor can be imported by other drivers (e.g. a display or an encoder) to access
+    /// the frame data without copying it.
+    ///
+    /// The exported descriptor refers to the same memory as the mapped buffer. It keeps the
+    /// memory alive on its own, so it stays valid even after the stream has been dropped. While
+    /// the stream is running, the driver overwrites the contents whenever the buffer is queued,
+    /// so consumers must be done with a frame before the buffer is handed back to the driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Buffer index, e.g. as returned by `dequeue`
+    pub fn export_dmabuf(&self, index: usize) -> io::Result<OwnedFd> {
+        if index >= self.arena.bufs.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "buffer index out of range",
+            ));
+        }
+
+        dmabuf::arena::export(self.handle.fd(), self.buf_type, index as u32, 0)
+    }
+
+    /// Enables or disables the frame size check
+    ///
+    /// For uncompressed formats, every frame must carry exactly as many bytes as the image size
+    /// of the format. Short frames usually indicate bandwidth problems or driver bugs. Frames
+    /// which do not match are counted in [`Stream::stats`]. Compressed and multi-planar formats
+    /// are never checked.
+    ///
+    /// The check is enabled by default. The expected size follows format changes made with
+    /// [`Stream::reconfigure`].
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to check the frame size
+    pub fn check_frame_size(mut self, enable: bool) -> io::Result<Self> {
+        self.check_size = enable;
+        self.expected_size = if enable {
+            self.uncompressed_size()?
+        } else {
+            None
+        };
+        Ok(self)
+    }
+
+    /// Returns the frame statistics of the stream
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Returns the number of frames the driver dropped before the last dequeued one
+    ///
+    /// Computed from the gap between the sequence numbers of the last two dequeued buffers,
+    /// which points to bandwidth problems (e.g. on USB) when it is non-zero. The count starts
+    /// over when the stream is restarted.
+    pub fn dropped_since_last(&self) -> u32 {
+        self.sequence.dropped()
+    }
+
+    fn uncompressed_size(&self) -> io::Result<Option<u32>> {
+        if self.buf_type.is_multiplanar() {
+            // the size of each plane is reported separately, see Metadata::planes
+            return Ok(None);
+        }
+        let fmt = self.format()?;
+        let mut v4l2_desc = v4l2_fmtdesc {
+            type_: self.buf_type as u32,
+            ..unsafe { mem::zeroed() }
+        };
+        loop {
+            let ret = unsafe {
+                v4l2::ioctl(
+                    self.handle.fd(),
+                    v4l2::vidioc::VIDIOC_ENUM_FMT,
+                    &mut v4l2_desc as *mut _ as *mut std::os::raw::c_void,
+                )
+            };
+            if ret.is_err() {
+                // format not enumerated, so we cannot tell
+                return Ok(None);
+            }
+
+            if v4l2_desc.pixelformat == u32::from(fmt.fourcc) {
+                let flags = DescriptionFlags::from(v4l2_desc.flags);
+                if flags.contains(DescriptionFlags::COMPRESSED) {
+                    return Ok(None);
+                }
+                return Ok(Some(fmt.size));
+            }
+            v4l2_desc.index += 1;
+        }
+    }
+
+    /// Maps all buffers again without reallocating them
+    ///
+    /// Some drivers invalidate the buffer mappings when the system suspends, accessing them
+    /// after resume then faults. Since this cannot be detected reliably, applications should
+    /// call this explicitly once they get notified about a resume.
+    ///
+    /// The stream is stopped first and restarted on the next call to fetch a frame.
+    ///
+    /// If a buffer cannot be mapped again, all buffers are released and the same number of
+    /// buffers is allocated anew, so the stream stays usable and the mapping error is returned.
+    /// The metadata of the previous frames is lost in that case. If the allocation fails as well,
+    /// the stream has no buffers left and [`StreamError::BuffersLost`] is returned.
+    pub fn remap(&mut self) -> io::Result<()> {
+        if self.active {
+            StreamTrait::stop(self)?;
+        }
+
+        let count = self.arena.bufs.len() as u32;
+        let error = match self.arena.remap() {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        if !self.arena.bufs.is_empty() {
+            // unmapping failed, the buffers are still in place
+            return Err(error);
+        }
+
+        let restored = self.arena.allocate(count).unwrap_or(0);
+        self.buf_meta = vec![Metadata::default(); restored as usize];
+        self.outstanding = Outstanding::new(restored as usize);
+        self.arena_index = 0;
+        self.holding = false;
+        if restored == 0 {
+            return Err(io::Error::new(error.kind(), StreamError::BuffersLost));
+        }
+        Err(error)
+    }
+
+    fn format(&self) -> io::Result<Format> {
+        unsafe {
+            let mut v4l2_fmt = v4l2_format {
+                type_: self.buf_type as u32,
+                ..mem::zeroed()
+            };
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_G_FMT,
+                &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            if self.buf_type.is_multiplanar() {
+                Ok(Format::from(v4l2_fmt.fmt.pix_mp))
+            } else {
+                Ok(Format::from(v4l2_fmt.fmt.pix))
+            }
+        }
+    }
+
+    /// Returns a buffer and its metadata by index
+    ///
+    /// Meant for custom capture loops, where the index is returned by `dequeue`.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Buffer index
+    pub fn get(&self, index: usize) -> Option<(&[u8], &Metadata)> {
+        Some((self.arena.bufs.get(index)?, self.buf_meta.get(index)?))
+    }
+
+    /// Returns the number of buffers granted by the driver
+    ///
+    /// Drivers may grant fewer (or more) buffers than requested, e.g. on memory constrained
+    /// systems.
+    pub fn buffer_count(&self) -> usize {
+        self.arena.bufs.len()
+    }
+
+    /// Returns a buffer and its metadata for writing
+    pub(crate) fn buffer_mut(&mut self, index: usize) -> (&mut [u8], &mut Metadata) {
+        (&mut *self.arena.bufs[index], &mut self.buf_meta[index])
+    }
+
+    /// Returns all planes of a buffer by index
+    ///
+    /// Frames of multi-planar buffer types (e.g. NV12M) are split across several planes, the
+    /// other stream functions only return the first one. Single-planar buffers consist of exactly
+    /// one plane. The number of bytes used per plane is reported in [`Metadata::planes`].
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Buffer index
+    pub fn planes(&self, index: usize) -> Option<Vec<&[u8]>> {
+        self.arena.buffer_planes(index)
     }
 
     /// Returns the raw device handle
@@ -71,21 +616,197 @@ impl<'a> Stream<'a> {
     }
 
     /// Sets a timeout of the v4l file handle.
+    ///
+    /// Each call to `dequeue` (and thus `next`) waits at most this long for a frame by polling
+    /// the device before issuing `VIDIOC_DQBUF`, then fails with [`io::ErrorKind::TimedOut`].
+    /// The stream remains usable after a timeout. Durations beyond `i32::MAX` milliseconds are
+    /// saturated.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - Maximum time to wait for a frame
     pub fn set_timeout(&mut self, duration: Duration) {
-        self.timeout = Some(duration.as_millis().try_into().unwrap());
+        self.timeout = Some(duration.as_millis().try_into().unwrap_or(i32::MAX));
     }
 
-    /// Clears the timeout of the v4l file handle.
+    /// Clears the timeout of the v4l file handle, dequeueing blocks until a frame arrives.
     pub fn clear_timeout(&mut self) {
         self.timeout = None;
     }
 
-    fn buffer_desc(&self) -> v4l2_buffer {
-        v4l2_buffer {
-            type_: self.buf_type as u32,
-            memory: Memory::Mmap as u32,
-            ..unsafe { mem::zeroed() }
+    /// Fetches a new frame which is re-queued once it is dropped
+    ///
+    /// Unlike `next`, the buffer is not handed back to the driver on the next call but when the
+    /// returned [`Frame`] goes out of scope, so the data cannot be overwritten while it is still
+    /// in use.
+    pub fn next_frame(&mut self) -> io::Result<Frame<'_, 'a>> {
+        self.prepare_next()?;
+
+        let mut index = CaptureStream::dequeue(self)?;
+        while self.discard(index)? {
+            CaptureStream::queue(self, index)?;
+            index = CaptureStream::dequeue(self)?;
         }
+
+        Ok(Frame {
+            stream: self,
+            index,
+            queued: false,
+        })
+    }
+
+    /// Returns the index of the buffer returned by the last call to `next` or `poll_next`
+    pub fn last_index(&self) -> usize {
+        self.arena_index
+    }
+
+    /// Returns the indices of the buffers held by the caller in [`Requeue::Manual`] mode
+    pub fn outstanding(&self) -> Vec<usize> {
+        self.outstanding.indices()
+    }
+
+    /// Hands a buffer held in [`Requeue::Manual`] mode back to the driver
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the buffer is not held by the caller. While
+    /// the stream is stopped, the buffer is only marked as free and queued once the stream is
+    /// started again.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Buffer index, as returned by [`Stream::last_index`]
+    pub fn requeue(&mut self, index: usize) -> io::Result<()> {
+        if self.active && self.outstanding.held(index) {
+            CaptureStream::queue(self, index)?;
+        }
+        self.outstanding.release(index)
+    }
+
+    /// Fetches a new frame or an event, whichever arrives first
+    ///
+    /// Waits for a filled buffer and for pending events on the same file descriptor, so frames
+    /// and e.g. control changes can be handled in a single loop. Events must be subscribed to
+    /// beforehand, either with [`Device::subscribe_event`] or [`Stream::with_source_change_events`].
+    /// Pending events are returned before frames. Once [`FrameOrEvent::SourceChanged`] is
+    /// returned, [`Stream::reconfigure`] must be called before fetching the next frame.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use v4l::buffer::Type;
+    /// use v4l::event;
+    /// use v4l::io::mmap::FrameOrEvent;
+    /// use v4l::prelude::*;
+    /// use v4l::v4l_sys::V4L2_CID_BRIGHTNESS;
+    ///
+    /// let dev = Device::new(0).unwrap();
+    /// dev.subscribe_event(event::Type::Ctrl, V4L2_CID_BRIGHTNESS).unwrap();
+    /// let mut stream = MmapStream::new(&dev, Type::VideoCapture).unwrap();
+    ///
+    /// loop {
+    ///     match stream.next_or_event().unwrap() {
+    ///         FrameOrEvent::Frame(buf, meta) => println!("frame {}: {} bytes", meta.sequence, buf.len()),
+    ///         FrameOrEvent::Event(event) => println!("event: {}", event),
+    ///         FrameOrEvent::SourceChanged(_) => break,
+    ///     }
+    /// }
+    /// ```
+    pub fn next_or_event(&mut self) -> io::Result<FrameOrEvent<'_>> {
+        self.prepare_next()?;
+
+        loop {
+            if self.source_change || self.events {
+                if let Some(event) = event::dequeue(self.handle.fd())? {
+                    return self.on_event(event);
+                }
+            }
+
+            let mut planes = arena::planes();
+            let mut v4l2_buf = self.buffer_desc(&mut planes);
+            match self.dequeue_buffer(&mut v4l2_buf) {
+                Ok(index) => {
+                    self.capture_meta(&v4l2_buf)?;
+                    self.arena_index = index;
+                    if self.discard(index)? {
+                        CaptureStream::queue(self, index)?;
+                        continue;
+                    }
+
+                    self.hold();
+                    return Ok(FrameOrEvent::Frame(
+                        self.arena.bufs[index],
+                        &self.buf_meta[index],
+                    ));
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    if self.handle.is_nonblocking() {
+                        return Err(error);
+                    }
+                    // pending events are signaled as exceptional condition
+                    let revents = self.wait(libc::POLLIN | libc::POLLPRI)?;
+                    self.events |= revents & libc::POLLPRI != 0;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Waits for the given poll events and returns the ones which occurred
+    fn wait(&self, events: i16) -> io::Result<i16> {
+        let mut fd = libc::pollfd {
+            fd: self.handle.fd(),
+            events,
+            revents: 0,
+        };
+        match unsafe { libc::poll(&mut fd, 1, self.timeout.unwrap_or(-1)) } {
+            -1 => Err(io::Error::last_os_error()),
+            0 => Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF")),
+            _ => Ok(fd.revents),
+        }
+    }
+
+    /// Turns a dequeued event into the result of `next_or_event`
+    fn on_event(&self, event: event::Event) -> io::Result<FrameOrEvent<'static>> {
+        match event.payload {
+            event::Payload::SourceChange(changes)
+                if changes.contains(event::SourceChanges::RESOLUTION) =>
+            {
+                Ok(FrameOrEvent::SourceChanged(self.format()?))
+            }
+            _ => Ok(FrameOrEvent::Event(event)),
+        }
+    }
+
+    /// Starts the stream or re-queues the buffer still held from the last call to `next`
+    fn prepare_next(&mut self) -> io::Result<()> {
+        if self.requeue == Requeue::Manual && self.outstanding.starved() {
+            return Err(io::Error::new(io::ErrorKind::Other, StreamError::Starved));
+        }
+
+        if !self.active {
+            // Enqueue all buffers once on stream start, except for the ones still held by the
+            // caller, they are queued by `requeue`
+            for index in self.outstanding.free() {
+                CaptureStream::queue(self, index)?;
+            }
+
+            self.start()?;
+        } else if self.holding {
+            CaptureStream::queue(self, self.arena_index)?;
+        }
+        self.holding = false;
+        Ok(())
+    }
+
+    /// Keeps the dequeued buffer at `arena_index` away from the driver until it is requeued
+    fn hold(&mut self) {
+        match self.requeue {
+            Requeue::Auto => self.holding = true,
+            Requeue::Manual => self.outstanding.hold(self.arena_index),
+        }
+    }
+
+    fn buffer_desc(&self, planes: &mut Planes) -> v4l2_buffer {
+        arena::buffer_desc(self.buf_type, planes)
     }
 
     fn dequeue_buffer(&self, v4l2_buf: &mut v4l2_buffer) -> io::Result<usize> {
@@ -94,10 +815,216 @@ impl<'a> Stream<'a> {
                 self.handle.fd(),
                 v4l2::vidioc::VIDIOC_DQBUF,
                 v4l2_buf as *mut _ as *mut std::os::raw::c_void,
-            )?;
+            )
+            .map_err(error::translate)?;
             Ok(v4l2_buf.index as usize)
         }
     }
+
+    /// Hands an output buffer to the driver without waiting
+    fn queue_output(&mut self, index: usize) -> io::Result<()> {
+        let mut planes = arena::planes();
+        let mut v4l2_buf = v4l2_buffer {
+            index: index as u32,
+            ..self.buffer_desc(&mut planes)
+        };
+        unsafe {
+            // output settings
+            //
+            // MetaData.bytesused is initialized to 0. For an output device, when bytesused is
+            // set to 0 v4l2 will set it to the size of the plane:
+            // https://www.kernel.org/doc/html/v4.15/media/uapi/v4l/buffer.html#struct-v4l2-plane
+            v4l2_buf.bytesused = self.buf_meta[index].bytesused;
+            if self.buf_type.is_multiplanar() {
+                // only the first plane can be partially filled, the others are used in full
+                (*v4l2_buf.m.planes).bytesused = self.buf_meta[index].bytesused;
+            }
+            v4l2_buf.field = match self.field {
+                Some(FieldOrder::Alternate) => {
+                    let field = if self.top_field_next {
+                        FieldOrder::Top
+                    } else {
+                        FieldOrder::Bottom
+                    };
+                    self.top_field_next = !self.top_field_next;
+                    field as u32
+                }
+                Some(field) => field as u32,
+                None => self.buf_meta[index].field,
+            };
+
+            v4l2::ioctl(
+                self.handle.fd(),
+                v4l2::vidioc::VIDIOC_QBUF,
+                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
+            )
+            .map_err(error::translate)
+        }
+    }
+
+    /// Returns true if the frame in a dequeued buffer must not be handed to the caller
+    ///
+    /// Each call consumes one of the initial frames to skip. Fails once too many erroneous frames
+    /// were discarded in a row, the buffer is re-queued in that case.
+    fn discard(&mut self, index: usize) -> io::Result<bool> {
+        if self.skip_remaining > 0 {
+            self.skip_remaining -= 1;
+            return Ok(true);
+        }
+        if !(self.skip_errors && self.buf_meta[index].flags.contains(Flags::ERROR)) {
+            self.skipped_errors = 0;
+            return Ok(false);
+        }
+
+        self.skipped_errors += 1;
+        if self.skipped_errors >= MAX_SKIPPED_ERRORS {
+            self.skipped_errors = 0;
+            CaptureStream::queue(self, index)?;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "too many erroneous frames in a row",
+            ));
+        }
+        Ok(true)
+    }
+
+    fn capture_meta(&mut self, v4l2_buf: &v4l2_buffer) -> io::Result<()> {
+        let wall_clock = match &mut self.wallclock {
+            Some(clock) => {
+                clock.refresh()?;
+                Some(clock.to_system_time(v4l2_buf.timestamp.into()))
+            }
+            None => None,
+        };
+
+        self.stats.frames += 1;
+        self.stats.dropped_frames += u64::from(self.sequence.update(v4l2_buf.sequence));
+        if Flags::from(v4l2_buf.flags).contains(Flags::ERROR) {
+            self.stats.error_frames += 1;
+        }
+        if let Some(size) = self.expected_size {
+            if v4l2_buf.bytesused < size {
+                self.stats.short_frames += 1;
+            } else if v4l2_buf.bytesused > size {
+                self.stats.long_frames += 1;
+            }
+        }
+
+        let (planes, num_planes) = unsafe { PlaneMeta::from_buffer(v4l2_buf) };
+        self.buf_meta[v4l2_buf.index as usize] = Metadata {
+            bytesused: v4l2_buf.bytesused,
+            flags: v4l2_buf.flags.into(),
+            field: v4l2_buf.field,
+            timestamp: v4l2_buf.timestamp.into(),
+            sequence: v4l2_buf.sequence,
+            wall_clock,
+            planes,
+            num_planes,
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<'a> Stream<'a> {
+    /// Fetches a new frame along with its buffer index, without re-queueing any buffer
+    ///
+    /// Unlike [`crate::io::traits::AsyncCaptureStream::poll_next`], the previously returned
+    /// buffer is not handed back to the driver automatically. Once the frame has been processed,
+    /// the buffer must be re-queued by passing the index to [`CaptureStream::queue`], otherwise
+    /// the driver eventually runs out of buffers.
+    ///
+    /// Do not mix this with `next` or `poll_next` on the same stream, those assume that the
+    /// last returned buffer is still owned by the caller and re-queue it.
+    pub async fn poll_next_indexed(&mut self) -> io::Result<(usize, &[u8], Metadata)> {
+        use crate::io::traits::AsyncCaptureStream;
+
+        if !self.active {
+            // Enqueue all buffers once on stream start
+            for index in 0..self.arena.bufs.len() {
+                CaptureStream::queue(self, index)?;
+            }
+
+            self.start()?;
+        }
+
+        let mut index = AsyncCaptureStream::poll_dequeue(self).await?;
+        while self.discard(index)? {
+            CaptureStream::queue(self, index)?;
+            index = AsyncCaptureStream::poll_dequeue(self).await?;
+        }
+        self.arena_index = index;
+
+        Ok((index, self.arena.bufs[index], self.buf_meta[index]))
+    }
+
+    /// Fetches a new frame which is re-queued once it is dropped
+    ///
+    /// Async variant of [`Stream::next_frame`]. The returned [`Frame`] may be held across await
+    /// points, e.g. while the data is being encoded, without the driver overwriting it.
+    pub async fn poll_next_frame(&mut self) -> io::Result<Frame<'_, 'a>> {
+        use crate::io::traits::AsyncCaptureStream;
+
+        self.prepare_next()?;
+
+        let mut index = AsyncCaptureStream::poll_dequeue(self).await?;
+        while self.discard(index)? {
+            CaptureStream::queue(self, index)?;
+            index = AsyncCaptureStream::poll_dequeue(self).await?;
+        }
+
+        Ok(Frame {
+            stream: self,
+            index,
+            queued: false,
+        })
+    }
+
+    /// Fetches a new frame or an event, whichever arrives first
+    ///
+    /// Async variant of [`Stream::next_or_event`].
+    pub async fn poll_next_or_event(&mut self) -> io::Result<FrameOrEvent<'_>> {
+        use tokio::io::{unix::AsyncFd, Interest};
+
+        self.prepare_next()?;
+
+        let interest = Interest::READABLE | Interest::PRIORITY;
+        let async_fd = AsyncFd::with_interest(self.handle.fd(), interest)?;
+        loop {
+            if self.source_change || self.events {
+                if let Some(event) = event::dequeue(self.handle.fd())? {
+                    return self.on_event(event);
+                }
+            }
+
+            let mut planes = arena::planes();
+
+            let mut v4l2_buf = self.buffer_desc(&mut planes);
+            match self.dequeue_buffer(&mut v4l2_buf) {
+                Ok(index) => {
+                    self.capture_meta(&v4l2_buf)?;
+                    self.arena_index = index;
+                    if self.discard(index)? {
+                        CaptureStream::queue(self, index)?;
+                        continue;
+                    }
+
+                    self.hold();
+                    return Ok(FrameOrEvent::Frame(
+                        self.arena.bufs[index],
+                        &self.buf_meta[index],
+                    ));
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    let mut guard = async_fd.ready(interest).await?;
+                    self.events |= guard.ready().is_priority();
+                    guard.clear_ready();
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
 }
 
 impl<'a> Drop for Stream<'a> {
@@ -122,6 +1049,12 @@ impl<'a> StreamTrait for Stream<'a> {
     type Item = [u8];
 
     fn start(&mut self) -> io::Result<()> {
+        if let Some(clock) = &mut self.wallclock {
+            clock.resample()?;
+        }
+        self.top_field_next = true;
+        self.skipped_errors = 0;
+
         unsafe {
             let mut typ = self.buf_type as u32;
             v4l2::ioctl(
@@ -131,6 +1064,8 @@ impl<'a> StreamTrait for Stream<'a> {
             )?;
         }
 
+        self.skip_remaining = self.skip_initial;
+        self.sequence.reset();
         self.active = true;
         Ok(())
     }
@@ -152,9 +1087,10 @@ impl<'a> StreamTrait for Stream<'a> {
 
 impl<'a, 'b> CaptureStream<'b> for Stream<'a> {
     fn queue(&mut self, index: usize) -> io::Result<()> {
+        let mut planes = arena::planes();
         let mut v4l2_buf = v4l2_buffer {
             index: index as u32,
-            ..self.buffer_desc()
+            ..self.buffer_desc(&mut planes)
         };
 
         unsafe {
@@ -162,16 +1098,20 @@ impl<'a, 'b> CaptureStream<'b> for Stream<'a> {
                 self.handle.fd(),
                 v4l2::vidioc::VIDIOC_QBUF,
                 &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
-            )?;
+            )
+            .map_err(error::translate)?;
         }
 
         Ok(())
     }
 
     fn dequeue(&mut self) -> io::Result<usize> {
-        let mut v4l2_buf = self.buffer_desc();
+        let mut planes = arena::planes();
+        let mut v4l2_buf = self.buffer_desc(&mut planes);
 
-        if self.handle.poll(libc::POLLIN, self.timeout.unwrap_or(-1))? == 0 {
+        if !self.handle.is_nonblocking()
+            && self.handle.poll(libc::POLLIN, self.timeout.unwrap_or(-1))? == 0
+        {
             // This condition can only happen if there was a timeout.
             // A timeout is only possible if the `timeout` value is non-zero, meaning we should
             // propagate it to the caller.
@@ -180,31 +1120,20 @@ impl<'a, 'b> CaptureStream<'b> for Stream<'a> {
 
         self.dequeue_buffer(&mut v4l2_buf)?;
         self.arena_index = v4l2_buf.index as usize;
-
-        self.buf_meta[self.arena_index] = Metadata {
-            bytesused: v4l2_buf.bytesused,
-            flags: v4l2_buf.flags.into(),
-            field: v4l2_buf.field,
-            timestamp: v4l2_buf.timestamp.into(),
-            sequence: v4l2_buf.sequence,
-        };
+        self.capture_meta(&v4l2_buf)?;
 
         Ok(self.arena_index)
     }
 
     fn next(&'b mut self) -> io::Result<(&Self::Item, &Metadata)> {
-        if !self.active {
-            // Enqueue all buffers once on stream start
-            for index in 0..self.arena.bufs.len() {
-                CaptureStream::queue(self, index)?;
-            }
+        self.prepare_next()?;
 
-            self.start()?;
-        } else {
+        self.arena_index = CaptureStream::dequeue(self)?;
+        while self.discard(self.arena_index)? {
             CaptureStream::queue(self, self.arena_index)?;
+            self.arena_index = CaptureStream::dequeue(self)?;
         }
-
-        self.arena_index = CaptureStream::dequeue(self)?;
+        self.hold();
 
         // The index used to access the buffer elements is given to us by v4l2, so we assume it
         // will always be valid.
@@ -216,50 +1145,38 @@ impl<'a, 'b> CaptureStream<'b> for Stream<'a> {
 
 impl<'a, 'b> OutputStream<'b> for Stream<'a> {
     fn queue(&mut self, index: usize) -> io::Result<()> {
-        let mut v4l2_buf = v4l2_buffer {
-            index: index as u32,
-            ..self.buffer_desc()
-        };
-        unsafe {
-            // output settings
-            //
-            // MetaData.bytesused is initialized to 0. For an output device, when bytesused is
-            // set to 0 v4l2 will set it to the size of the plane:
-            // https://www.kernel.org/doc/html/v4.15/media/uapi/v4l/buffer.html#struct-v4l2-plane
-            v4l2_buf.bytesused = self.buf_meta[index].bytesused;
-            v4l2_buf.field = self.buf_meta[index].field;
-
-            if self
+        if !self.handle.is_nonblocking()
+            && self
                 .handle
                 .poll(libc::POLLOUT, self.timeout.unwrap_or(-1))?
                 == 0
-            {
-                // This condition can only happen if there was a timeout.
-                // A timeout is only possible if the `timeout` value is non-zero, meaning we should
-                // propagate it to the caller.
-                return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_QBUF"));
-            }
-
-            v4l2::ioctl(
-                self.handle.fd(),
-                v4l2::vidioc::VIDIOC_QBUF,
-                &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
-            )
+        {
+            // This condition can only happen if there was a timeout.
+            // A timeout is only possible if the `timeout` value is non-zero, meaning we should
+            // propagate it to the caller.
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_QBUF"));
         }
+
+        self.queue_output(index)
     }
 
     fn dequeue(&mut self) -> io::Result<usize> {
-        let mut v4l2_buf = self.buffer_desc();
+        let mut planes = arena::planes();
+        let mut v4l2_buf = self.buffer_desc(&mut planes);
 
         self.dequeue_buffer(&mut v4l2_buf)?;
         self.arena_index = v4l2_buf.index as usize;
 
+        let (planes, num_planes) = unsafe { PlaneMeta::from_buffer(&v4l2_buf) };
         self.buf_meta[self.arena_index] = Metadata {
             bytesused: v4l2_buf.bytesused,
             flags: v4l2_buf.flags.into(),
             field: v4l2_buf.field,
             timestamp: v4l2_buf.timestamp.into(),
             sequence: v4l2_buf.sequence,
+            wall_clock: None,
+            planes,
+            num_planes,
         };
 
         Ok(self.arena_index)
@@ -289,17 +1206,25 @@ impl<'a, 'b> OutputStream<'b> for Stream<'a> {
 
 #[cfg(feature = "tokio")]
 impl<'a, 'b> crate::io::traits::AsyncCaptureStream<'b> for Stream<'a> {
-    async fn poll_dequeue(&mut self) -> io::Result<usize> {
+    async fn ready(&self) -> io::Result<()> {
         use tokio::io::unix::AsyncFd;
-        let mut v4l2_buf = self.buffer_desc();
+        let async_fd = AsyncFd::new(self.handle.fd())?;
+
+        let _ = core::future::poll_fn(|cx| async_fd.poll_read_ready(cx)).await?;
+        Ok(())
+    }
+
+    async fn poll_dequeue(&mut self) -> io::Result<usize> {
+        let mut planes = arena::planes();
+        let mut v4l2_buf = self.buffer_desc(&mut planes);
         loop {
             match self.dequeue_buffer(&mut v4l2_buf) {
-                Ok(index) => return Ok(index),
+                Ok(index) => {
+                    self.capture_meta(&v4l2_buf)?;
+                    return Ok(index);
+                }
                 Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {
-                    let fd = self.handle.fd();
-                    let async_fd = AsyncFd::new(fd)?;
-
-                    let _ = core::future::poll_fn(|cx| async_fd.poll_read_ready(cx)).await?;
+                    self.ready().await?;
                 }
                 Err(error) => return Err(error),
             }
@@ -307,19 +1232,16 @@ impl<'a, 'b> crate::io::traits::AsyncCaptureStream<'b> for Stream<'a> {
     }
 
     async fn poll_next(&'b mut self) -> io::Result<(&Self::Item, &Metadata)> {
-        if !self.active {
-            // Enqueue all buffers once on stream start
-            for index in 0..self.arena.bufs.len() {
-                <Self as CaptureStream>::queue(self, index)?;
-            }
+        // a buffer requeued here must not be queued again if this future is cancelled
+        self.prepare_next()?;
 
-            self.start()?;
-        } else {
+        self.arena_index = self.poll_dequeue().await?;
+        while self.discard(self.arena_index)? {
             let index = self.arena_index;
             <Self as CaptureStream>::queue(self, index)?;
+            self.arena_index = self.poll_dequeue().await?;
         }
-
-        self.arena_index = self.poll_dequeue().await?;
+        self.hold();
 
         // The index used to access the buffer elements is given to us by v4l2, so we assume it
         // will always be valid.
@@ -328,3 +1250,95 @@ impl<'a, 'b> crate::io::traits::AsyncCaptureStream<'b> for Stream<'a> {
         Ok((bytes, meta))
     }
 }
+
+#[cfg(feature = "tokio")]
+impl<'a, 'b> crate::io::traits::AsyncOutputStream<'b> for Stream<'a> {
+    async fn ready(&self) -> io::Result<()> {
+        use tokio::io::unix::AsyncFd;
+        let async_fd = AsyncFd::new(self.handle.fd())?;
+
+        let _ = core::future::poll_fn(|cx| async_fd.poll_write_ready(cx)).await?;
+        Ok(())
+    }
+
+    async fn poll_dequeue(&mut self) -> io::Result<usize> {
+        loop {
+            match OutputStream::dequeue(self) {
+                Ok(index) => return Ok(index),
+                Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {
+                    crate::io::traits::AsyncOutputStream::ready(self).await?;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    async fn poll_write_next(&'b mut self) -> io::Result<(&mut Self::Item, &mut Metadata)> {
+        use crate::io::traits::AsyncOutputStream;
+
+        if !self.active {
+            self.start()?;
+            self.unqueued = self.arena.bufs.len();
+        } else if self.holding {
+            self.queue_output(self.arena_index)?;
+        }
+        // a buffer queued here must not be queued again if this future is cancelled
+        self.holding = false;
+
+        self.arena_index = if self.unqueued > 0 {
+            self.unqueued -= 1;
+            self.arena.bufs.len() - self.unqueued - 1
+        } else {
+            AsyncOutputStream::poll_dequeue(self).await?
+        };
+        self.holding = true;
+
+        // The index used to access the buffer elements is given to us by v4l2, so we assume it
+        // will always be valid.
+        let bytes = &mut self.arena.bufs[self.arena_index];
+        let meta = &mut self.buf_meta[self.arena_index];
+        Ok((bytes, meta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outstanding() {
+        let mut outstanding = Outstanding::new(3);
+        assert!(outstanding.indices().is_empty());
+        assert!(!outstanding.starved());
+
+        outstanding.hold(0);
+        outstanding.hold(2);
+        assert_eq!(vec![0, 2], outstanding.indices());
+        // the held buffers are not queued again when the stream is restarted
+        assert_eq!(vec![1], outstanding.free());
+
+        outstanding.hold(1);
+        assert!(outstanding.starved());
+        assert!(outstanding.free().is_empty());
+
+        outstanding.release(1).unwrap();
+        assert!(!outstanding.starved());
+        assert_eq!(vec![0, 2], outstanding.indices());
+    }
+
+    #[test]
+    fn test_outstanding_release() {
+        let mut outstanding = Outstanding::new(2);
+        outstanding.hold(0);
+
+        let e = outstanding.release(1).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+        let e = outstanding.release(5).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+
+        outstanding.release(0).unwrap();
+        // a buffer can only be handed back once
+        assert!(outstanding.release(0).is_err());
+        assert_eq!(vec![0, 1], outstanding.free());
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+use crate::buffer::{Flags, Metadata};
+use crate::io::traits::{CaptureStream, Stream as StreamTrait};
+use crate::timestamp::Timestamp;
+
+/// In-memory capture stream serving canned frames
+///
+/// Stands in for a device stream in tests, e.g. to check code built on [`CaptureStream`]
+/// without any hardware. Frames are returned in the order they were pushed, along with
+/// metadata as a driver would fill it in: sequence numbers count up from zero and timestamps
+/// advance by a fixed frame interval. Dropped frames (sequence gaps) and frames flagged with
+/// `V4L2_BUF_FLAG_ERROR` can be injected. Once all frames have been consumed, dequeueing fails
+/// with [`io::ErrorKind::TimedOut`] like a device stream with a timeout.
+///
+/// # Example
+///
+/// ```
+/// use v4l::buffer::Flags;
+/// use v4l::io::mock::Stream;
+/// use v4l::io::traits::CaptureStream;
+///
+/// let mut stream = Stream::new();
+/// stream.push_frame(vec![0; 16]);
+/// stream.drop_frames(2);
+/// stream.push_error_frame(vec![0; 8]);
+///
+/// let (buf, meta) = stream.next().unwrap();
+/// assert_eq!((16, 0), (buf.len(), meta.sequence));
+/// let (buf, meta) = stream.next().unwrap();
+/// assert_eq!((8, 3), (buf.len(), meta.sequence));
+/// assert!(meta.flags.contains(Flags::ERROR));
+/// assert!(stream.next().is_err());
+/// ```
+pub struct Stream {
+    frames: VecDeque<(Vec<u8>, Metadata)>,
+    buf: Vec<u8>,
+    meta: Metadata,
+    sequence: u32,
+    interval: Duration,
+    active: bool,
+}
+
+impl Stream {
+    /// Returns an empty stream with a frame interval of 1/30 s
+    pub fn new() -> Self {
+        Stream {
+            frames: VecDeque::new(),
+            buf: Vec::new(),
+            meta: Metadata::default(),
+            sequence: 0,
+            interval: Duration::from_nanos(1_000_000_000 / 30),
+            active: false,
+        }
+    }
+
+    /// Sets the time between the timestamps of consecutive frames
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Frame interval, applies to frames pushed afterwards
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Appends a frame
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Frame data
+    pub fn push_frame(&mut self, data: Vec<u8>) {
+        self.push(data, Flags::empty());
+    }
+
+    /// Appends a frame which the driver flagged as erroneous (`V4L2_BUF_FLAG_ERROR`)
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Frame data
+    pub fn push_error_frame(&mut self, data: Vec<u8>) {
+        self.push(data, Flags::ERROR);
+    }
+
+    /// Skips sequence numbers as if the driver had dropped frames
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Number of dropped frames before the next pushed one
+    pub fn drop_frames(&mut self, count: u32) {
+        self.sequence += count;
+    }
+
+    /// Returns the number of frames which have not been fetched yet
+    pub fn pending(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn push(&mut self, data: Vec<u8>, flags: Flags) {
+        let meta = Metadata {
+            bytesused: data.len() as u32,
+            flags: flags | Flags::DONE,
+            timestamp: Timestamp::default() + self.interval * self.sequence,
+            sequence: self.sequence,
+            ..Metadata::default()
+        };
+        self.sequence += 1;
+        self.frames.push_back((data, meta));
+    }
+}
+
+impl Default for Stream {
+    fn default() -> Self {
+        Stream::new()
+    }
+}
+
+impl StreamTrait for Stream {
+    type Item = [u8];
+
+    fn start(&mut self) -> io::Result<()> {
+        self.active = true;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        self.active = false;
+        Ok(())
+    }
+}
+
+impl<'a> CaptureStream<'a> for Stream {
+    /// There are no driver buffers to be queued, so this does nothing
+    fn queue(&mut self, _index: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Takes the next frame, the index of the single buffer is always zero
+    fn dequeue(&mut self) -> io::Result<usize> {
+        let (buf, meta) = self
+            .frames
+            .pop_front()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF"))?;
+        self.buf = buf;
+        self.meta = meta;
+        Ok(0)
+    }
+
+    fn next(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        if !self.active {
+            self.start()?;
+        }
+
+        CaptureStream::dequeue(self)?;
+        Ok((&self.buf, &self.meta))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<'a> crate::io::traits::AsyncCaptureStream<'a> for Stream {
+    /// Canned frames are always ready
+    async fn ready(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    async fn poll_dequeue(&mut self) -> io::Result<usize> {
+        CaptureStream::dequeue(self)
+    }
+
+    async fn poll_next(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        CaptureStream::next(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::stats::StreamStats;
+
+    #[test]
+    fn test_stream() {
+        let mut stream = Stream::new().with_interval(Duration::from_millis(10));
+        stream.push_frame(vec![1; 4]);
+        stream.drop_frames(1);
+        stream.push_error_frame(vec![2; 2]);
+        assert_eq!(2, stream.pending());
+
+        let (buf, meta) = stream.next().unwrap();
+        assert_eq!(&[1; 4], buf);
+        assert_eq!(0, meta.sequence);
+        assert!(!meta.flags.contains(Flags::ERROR));
+
+        let (buf, meta) = stream.next().unwrap();
+        assert_eq!(&[2; 2], buf);
+        assert_eq!(2, meta.sequence);
+        assert!(meta.flags.contains(Flags::ERROR));
+        assert_eq!(Timestamp::new(0, 20_000), meta.timestamp);
+
+        assert_eq!(io::ErrorKind::TimedOut, stream.next().err().unwrap().kind());
+    }
+
+    #[test]
+    fn test_stream_stats() {
+        let mut stream = Stream::new();
+        for i in 0..10 {
+            if i == 5 {
+                stream.drop_frames(3);
+            }
+            stream.push_frame(vec![0; 1024]);
+        }
+
+        let mut stats = StreamStats::new(stream);
+        while stats.next().is_ok() {}
+
+        let snapshot = stats.snapshot();
+        assert_eq!(10, snapshot.frames);
+        assert_eq!(3, snapshot.dropped);
+        assert_eq!(10 * 1024, snapshot.bytes);
+    }
+}
@@ -1,4 +1,67 @@
+use std::io;
+
 pub mod traits;
 
+pub mod error;
+pub use error::StreamError;
+
+pub mod dmabuf;
 pub mod mmap;
+pub mod mock;
+pub mod read;
 pub mod userptr;
+
+pub mod builder;
+
+pub mod latency;
+pub mod recorder;
+pub mod stats;
+
+mod sequence;
+pub(crate) use sequence::SequenceGap;
+
+pub mod select;
+pub use select::Selector;
+
+#[cfg(feature = "tokio")]
+pub mod tee;
+
+pub mod sync;
+
+/// Fails if the driver granted no buffers at all for a non-zero request
+///
+/// Drivers may grant fewer buffers than requested, but streaming is impossible without any.
+pub(crate) fn check_granted_buffers(requested: u32, granted: u32) -> io::Result<()> {
+    if requested > 0 && granted == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            format!(
+                "VIDIOC_REQBUFS: driver granted no buffers ({} requested)",
+                requested
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Fails if `count` buffers of `size` bytes exceed the memory budget
+pub(crate) fn check_buffer_budget(count: u32, size: usize, max: Option<usize>) -> io::Result<()> {
+    let max = match max {
+        Some(max) => max,
+        None => return Ok(()),
+    };
+
+    let total = (count as usize).saturating_mul(size);
+    if total > max {
+        return Err(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            format!(
+                "{} buffers of {} bytes exceed the buffer budget of {} bytes",
+                count, size, max
+            ),
+        ));
+    }
+
+    Ok(())
+}
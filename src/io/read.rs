@@ -0,0 +1,195 @@
+use std::convert::TryInto;
+use std::time::Duration;
+use std::{io, sync::Arc};
+
+use crate::buffer::Metadata;
+use crate::capability::Flags as CapabilityFlags;
+use crate::device::{Device, Handle};
+use crate::io::error;
+use crate::io::traits::{CaptureStream, Stream as StreamTrait};
+use crate::timestamp::{clock_gettime, Timestamp};
+use crate::video::Capture;
+
+/// Stream of frames read through plain `read()` calls
+///
+/// Fallback for devices which support read/write I/O but no streaming I/O. Each frame is copied
+/// into a single buffer owned by the stream, which is sized for the format active when the
+/// stream is created. There are no driver timestamps, frames are stamped with
+/// `CLOCK_MONOTONIC` once they are read, and sequence numbers are counted by the stream.
+///
+/// The yielded slice only holds the data of the frame, i.e. its length equals
+/// [`Metadata::bytesused`].
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::io::read::Stream;
+/// use v4l::io::traits::CaptureStream;
+/// use v4l::prelude::*;
+///
+/// let dev = Device::new(0).unwrap();
+/// let mut stream = Stream::new(&dev).unwrap();
+/// let (buf, meta) = stream.next().unwrap();
+/// println!("frame {}: {} bytes", meta.sequence, buf.len());
+/// ```
+pub struct Stream {
+    handle: Arc<Handle>,
+    buf: Vec<u8>,
+    meta: Metadata,
+    sequence: u32,
+    timeout: Option<i32>,
+    active: bool,
+}
+
+impl Stream {
+    /// Returns a stream reading frames of the active capture format
+    ///
+    /// Fails with [`io::ErrorKind::Unsupported`] if the device does not support read/write I/O.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Capture device ref to get its file descriptor
+    pub fn new(dev: &Device) -> io::Result<Self> {
+        let caps = dev.query_caps()?;
+        if !caps.capabilities.contains(CapabilityFlags::READ_WRITE) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "device does not support read/write I/O",
+            ));
+        }
+
+        let format = Capture::format(dev)?;
+        Ok(Stream {
+            handle: dev.handle(),
+            buf: vec![0; format.size as usize],
+            meta: Metadata::default(),
+            sequence: 0,
+            timeout: None,
+            active: false,
+        })
+    }
+
+    /// Returns the raw device handle
+    pub fn handle(&self) -> Arc<Handle> {
+        self.handle.clone()
+    }
+
+    /// Sets a timeout of the v4l file handle.
+    ///
+    /// Each call to `dequeue` (and thus `next`) waits at most this long for a frame, then fails
+    /// with [`io::ErrorKind::TimedOut`]. Durations beyond `i32::MAX` milliseconds are saturated.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - Maximum time to wait for a frame
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.timeout = Some(duration.as_millis().try_into().unwrap_or(i32::MAX));
+    }
+
+    /// Clears the timeout of the v4l file handle, reading blocks until a frame arrives.
+    pub fn clear_timeout(&mut self) {
+        self.timeout = None;
+    }
+
+    /// Reads a frame into the buffer, fails with [`io::ErrorKind::WouldBlock`] if none is ready
+    fn read_frame(&mut self) -> io::Result<()> {
+        let ret = unsafe {
+            libc::read(
+                self.handle.fd(),
+                self.buf.as_mut_ptr() as *mut std::os::raw::c_void,
+                self.buf.len(),
+            )
+        };
+        if ret == -1 {
+            return Err(error::translate(io::Error::last_os_error()));
+        }
+
+        self.meta = Metadata {
+            bytesused: ret as u32,
+            timestamp: Timestamp::from(clock_gettime(libc::CLOCK_MONOTONIC)?),
+            sequence: self.sequence,
+            ..Metadata::default()
+        };
+        self.sequence = self.sequence.wrapping_add(1);
+        Ok(())
+    }
+}
+
+impl StreamTrait for Stream {
+    type Item = [u8];
+
+    /// Reading the first frame starts the capture implicitly
+    fn start(&mut self) -> io::Result<()> {
+        self.active = true;
+        Ok(())
+    }
+
+    /// Frames captured in the meantime are dropped by the driver
+    fn stop(&mut self) -> io::Result<()> {
+        self.active = false;
+        Ok(())
+    }
+}
+
+impl<'a> CaptureStream<'a> for Stream {
+    /// There are no buffers to be queued, so this does nothing
+    fn queue(&mut self, _index: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Reads a frame, the index of the single buffer is always zero
+    fn dequeue(&mut self) -> io::Result<usize> {
+        if !self.handle.is_nonblocking()
+            && self.handle.poll(libc::POLLIN, self.timeout.unwrap_or(-1))? == 0
+        {
+            // This condition can only happen if there was a timeout.
+            // A timeout is only possible if the `timeout` value is non-zero, meaning we should
+            // propagate it to the caller.
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "read"));
+        }
+
+        self.read_frame()?;
+        Ok(0)
+    }
+
+    fn next(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        if !self.active {
+            self.start()?;
+        }
+
+        CaptureStream::dequeue(self)?;
+        Ok((&self.buf[..self.meta.bytesused as usize], &self.meta))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<'a> crate::io::traits::AsyncCaptureStream<'a> for Stream {
+    async fn ready(&self) -> io::Result<()> {
+        use tokio::io::unix::AsyncFd;
+        let async_fd = AsyncFd::new(self.handle.fd())?;
+
+        let _ = core::future::poll_fn(|cx| async_fd.poll_read_ready(cx)).await?;
+        Ok(())
+    }
+
+    async fn poll_dequeue(&mut self) -> io::Result<usize> {
+        loop {
+            match self.read_frame() {
+                Ok(()) => return Ok(0),
+                Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {
+                    self.ready().await?;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    async fn poll_next(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        if !self.active {
+            self.start()?;
+        }
+
+        self.poll_dequeue().await?;
+        Ok((&self.buf[..self.meta.bytesused as usize], &self.meta))
+    }
+}
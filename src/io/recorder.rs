@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::buffer::Metadata;
+use crate::format::Format;
+
+/// Records raw frames to disk
+///
+/// Frame data is appended to a single file as-is, no matter the pixelformat. For each frame, a
+/// line is appended to a CSV sidecar file which holds the capture timing information:
+///
+/// `frame_index,sequence,timestamp_us,bytesused`
+///
+/// The `frame_index` counts the frames written by this recorder, so a frame can be located in
+/// the video file by summing up the `bytesused` values of all preceding frames.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::io::recorder::RawRecorder;
+/// use v4l::io::traits::CaptureStream;
+/// use v4l::prelude::*;
+/// use v4l::video::Capture;
+///
+/// let dev = Device::new(0).unwrap();
+/// let format = dev.format().unwrap();
+/// let mut stream = MmapStream::new(&dev, Type::VideoCapture).unwrap();
+/// let mut recorder = RawRecorder::new("capture.raw", "capture.csv", format).unwrap();
+///
+/// for _ in 0..10 {
+///     let (buf, meta) = stream.next().unwrap();
+///     recorder.write(buf, meta).unwrap();
+/// }
+/// recorder.flush().unwrap();
+/// ```
+pub struct RawRecorder {
+    video: BufWriter<File>,
+    meta: BufWriter<File>,
+    format: Format,
+    frame_index: u64,
+}
+
+impl RawRecorder {
+    /// Returns a recorder writing to the given files
+    ///
+    /// Existing files are truncated.
+    ///
+    /// # Arguments
+    ///
+    /// * `video_path` - File receiving the raw frame data
+    /// * `meta_path` - CSV file receiving the per-frame metadata
+    /// * `format` - Format of the recorded frames
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>>(
+        video_path: P,
+        meta_path: Q,
+        format: Format,
+    ) -> io::Result<Self> {
+        let video = BufWriter::new(File::create(video_path)?);
+        let mut meta = BufWriter::new(File::create(meta_path)?);
+        writeln!(meta, "frame_index,sequence,timestamp_us,bytesused")?;
+
+        Ok(RawRecorder {
+            video,
+            meta,
+            format,
+            frame_index: 0,
+        })
+    }
+
+    /// Returns the format of the recorded frames
+    pub fn format(&self) -> &Format {
+        &self.format
+    }
+
+    /// Returns the number of frames written so far
+    pub fn frames(&self) -> u64 {
+        self.frame_index
+    }
+
+    /// Appends a frame to the recording
+    ///
+    /// Only the first `bytesused` bytes of the buffer are written. Drivers which do not report
+    /// `bytesused` (i.e. set it to zero) get the whole buffer written instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - Frame data
+    /// * `meta` - Metadata of the frame
+    pub fn write(&mut self, buf: &[u8], meta: &Metadata) -> io::Result<()> {
+        let len = match meta.bytesused as usize {
+            0 => buf.len(),
+            n => n.min(buf.len()),
+        };
+        self.video.write_all(&buf[..len])?;
+
+        let timestamp_us = Duration::from(meta.timestamp).as_micros();
+        writeln!(
+            self.meta,
+            "{},{},{},{}",
+            self.frame_index, meta.sequence, timestamp_us, len
+        )?;
+
+        self.frame_index += 1;
+        Ok(())
+    }
+
+    /// Flushes all buffered data to disk
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.video.flush()?;
+        self.meta.flush()
+    }
+}
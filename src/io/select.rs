@@ -0,0 +1,127 @@
+use std::convert::TryInto;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::device::Handle;
+
+/// Waits for any of several devices to have a buffer ready
+///
+/// Allows capturing from multiple cameras in a single thread. The streams need to be started
+/// with buffers queued, a stream which is not streaming is reported as ready right away and its
+/// dequeue fails.
+///
+/// Ready devices are reported in turn, so a device which always has a buffer ready does not
+/// starve the others.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::io::traits::{CaptureStream, Stream};
+/// use v4l::io::Selector;
+/// use v4l::prelude::*;
+///
+/// let devs = vec![Device::new(0).unwrap(), Device::new(1).unwrap()];
+/// let mut streams = devs
+///     .iter()
+///     .map(|dev| MmapStream::with_buffers(dev, Type::VideoCapture, 4).unwrap())
+///     .collect::<Vec<_>>();
+///
+/// let mut selector = Selector::new();
+/// for stream in &mut streams {
+///     for index in 0..4 {
+///         CaptureStream::queue(stream, index).unwrap();
+///     }
+///     stream.start().unwrap();
+///     selector.add(stream.handle());
+/// }
+///
+/// loop {
+///     let ready = selector.select(None).unwrap().unwrap();
+///     let index = CaptureStream::dequeue(&mut streams[ready]).unwrap();
+///     let (buf, meta) = streams[ready].get(index).unwrap();
+///     println!("camera {}: frame {} ({} bytes)", ready, meta.sequence, buf.len());
+///     CaptureStream::queue(&mut streams[ready], index).unwrap();
+/// }
+/// ```
+#[derive(Default)]
+pub struct Selector {
+    handles: Vec<Arc<Handle>>,
+    /// Index of the device to check first on the next call
+    next: usize,
+}
+
+impl Selector {
+    /// Returns an empty selector
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a device and returns its index
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - Device handle, e.g. obtained from a stream
+    pub fn add(&mut self, handle: Arc<Handle>) -> usize {
+        self.handles.push(handle);
+        self.handles.len() - 1
+    }
+
+    /// Returns the number of devices
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Returns true if no device was added
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// Waits until a device has a buffer ready and returns its index
+    ///
+    /// Returns `None` if the timeout expired before any device became ready.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Maximum time to wait, `None` waits forever
+    pub fn select(&mut self, timeout: Option<Duration>) -> io::Result<Option<usize>> {
+        if self.handles.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no devices to select from",
+            ));
+        }
+
+        let timeout = match timeout {
+            Some(timeout) => timeout.as_millis().try_into().unwrap_or(i32::MAX),
+            None => -1,
+        };
+        let mut fds = self
+            .handles
+            .iter()
+            .map(|handle| libc::pollfd {
+                fd: handle.fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect::<Vec<_>>();
+
+        let ret = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+        match ret {
+            -1 => Err(io::Error::last_os_error()),
+            0 => Ok(None),
+            _ => {
+                // errors are reported as ready, so the following dequeue surfaces them
+                let count = fds.len();
+                let ready = (0..count)
+                    .map(|i| (self.next + i) % count)
+                    .find(|&i| fds[i].revents != 0);
+                if let Some(index) = ready {
+                    self.next = (index + 1) % count;
+                }
+                Ok(ready)
+            }
+        }
+    }
+}
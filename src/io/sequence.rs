@@ -0,0 +1,53 @@
+/// Tracks the sequence numbers of dequeued buffers to detect dropped frames
+///
+/// Drivers increment `v4l2_buffer.sequence` for every frame they capture, including the ones
+/// they have to skip because no buffer was queued or the bus ran out of bandwidth.
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct SequenceGap {
+    last: Option<u32>,
+    dropped: u32,
+}
+
+impl SequenceGap {
+    /// Records the sequence number of a new frame and returns the number of frames missing
+    /// since the previous one
+    pub fn update(&mut self, sequence: u32) -> u32 {
+        self.dropped = match self.last {
+            // the sequence only goes backwards if the driver restarted counting
+            Some(last) => sequence.saturating_sub(last).saturating_sub(1),
+            None => 0,
+        };
+        self.last = Some(sequence);
+        self.dropped
+    }
+
+    /// Returns the number of frames missing between the last two frames
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Forgets the last sequence number, the driver counts from zero after a restart
+    pub fn reset(&mut self) {
+        *self = SequenceGap::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        let mut gap = SequenceGap::default();
+        assert_eq!(0, gap.update(7));
+        assert_eq!(0, gap.update(8));
+        assert_eq!(2, gap.update(11));
+        assert_eq!(2, gap.dropped());
+        assert_eq!(0, gap.update(3));
+        assert_eq!(1, gap.update(5));
+
+        gap.reset();
+        assert_eq!(0, gap.dropped());
+        assert_eq!(0, gap.update(0));
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    fmt, io,
+    time::{Duration, Instant},
+};
+
+use crate::buffer::Metadata;
+use crate::io::traits::{CaptureStream, Stream as StreamTrait};
+use crate::io::SequenceGap;
+
+/// Throughput figures of a capture stream
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Stats {
+    /// Number of frames received
+    pub frames: u64,
+    /// Number of frames the driver dropped, derived from gaps in the sequence numbers
+    pub dropped: u64,
+    /// Number of bytes received
+    pub bytes: u64,
+    /// Time between the first and the last frame
+    pub elapsed: Duration,
+    /// Frame rate derived from the interval between the last two frames
+    pub fps: f64,
+    /// Frame rate averaged over all frames
+    pub avg_fps: f64,
+    /// Data rate averaged over all frames, in MiB per second
+    pub mb_per_sec: f64,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames ({} dropped), {:.2} fps (avg {:.2} fps), {:.2} MB/s",
+            self.frames, self.dropped, self.fps, self.avg_fps, self.mb_per_sec
+        )
+    }
+}
+
+/// Accumulates the statistics of single frames
+#[derive(Debug, Default, Clone)]
+struct Counter {
+    frames: u64,
+    dropped: u64,
+    bytes: u64,
+    first: Option<Instant>,
+    last: Option<Instant>,
+    interval: Option<Duration>,
+    sequence: SequenceGap,
+}
+
+impl Counter {
+    fn record(&mut self, bytes: usize, sequence: u32, now: Instant) {
+        self.dropped += u64::from(self.sequence.update(sequence));
+        if let Some(last) = self.last {
+            self.interval = Some(now.saturating_duration_since(last));
+        }
+
+        self.frames += 1;
+        self.bytes += bytes as u64;
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+    }
+
+    fn snapshot(&self) -> Stats {
+        let elapsed = match (self.first, self.last) {
+            (Some(first), Some(last)) => last.saturating_duration_since(first),
+            _ => Duration::ZERO,
+        };
+        let secs = elapsed.as_secs_f64();
+        let (avg_fps, mb_per_sec) = if secs > 0.0 {
+            (
+                (self.frames - 1) as f64 / secs,
+                self.bytes as f64 / 1_048_576.0 / secs,
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        let fps = match self.interval {
+            Some(interval) if !interval.is_zero() => 1.0 / interval.as_secs_f64(),
+            _ => 0.0,
+        };
+
+        Stats {
+            frames: self.frames,
+            dropped: self.dropped,
+            bytes: self.bytes,
+            elapsed,
+            fps,
+            avg_fps,
+            mb_per_sec,
+        }
+    }
+}
+
+/// Collects throughput statistics of the frames passing through a capture stream
+///
+/// Every frame returned by `next` (or `poll_next`) is counted, along with its size and arrival
+/// time. Frames which the driver dropped are detected by gaps in the sequence numbers.
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::io::stats::StreamStats;
+/// use v4l::io::traits::CaptureStream;
+/// use v4l::prelude::*;
+///
+/// let dev = Device::new(0).unwrap();
+/// let stream = MmapStream::new(&dev, Type::VideoCapture).unwrap();
+/// let mut stream = StreamStats::new(stream);
+///
+/// for _ in 0..100 {
+///     let (_buf, _meta) = stream.next().unwrap();
+/// }
+/// println!("{}", stream.snapshot());
+/// ```
+pub struct StreamStats<S> {
+    stream: S,
+    counter: Counter,
+}
+
+impl<S> StreamStats<S> {
+    /// Returns a collector wrapping the given stream
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - Capture stream
+    pub fn new(stream: S) -> Self {
+        StreamStats {
+            stream,
+            counter: Counter::default(),
+        }
+    }
+
+    /// Returns the statistics of the frames received so far
+    pub fn snapshot(&self) -> Stats {
+        self.counter.snapshot()
+    }
+
+    /// Discards the statistics collected so far
+    pub fn reset(&mut self) {
+        self.counter = Counter::default();
+    }
+
+    /// Returns the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: StreamTrait> StreamTrait for StreamStats<S> {
+    type Item = S::Item;
+
+    fn start(&mut self) -> io::Result<()> {
+        self.stream.start()
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        self.stream.stop()
+    }
+}
+
+impl<'a, S: CaptureStream<'a, Item = [u8]>> CaptureStream<'a> for StreamStats<S> {
+    fn queue(&mut self, index: usize) -> io::Result<()> {
+        self.stream.queue(index)
+    }
+
+    fn dequeue(&mut self) -> io::Result<usize> {
+        self.stream.dequeue()
+    }
+
+    fn next(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        let (buf, meta) = self.stream.next()?;
+        self.counter
+            .record(meta.valid(buf).len(), meta.sequence, Instant::now());
+        Ok((buf, meta))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<'a, S> crate::io::traits::AsyncCaptureStream<'a> for StreamStats<S>
+where
+    S: crate::io::traits::AsyncCaptureStream<'a, Item = [u8]>,
+{
+    async fn ready(&self) -> io::Result<()> {
+        self.stream.ready().await
+    }
+
+    async fn poll_dequeue(&mut self) -> io::Result<usize> {
+        self.stream.poll_dequeue().await
+    }
+
+    async fn poll_next(&'a mut self) -> io::Result<(&Self::Item, &Metadata)> {
+        let (buf, meta) = self.stream.poll_next().await?;
+        self.counter
+            .record(meta.valid(buf).len(), meta.sequence, Instant::now());
+        Ok((buf, meta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter() {
+        let start = Instant::now();
+        let mut counter = Counter::default();
+        assert_eq!(Stats::default(), counter.snapshot());
+
+        // 1 MiB frames every 100ms, frame 3 got lost
+        for (i, sequence) in [0, 1, 2, 4, 5].iter().enumerate() {
+            let now = start + Duration::from_millis(100 * i as u64);
+            counter.record(1_048_576, *sequence, now);
+        }
+
+        let stats = counter.snapshot();
+        assert_eq!(5, stats.frames);
+        assert_eq!(1, stats.dropped);
+        assert_eq!(5 * 1_048_576, stats.bytes);
+        assert_eq!(Duration::from_millis(400), stats.elapsed);
+        assert!((stats.fps - 10.0).abs() < 1e-6);
+        assert!((stats.avg_fps - 10.0).abs() < 1e-6);
+        assert!((stats.mb_per_sec - 12.5).abs() < 1e-6);
+
+        // a restarted stream counts from zero again
+        counter.record(0, 0, start + Duration::from_millis(500));
+        assert_eq!(1, counter.snapshot().dropped);
+    }
+}
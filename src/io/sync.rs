@@ -0,0 +1,163 @@
+use std::io;
+use std::time::Duration;
+
+use crate::buffer::Metadata;
+use crate::io::mmap::Stream;
+use crate::io::traits::{CaptureStream, Stream as StreamTrait};
+
+/// Owned frame data and its metadata
+pub type Frame = (Vec<u8>, Metadata);
+
+/// Captures frames from multiple cameras aligned to a common cadence
+///
+/// Hardware synchronization is not assumed. Instead, the streams are started back-to-back to
+/// keep the initial skew minimal and frames are correlated by their driver timestamps (which
+/// are all based on `CLOCK_MONOTONIC`).
+///
+/// For each set, the most recent of the frames dequeued from every stream acts as the shared
+/// deadline. Streams lagging behind it by more than the tolerance get one chance to catch up
+/// with their next frame. Frames which still do not match the deadline are dropped.
+///
+/// The streams are driven through [`CaptureStream::queue`] and [`CaptureStream::dequeue`], so
+/// stream options which only apply to [`CaptureStream::next`] (such as skipping initial frames)
+/// have no effect. A timeout set on a stream does apply.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use v4l::buffer::Type;
+/// use v4l::io::sync::SyncGroup;
+/// use v4l::prelude::*;
+///
+/// let left = Device::new(0).unwrap();
+/// let right = Device::new(1).unwrap();
+/// let streams = vec![
+///     MmapStream::new(&left, Type::VideoCapture).unwrap(),
+///     MmapStream::new(&right, Type::VideoCapture).unwrap(),
+/// ];
+/// let mut group = SyncGroup::new(streams, Duration::from_millis(5));
+///
+/// let frames = group.next_synced().unwrap();
+/// if frames.iter().all(Option::is_some) {
+///     println!("got a synchronized stereo pair");
+/// }
+/// ```
+pub struct SyncGroup<'a> {
+    streams: Vec<Stream<'a>>,
+    tolerance: Duration,
+    active: bool,
+}
+
+impl<'a> SyncGroup<'a> {
+    /// Returns a group of streams
+    ///
+    /// # Arguments
+    ///
+    /// * `streams` - Capture streams, not started yet
+    /// * `tolerance` - Maximum distance of a frame to the shared deadline
+    pub fn new(streams: Vec<Stream<'a>>, tolerance: Duration) -> Self {
+        SyncGroup {
+            streams,
+            tolerance,
+            active: false,
+        }
+    }
+
+    /// Starts all streams
+    ///
+    /// All buffers are queued first, then the streams are started back-to-back.
+    /// Called by [`SyncGroup::next_synced`] if necessary.
+    pub fn start(&mut self) -> io::Result<()> {
+        for stream in &mut self.streams {
+            let mut index = 0;
+            while stream.get(index).is_some() {
+                CaptureStream::queue(stream, index)?;
+                index += 1;
+            }
+        }
+
+        for stream in &mut self.streams {
+            StreamTrait::start(stream)?;
+        }
+
+        self.active = true;
+        Ok(())
+    }
+
+    /// Stops all streams
+    pub fn stop(&mut self) -> io::Result<()> {
+        for stream in &mut self.streams {
+            StreamTrait::stop(stream)?;
+        }
+
+        self.active = false;
+        Ok(())
+    }
+
+    /// Returns the grouped streams
+    pub fn into_inner(self) -> Vec<Stream<'a>> {
+        self.streams
+    }
+
+    /// Captures a set of frames, one slot per stream
+    ///
+    /// Slots are `None` if the stream did not deliver a frame close enough to the deadline.
+    pub fn next_synced(&mut self) -> io::Result<Vec<Option<Frame>>> {
+        if !self.active {
+            self.start()?;
+        }
+
+        let mut frames = Vec::with_capacity(self.streams.len());
+        for stream in &mut self.streams {
+            frames.push(capture(stream)?);
+        }
+
+        let deadline = frames
+            .iter()
+            .map(|(_, meta)| Duration::from(meta.timestamp))
+            .max()
+            .unwrap_or_default();
+
+        for (stream, frame) in self.streams.iter_mut().zip(frames.iter_mut()) {
+            if deadline - Duration::from(frame.1.timestamp) > self.tolerance {
+                // straggler, see whether its next frame is a better match
+                let next = capture(stream)?;
+                if distance(&next.1, deadline) < distance(&frame.1, deadline) {
+                    *frame = next;
+                }
+            }
+        }
+
+        Ok(frames
+            .into_iter()
+            .map(|frame| {
+                if distance(&frame.1, deadline) <= self.tolerance {
+                    Some(frame)
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+fn capture(stream: &mut Stream) -> io::Result<Frame> {
+    let index = CaptureStream::dequeue(stream)?;
+    let (buf, meta) = stream
+        .get(index)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid buffer index"))?;
+    let len = match meta.bytesused as usize {
+        0 => buf.len(),
+        n => n.min(buf.len()),
+    };
+    let frame = (buf[..len].to_vec(), *meta);
+
+    CaptureStream::queue(stream, index)?;
+    Ok(frame)
+}
+
+fn distance(meta: &Metadata, deadline: Duration) -> Duration {
+    Duration::from(meta.timestamp).abs_diff(deadline)
+}
@@ -0,0 +1,168 @@
+use std::{io, sync::Arc};
+
+use tokio::sync::broadcast;
+
+use crate::buffer::Metadata;
+use crate::io::traits::AsyncCaptureStream;
+
+/// Captured frame shared between the subscribers of a [`Tee`]
+#[derive(Clone)]
+pub struct Frame {
+    /// Frame data, copied out of the stream buffer
+    pub data: Arc<[u8]>,
+    /// Metadata of the frame
+    pub meta: Metadata,
+}
+
+/// What happens when a subscriber falls behind
+///
+/// A [`Tee`] never waits for its subscribers. Once a subscriber has `capacity` frames pending,
+/// its oldest frames are overwritten by new ones.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Overflow {
+    /// Overwritten frames are silently skipped by the subscriber
+    #[default]
+    Drop,
+    /// The subscriber reports the number of overwritten frames as an error before continuing
+    /// with the oldest frame still available
+    Lag,
+}
+
+/// Fans out a capture stream to multiple consumers
+///
+/// Each frame dequeued from the wrapped stream is copied once and broadcast to all
+/// [`Subscriber`]s, e.g. a recorder and a live preview. Slow subscribers never stall the
+/// capture loop, see [`Overflow`].
+///
+/// # Example
+///
+/// ```no_run
+/// use v4l::buffer::Type;
+/// use v4l::io::tee::Tee;
+/// use v4l::prelude::*;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let dev = Device::new(0)?;
+/// let stream = MmapStream::new(&dev, Type::VideoCapture)?;
+/// let mut tee = Tee::new(stream, 4);
+///
+/// let mut preview = tee.subscribe();
+/// let mut recorder = tee.subscribe();
+///
+/// tee.feed().await?;
+/// let frame = preview.recv().await?.unwrap();
+/// let frame = recorder.recv().await?.unwrap();
+/// # Ok(())
+/// # }
+/// ```
+pub struct Tee<S> {
+    stream: S,
+    sender: broadcast::Sender<Frame>,
+    overflow: Overflow,
+}
+
+impl<S> Tee<S> {
+    /// Returns a tee for the given stream
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - Capture stream feeding the subscribers
+    /// * `capacity` - Number of frames a subscriber may fall behind before frames are dropped,
+    ///   at least 1 (0 is treated as 1)
+    pub fn new(stream: S, capacity: usize) -> Self {
+        // the channel panics on a zero capacity
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Tee {
+            stream,
+            sender,
+            overflow: Overflow::default(),
+        }
+    }
+
+    /// Sets the policy for subscribers falling behind
+    ///
+    /// Only affects subscribers created afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `overflow` - Overflow policy
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Returns a new subscriber which receives all frames fed from now on
+    pub fn subscribe(&self) -> Subscriber {
+        Subscriber {
+            receiver: self.sender.subscribe(),
+            overflow: self.overflow,
+        }
+    }
+
+    /// Returns the number of active subscribers
+    pub fn subscribers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Returns the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> Tee<S>
+where
+    S: for<'a> AsyncCaptureStream<'a, Item = [u8]>,
+{
+    /// Captures a single frame and broadcasts it to all subscribers
+    ///
+    /// Frames are captured even if there are no subscribers at all. Returns the number of
+    /// subscribers the frame was sent to.
+    pub async fn feed(&mut self) -> io::Result<usize> {
+        let (buf, meta) = self.stream.poll_next().await?;
+        let frame = Frame {
+            data: Arc::from(buf),
+            meta: *meta,
+        };
+
+        // sending only fails if there are no subscribers
+        Ok(self.sender.send(frame).unwrap_or(0))
+    }
+
+    /// Feeds frames until all subscribers are gone
+    pub async fn run(&mut self) -> io::Result<()> {
+        while self.subscribers() > 0 {
+            self.feed().await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Receiving end of a [`Tee`]
+pub struct Subscriber {
+    receiver: broadcast::Receiver<Frame>,
+    overflow: Overflow,
+}
+
+impl Subscriber {
+    /// Returns the next frame
+    ///
+    /// Returns `None` once the [`Tee`] is dropped and all pending frames have been received.
+    pub async fn recv(&mut self) -> io::Result<Option<Frame>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(frame) => return Ok(Some(frame)),
+                Err(broadcast::error::RecvError::Closed) => return Ok(None),
+                Err(broadcast::error::RecvError::Lagged(count)) => {
+                    if self.overflow == Overflow::Lag {
+                        return Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            format!("subscriber lagged behind by {} frames", count),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use std::{future::Future, io};
 
+#[cfg(feature = "tokio")]
+use std::time::Duration;
+
 use crate::buffer::Metadata;
 
 /// Streaming I/O
@@ -38,10 +41,65 @@ pub trait OutputStream<'a>: Stream {
 }
 
 pub trait AsyncCaptureStream<'a>: CaptureStream<'a> {
+    /// Wait until a buffer can be removed from the drivers' outgoing queue
+    ///
+    /// This only waits for the device to become readable, no buffer is dequeued. It allows for
+    /// custom capture loops which issue [`CaptureStream::dequeue`] and [`CaptureStream::queue`]
+    /// themselves.
+    ///
+    /// Readiness is not consumed by this call: the next `dequeue` removes the buffer. A
+    /// dequeue may still fail with [`io::ErrorKind::WouldBlock`] in case of a spurious wakeup,
+    /// in which case `ready` should be awaited again. The stream must have been started with
+    /// buffers queued, otherwise the device reports an error condition and the subsequent
+    /// dequeue fails.
+    fn ready(&self) -> impl Future<Output = io::Result<()>>;
+
     /// Remove a buffer from the drivers' outgoing queue
     fn poll_dequeue(&mut self) -> impl Future<Output = io::Result<usize>>;
 
     /// Fetch a new frame by first queueing and then dequeueing.
     /// First time initialization is performed if necessary.
     fn poll_next(&'a mut self) -> impl Future<Output = io::Result<(&Self::Item, &Metadata)>>;
+
+    /// Fetch a new frame like [`AsyncCaptureStream::poll_next`], but give up after a timeout
+    ///
+    /// Fails with [`io::ErrorKind::TimedOut`] if no frame arrives in time, e.g. because the
+    /// device stalled. The stream remains usable afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Maximum time to wait for a frame
+    #[cfg(feature = "tokio")]
+    fn poll_next_timeout(
+        &'a mut self,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<(&Self::Item, &Metadata)>> {
+        async move {
+            match tokio::time::timeout(timeout, self.poll_next()).await {
+                Ok(result) => result,
+                Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "VIDIOC_DQBUF")),
+            }
+        }
+    }
+}
+
+pub trait AsyncOutputStream<'a>: OutputStream<'a> {
+    /// Wait until a buffer can be removed from the drivers' outgoing queue
+    ///
+    /// This only waits for the device to become writable, no buffer is dequeued. Like
+    /// [`AsyncCaptureStream::ready`], readiness is not consumed by this call.
+    fn ready(&self) -> impl Future<Output = io::Result<()>>;
+
+    /// Remove a buffer from the drivers' outgoing queue
+    fn poll_dequeue(&mut self) -> impl Future<Output = io::Result<usize>>;
+
+    /// Fetch a buffer for the next frame by first queueing the previous one and then dequeueing.
+    /// First time initialization is performed if necessary.
+    ///
+    /// The returned buffer is handed to the driver on the next call, so it must be filled
+    /// before. Buffers which have never been queued are handed out first, so only then this
+    /// waits for the driver to release a buffer.
+    fn poll_write_next(
+        &'a mut self,
+    ) -> impl Future<Output = io::Result<(&mut Self::Item, &mut Metadata)>>;
 }
@@ -1,18 +1,45 @@
 use std::{io, mem, sync::Arc};
 
+use crate::aligned_alloc::AlignedBuffer;
 use crate::buffer;
 use crate::device::Handle;
 use crate::memory::Memory;
 use crate::v4l2;
 use crate::v4l_sys::*;
 
+/// User allocated buffer
+pub type Buffer = AlignedBuffer;
+
+/// Returns the buffer alignment used if none is set explicitly
+///
+/// This is the page size with the `aligned-alloc` feature, which certain environments require,
+/// and no particular alignment otherwise.
+///
+/// e.g. https://forums.developer.nvidia.com/t/jetson-orin-v4l2-memory-userptr-capture-fail/261393
+pub fn default_alignment() -> Option<usize> {
+    if cfg!(feature = "aligned-alloc") {
+        Some(unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize)
+    } else {
+        None
+    }
+}
+
 /// Manage user allocated buffers
 ///
+/// Buffers are aligned to [`default_alignment`] unless a different alignment is set
+/// before allocating.
+///
 /// All buffers are released in the Drop impl.
 pub struct Arena {
     handle: Arc<Handle>,
-    pub bufs: Vec<Vec<u8>>,
+    pub bufs: Vec<Buffer>,
     pub buf_type: buffer::Type,
+    /// Memory budget checked before allocating buffers
+    pub max_total_bytes: Option<usize>,
+    prefill: Option<u8>,
+    alignment: Option<usize>,
+    /// Size of each buffer as reported by the driver (`sizeimage`)
+    buf_size: usize,
 }
 
 impl Arena {
@@ -30,6 +57,63 @@ impl Arena {
             handle,
             bufs: Vec::new(),
             buf_type,
+            max_total_bytes: None,
+            prefill: None,
+            alignment: default_alignment(),
+            buf_size: 0,
+        }
+    }
+
+    /// Returns the alignment of the buffers in bytes, `None` if there is no particular one
+    pub fn alignment(&self) -> Option<usize> {
+        self.alignment
+    }
+
+    /// Sets the alignment of buffers allocated afterwards
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the alignment is not a power of two.
+    ///
+    /// # Arguments
+    ///
+    /// * `alignment` - Alignment in bytes (e.g. 64 for cache lines), `None` for no particular one
+    pub fn set_alignment(&mut self, alignment: Option<usize>) -> io::Result<()> {
+        if let Some(align) = alignment {
+            if !align.is_power_of_two() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("buffer alignment {} is not a power of two", align),
+                ));
+            }
+        }
+
+        self.alignment = alignment;
+        Ok(())
+    }
+
+    /// Returns the size of each buffer in bytes, zero before allocating
+    ///
+    /// This is the `sizeimage` reported by the driver, which may be larger than the frame size
+    /// computed from the width, height and bits per pixel due to padding.
+    pub fn buffer_size(&self) -> usize {
+        self.buf_size
+    }
+
+    /// Sets a byte pattern which buffers are filled with before they are queued
+    ///
+    /// This is a debugging aid: regions which still hold the pattern after dequeueing were not
+    /// written by the driver. Disabled by default since it costs a memset per frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Fill byte (e.g. 0xDE), None to disable
+    pub fn set_prefill(&mut self, pattern: Option<u8>) {
+        self.prefill = pattern;
+    }
+
+    /// Fills a buffer with the prefill pattern, if one is set
+    pub(crate) fn fill(&mut self, index: usize) {
+        if let Some(pattern) = self.prefill {
+            self.bufs[index].fill(pattern);
         }
     }
 
@@ -55,6 +139,12 @@ impl Arena {
             )?;
         }
 
+        crate::io::check_buffer_budget(
+            count,
+            unsafe { v4l2_fmt.fmt.pix.sizeimage as usize },
+            self.max_total_bytes,
+        )?;
+
         #[cfg(feature = "v4l-sys")]
         eprintln!(
             "\n### WARNING ###\n\
@@ -73,34 +163,43 @@ impl Arena {
                 &mut v4l2_reqbufs as *mut _ as *mut std::os::raw::c_void,
             )?;
         }
+        crate::io::check_granted_buffers(count, v4l2_reqbufs.count)?;
 
         // allocate the new user buffers
         self.allocate_new_user_buffer(v4l2_reqbufs.count as usize, unsafe {
             v4l2_fmt.fmt.pix.sizeimage as usize
         });
 
+        // misaligned buffers would only be rejected with EINVAL by VIDIOC_QBUF later on
+        if cfg!(debug_assertions) {
+            if let Err(e) = self.verify_alignment() {
+                self.bufs.clear();
+                let _ = self.release();
+                return Err(e);
+            }
+        }
+
         Ok(v4l2_reqbufs.count)
     }
 
-    #[cfg(not(feature = "aligned-alloc"))]
-    fn allocate_new_user_buffer(&mut self, count: usize, size: usize) {
-        self.bufs.resize(count, Vec::new());
-        for i in 0..count {
-            let buf = &mut self.bufs[i];
-            buf.resize(size, 0);
+    /// Checks that all buffers start at a multiple of the alignment
+    ///
+    /// Done on allocation in debug builds. Fails with [`io::ErrorKind::InvalidData`] naming the
+    /// first misaligned buffer.
+    pub fn verify_alignment(&self) -> io::Result<()> {
+        match self.alignment {
+            Some(align) => check_alignment(&self.bufs, align),
+            None => Ok(()),
         }
     }
 
-    // In certain environments, it is necessary to allocate memory aligned to the page size
-    //
-    // e.g. https://forums.developer.nvidia.com/t/jetson-orin-v4l2-memory-userptr-capture-fail/261393
-    #[cfg(feature = "aligned-alloc")]
     fn allocate_new_user_buffer(&mut self, count: usize, size: usize) {
-        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
-        self.bufs.resize(count, Vec::new());
-        for i in 0..count {
-            self.bufs[i] = crate::aligned_alloc::aligned_alloc(size, page_size);
-        }
+        // an alignment of one is what a plain Vec<u8> gets
+        let align = self.alignment.unwrap_or(1);
+        self.buf_size = size;
+        self.bufs = (0..count)
+            .map(|_| AlignedBuffer::new(size, align))
+            .collect();
     }
 
     pub fn release(&mut self) -> io::Result<()> {
@@ -119,6 +218,24 @@ impl Arena {
     }
 }
 
+fn check_alignment(bufs: &[Buffer], align: usize) -> io::Result<()> {
+    for (index, buf) in bufs.iter().enumerate() {
+        // the alignment is a power of two
+        if buf.as_ptr() as usize & (align - 1) != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "userptr buffer {} at {:p} is not aligned to {} bytes",
+                    index,
+                    buf.as_ptr(),
+                    align
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
 impl Drop for Arena {
     fn drop(&mut self) {
         if self.bufs.is_empty() {
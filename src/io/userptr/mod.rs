@@ -1,4 +1,5 @@
 pub(crate) mod arena;
+pub use arena::default_alignment;
 
 pub mod stream;
 pub use stream::Stream;
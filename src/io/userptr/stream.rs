@@ -2,10 +2,12 @@ use std::convert::TryInto;
 use std::time::Duration;
 use std::{io, mem, sync::Arc};
 
-use crate::buffer::{Metadata, Type};
+use crate::buffer::{Metadata, PlaneMeta, Type};
 use crate::device::{Device, Handle};
+use crate::io::error;
 use crate::io::traits::{CaptureStream, Stream as StreamTrait};
-use crate::io::userptr::arena::Arena;
+use crate::io::userptr::arena::{default_alignment, Arena};
+use crate::io::SequenceGap;
 use crate::memory::Memory;
 use crate::v4l2;
 use crate::v4l_sys::*;
@@ -17,9 +19,12 @@ pub struct Stream {
     handle: Arc<Handle>,
     arena: Arena,
     arena_index: usize,
+    /// The buffer at `arena_index` is held by the caller and must be requeued
+    holding: bool,
     buf_type: Type,
     buf_meta: Vec<Metadata>,
     timeout: Option<i32>,
+    sequence: SequenceGap,
 
     active: bool,
 }
@@ -49,7 +54,60 @@ impl Stream {
     }
 
     pub fn with_buffers(dev: &Device, buf_type: Type, buf_count: u32) -> io::Result<Self> {
+        Stream::with_alignment(dev, buf_type, buf_count, default_alignment())
+    }
+
+    /// Returns a stream whose buffers have the given alignment
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the alignment is not a power of two.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - Device ref to get its file descriptor
+    /// * `buf_type` - Type of the buffers
+    /// * `buf_count` - Number of buffers to request
+    /// * `alignment` - Alignment in bytes (e.g. 4096 for pages), `None` for no particular one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::buffer::Type;
+    /// use v4l::device::Device;
+    /// use v4l::io::userptr::Stream;
+    ///
+    /// let dev = Device::new(0);
+    /// if let Ok(dev) = dev {
+    ///     let stream = Stream::with_alignment(&dev, Type::VideoCapture, 4, Some(64));
+    /// }
+    /// ```
+    pub fn with_alignment(
+        dev: &Device,
+        buf_type: Type,
+        buf_count: u32,
+        alignment: Option<usize>,
+    ) -> io::Result<Self> {
+        Stream::with_budget(
+            dev,
+            buf_type,
+            buf_count,
+            alignment,
+            dev.max_total_buffer_bytes(),
+        )
+    }
+
+    /// Returns a stream whose buffers are checked against the given memory budget
+    ///
+    /// Used by [`crate::io::builder::StreamBuilder`] to override the budget of the device.
+    pub(crate) fn with_budget(
+        dev: &Device,
+        buf_type: Type,
+        buf_count: u32,
+        alignment: Option<usize>,
+        max_total_bytes: Option<usize>,
+    ) -> io::Result<Self> {
         let mut arena = Arena::new(dev.handle(), buf_type);
+        arena.max_total_bytes = max_total_bytes;
+        arena.set_alignment(alignment)?;
         let count = arena.allocate(buf_count)?;
         let mut buf_meta = Vec::new();
         buf_meta.resize(count as usize, Metadata::default());
@@ -58,13 +116,66 @@ impl Stream {
             handle: dev.handle(),
             arena,
             arena_index: 0,
+            holding: false,
             buf_type,
             buf_meta,
             active: false,
             timeout: None,
+            sequence: SequenceGap::default(),
         })
     }
 
+    /// Returns a buffer and its metadata by index
+    ///
+    /// Meant for custom capture loops, where the index is returned by `dequeue`.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Buffer index
+    pub fn get(&self, index: usize) -> Option<(&[u8], &Metadata)> {
+        Some((self.arena.bufs.get(index)?, self.buf_meta.get(index)?))
+    }
+
+    /// Fills buffers with a byte pattern before they are queued
+    ///
+    /// This is a debugging aid for drivers which write partial frames: regions which still hold
+    /// the pattern after dequeueing were not touched by the hardware. Disabled by default since
+    /// it costs a memset per frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Fill byte (e.g. 0xDE), None to disable
+    pub fn with_prefill(mut self, pattern: Option<u8>) -> Self {
+        self.arena.set_prefill(pattern);
+        self
+    }
+
+    /// Returns the number of buffers granted by the driver
+    pub fn buffer_count(&self) -> usize {
+        self.arena.bufs.len()
+    }
+
+    /// Returns the size of each buffer in bytes
+    ///
+    /// This is the `sizeimage` reported by the driver, which may be larger than the frame size
+    /// computed from the width, height and bits per pixel due to padding.
+    pub fn buffer_size(&self) -> usize {
+        self.arena.buffer_size()
+    }
+
+    /// Returns the alignment of the buffers in bytes, `None` if there is no particular one
+    pub fn alignment(&self) -> Option<usize> {
+        self.arena.alignment()
+    }
+
+    /// Checks that all buffers are aligned to [`Stream::alignment`]
+    ///
+    /// This is done on allocation in debug builds already. Misaligned buffers are rejected by
+    /// the driver with a bare `EINVAL` when queued, this names the offending buffer instead.
+    pub fn verify_alignment(&self) -> io::Result<()> {
+        self.arena.verify_alignment()
+    }
+
     /// Returns the raw device handle
     pub fn handle(&self) -> Arc<Handle> {
         self.handle.clone()
@@ -80,6 +191,14 @@ impl Stream {
         self.timeout = None;
     }
 
+    /// Returns the number of frames the driver dropped before the last dequeued one
+    ///
+    /// Computed from the gap between the sequence numbers of the last two dequeued buffers.
+    /// The count starts over when the stream is restarted.
+    pub fn dropped_since_last(&self) -> u32 {
+        self.sequence.dropped()
+    }
+
     fn buffer_desc(&self) -> v4l2_buffer {
         v4l2_buffer {
             type_: self.buf_type as u32,
@@ -94,7 +213,8 @@ impl Stream {
                 self.handle.fd(),
                 v4l2::vidioc::VIDIOC_DQBUF,
                 v4l2_buf as *mut _ as *mut std::os::raw::c_void,
-            )?;
+            )
+            .map_err(error::translate)?;
             Ok(v4l2_buf.index as usize)
         }
     }
@@ -131,6 +251,7 @@ impl StreamTrait for Stream {
             )?;
         }
 
+        self.sequence.reset();
         self.active = true;
         Ok(())
     }
@@ -152,6 +273,7 @@ impl StreamTrait for Stream {
 
 impl<'a> CaptureStream<'a> for Stream {
     fn queue(&mut self, index: usize) -> io::Result<()> {
+        self.arena.fill(index);
         let buf = &mut self.arena.bufs[index];
         let mut v4l2_buf = v4l2_buffer {
             index: index as u32,
@@ -166,7 +288,8 @@ impl<'a> CaptureStream<'a> for Stream {
                 self.handle.fd(),
                 v4l2::vidioc::VIDIOC_QBUF,
                 &mut v4l2_buf as *mut _ as *mut std::os::raw::c_void,
-            )?;
+            )
+            .map_err(error::translate)?;
         }
 
         Ok(())
@@ -176,7 +299,9 @@ impl<'a> CaptureStream<'a> for Stream {
         let mut v4l2_buf = self.buffer_desc();
 
         // ここで次のバッファを待っている。これをwakeにしたら非同期になる
-        if self.handle.poll(libc::POLLIN, self.timeout.unwrap_or(-1))? == 0 {
+        if !self.handle.is_nonblocking()
+            && self.handle.poll(libc::POLLIN, self.timeout.unwrap_or(-1))? == 0
+        {
             // This condition can only happen if there was a timeout.
             // A timeout is only possible if the `timeout` value is non-zero, meaning we should
             // propagate it to the caller.
@@ -186,13 +311,18 @@ impl<'a> CaptureStream<'a> for Stream {
         // non_blockingなのでWouldBlockが返ってくる
         self.dequeue_buffer(&mut v4l2_buf)?;
         self.arena_index = v4l2_buf.index as usize;
+        self.sequence.update(v4l2_buf.sequence);
 
+        let (planes, num_planes) = unsafe { PlaneMeta::from_buffer(&v4l2_buf) };
         self.buf_meta[self.arena_index] = Metadata {
             bytesused: v4l2_buf.bytesused,
             flags: v4l2_buf.flags.into(),
             field: v4l2_buf.field,
             timestamp: v4l2_buf.timestamp.into(),
             sequence: v4l2_buf.sequence,
+            wall_clock: None,
+            planes,
+            num_planes,
         };
 
         Ok(self.arena_index)
@@ -206,11 +336,13 @@ impl<'a> CaptureStream<'a> for Stream {
             }
 
             self.start()?;
-        } else {
+        } else if self.holding {
             self.queue(self.arena_index)?;
         }
+        self.holding = false;
 
         self.arena_index = self.dequeue()?;
+        self.holding = true;
 
         // The index used to access the buffer elements is given to us by v4l2, so we assume it
         // will always be valid.
@@ -222,17 +354,21 @@ impl<'a> CaptureStream<'a> for Stream {
 
 #[cfg(feature = "tokio")]
 impl<'a> crate::io::traits::AsyncCaptureStream<'a> for Stream {
-    async fn poll_dequeue(&mut self) -> io::Result<usize> {
+    async fn ready(&self) -> io::Result<()> {
         use tokio::io::unix::AsyncFd;
+        let async_fd = AsyncFd::new(self.handle.fd())?;
+
+        let _ = core::future::poll_fn(|cx| async_fd.poll_read_ready(cx)).await?;
+        Ok(())
+    }
+
+    async fn poll_dequeue(&mut self) -> io::Result<usize> {
         let mut v4l2_buf = self.buffer_desc();
         loop {
             match self.dequeue_buffer(&mut v4l2_buf) {
                 Ok(index) => return Ok(index),
                 Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {
-                    let fd = self.handle.fd();
-                    let async_fd = AsyncFd::new(fd)?;
-
-                    let _ = core::future::poll_fn(|cx| async_fd.poll_read_ready(cx)).await?;
+                    self.ready().await?;
                 }
                 Err(error) => return Err(error),
             }
@@ -247,11 +383,14 @@ impl<'a> crate::io::traits::AsyncCaptureStream<'a> for Stream {
             }
 
             self.start()?;
-        } else {
+        } else if self.holding {
             self.queue(self.arena_index)?;
         }
+        // a buffer requeued here must not be queued again if this future is cancelled
+        self.holding = false;
 
         self.arena_index = self.poll_dequeue().await?;
+        self.holding = true;
 
         // The index used to access the buffer elements is given to us by v4l2, so we assume it
         // will always be valid.
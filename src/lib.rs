@@ -80,19 +80,21 @@ pub mod capability;
 pub mod context;
 pub mod control;
 pub mod device;
+pub mod event;
 pub mod format;
 pub mod fraction;
+pub mod frame;
 pub mod frameinterval;
 pub mod framesize;
 pub mod memory;
 pub mod parameters;
+pub mod selection;
 pub mod timestamp;
 pub mod util;
 pub mod video;
 
 pub mod io;
 
-#[cfg(feature = "aligned-alloc")]
 pub mod aligned_alloc;
 
 pub use {
@@ -109,5 +111,7 @@ pub use {
 
 pub mod prelude {
     pub use crate::device::Device;
-    pub use crate::io::{mmap::Stream as MmapStream, userptr::Stream as UserptrStream};
+    pub use crate::io::{
+        mmap::Stream as MmapStream, read::Stream as ReadStream, userptr::Stream as UserptrStream,
+    };
 }
@@ -4,6 +4,7 @@ use std::fmt;
 #[allow(clippy::unreadable_literal)]
 #[rustfmt::skip]
 #[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Memory {
     Mmap        = 1,
     UserPtr     = 2,
@@ -0,0 +1,137 @@
+use std::fmt;
+
+use crate::v4l_sys::*;
+
+/// Selection target
+///
+/// Crop targets refer to the source of the image data (e.g. the sensor area), compose targets
+/// refer to the destination (the area of the buffer the image is written to).
+#[allow(clippy::unreadable_literal)]
+#[rustfmt::skip]
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Current cropping area
+    Crop            = 0x0000,
+    /// Default cropping area
+    CropDefault     = 0x0001,
+    /// Bounds of the cropping area
+    CropBounds      = 0x0002,
+    /// Native size of the device, e.g. the sensor pixel array
+    NativeSize      = 0x0003,
+    /// Current composing area
+    Compose         = 0x0100,
+    /// Default composing area
+    ComposeDefault  = 0x0101,
+    /// Bounds of the composing area
+    ComposeBounds   = 0x0102,
+    /// Composing area plus all padding pixels modified by the hardware
+    ComposePadded   = 0x0103,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+    pub struct Flags: u32 {
+        /// The adjusted rectangle must be equal to or larger than the requested one
+        const GE            = 0x0001;
+        /// The adjusted rectangle must be equal to or smaller than the requested one
+        const LE            = 0x0002;
+        /// The configuration must not be propagated to other pipeline stages
+        const KEEP_CONFIG   = 0x0004;
+    }
+}
+
+impl From<u32> for Flags {
+    fn from(flags: u32) -> Self {
+        Self::from_bits_retain(flags)
+    }
+}
+
+impl From<Flags> for u32 {
+    fn from(flags: Flags) -> Self {
+        flags.bits()
+    }
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Rectangle used for cropping and composing
+pub struct Rect {
+    /// Horizontal offset of the top left corner (in pixels)
+    pub left: i32,
+    /// Vertical offset of the top left corner (in pixels)
+    pub top: i32,
+    /// Width of the rectangle (in pixels)
+    pub width: u32,
+    /// Height of the rectangle (in pixels)
+    pub height: u32,
+}
+
+impl Rect {
+    /// Returns a rectangle
+    ///
+    /// # Arguments
+    ///
+    /// * `left` - Horizontal offset of the top left corner
+    /// * `top` - Vertical offset of the top left corner
+    /// * `width` - Width in pixels
+    /// * `height` - Height in pixels
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use v4l::selection::Rect;
+    /// let rect = Rect::new(0, 0, 640, 480);
+    /// ```
+    pub fn new(left: i32, top: i32, width: u32, height: u32) -> Self {
+        Rect {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+}
+
+impl fmt::Display for Rect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{} @ ({}, {})",
+            self.width, self.height, self.left, self.top
+        )
+    }
+}
+
+impl From<v4l2_rect> for Rect {
+    fn from(rect: v4l2_rect) -> Self {
+        Self {
+            left: rect.left,
+            top: rect.top,
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+}
+
+impl From<Rect> for v4l2_rect {
+    fn from(rect: Rect) -> Self {
+        Self {
+            left: rect.left,
+            top: rect.top,
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fmt, time};
+use std::{cmp, fmt, io, mem, ops, time};
 
 use crate::v4l_sys::*;
 
@@ -26,6 +26,101 @@ impl Timestamp {
     pub fn new(sec: time_t, usec: time_t) -> Self {
         Timestamp { sec, usec }
     }
+
+    /// Returns the time elapsed since an earlier timestamp
+    ///
+    /// If `earlier` is actually later than `self` (e.g. because the timestamps of a stream went
+    /// backwards), the distance between both is returned as error instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `earlier` - Timestamp to measure from
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use v4l::timestamp::Timestamp;
+    ///
+    /// let a = Timestamp::new(1, 0);
+    /// let b = Timestamp::new(1, 500_000);
+    /// assert_eq!(b.since(a), Ok(Duration::from_millis(500)));
+    /// assert_eq!(a.since(b), Err(Duration::from_millis(500)));
+    /// ```
+    pub fn since(&self, earlier: Timestamp) -> Result<time::Duration, time::Duration> {
+        let delta = self.micros() - earlier.micros();
+        if delta >= 0 {
+            Ok(time::Duration::from_micros(delta as u64))
+        } else {
+            Err(time::Duration::from_micros(delta.unsigned_abs()))
+        }
+    }
+
+    /// Returns the timestamp `rhs` earlier, or `None` if that would be before zero
+    ///
+    /// # Arguments
+    ///
+    /// * `rhs` - Duration to subtract
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use v4l::timestamp::Timestamp;
+    ///
+    /// let ts = Timestamp::new(1, 0);
+    /// assert_eq!(ts.checked_sub(Duration::from_millis(500)), Some(Timestamp::new(0, 500_000)));
+    /// assert_eq!(ts.checked_sub(Duration::from_secs(2)), None);
+    /// ```
+    pub fn checked_sub(&self, rhs: time::Duration) -> Option<Timestamp> {
+        time::Duration::from(*self)
+            .checked_sub(rhs)
+            .map(Timestamp::from)
+    }
+
+    // time_t is not 64 bits wide on every target
+    #[allow(clippy::unnecessary_cast)]
+    fn micros(&self) -> i64 {
+        self.sec as i64 * 1_000_000 + self.usec as i64
+    }
+}
+
+impl PartialEq for Timestamp {
+    fn eq(&self, other: &Self) -> bool {
+        self.micros() == other.micros()
+    }
+}
+
+impl Eq for Timestamp {}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timestamp {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.micros().cmp(&other.micros())
+    }
+}
+
+impl ops::Add<time::Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, rhs: time::Duration) -> Self::Output {
+        Self::from(time::Duration::from(self) + rhs)
+    }
+}
+
+// Saturates at zero, e.g. when subtracting a latency from the timestamp of an early frame. Use
+// Timestamp::checked_sub to detect this case.
+impl ops::Sub<time::Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, rhs: time::Duration) -> Self::Output {
+        Self::from(time::Duration::from(self).saturating_sub(rhs))
+    }
 }
 
 impl fmt::Display for Timestamp {
@@ -67,3 +162,139 @@ impl From<Timestamp> for time::Duration {
         Self::new(ts.sec as u64, (ts.usec * 1000) as u32)
     }
 }
+
+/// Maps monotonic buffer timestamps onto the system (wall) clock
+///
+/// V4L2 drivers usually stamp buffers with `CLOCK_MONOTONIC` (see
+/// [`crate::buffer::Flags::TIMESTAMP_MONOTONIC`]), which is only meaningful within a single boot.
+/// Both clocks are sampled at the same instant to compute their offset, which is refreshed once
+/// the sample is older than the configured interval to account for clock drift and adjustments
+/// (e.g. NTP) of the realtime clock.
+#[derive(Debug, Clone, Copy)]
+pub struct WallClock {
+    /// `CLOCK_MONOTONIC` at the time of sampling
+    monotonic: time::Duration,
+    /// `CLOCK_REALTIME` at the time of sampling
+    realtime: time::SystemTime,
+    /// Maximum age of a sample before it is refreshed
+    interval: time::Duration,
+}
+
+impl WallClock {
+    /// Returns a wall clock mapping with a freshly sampled clock offset
+    ///
+    /// The offset is re-sampled every 10 seconds by default.
+    pub fn new() -> io::Result<Self> {
+        Self::with_interval(time::Duration::from_secs(10))
+    }
+
+    /// Returns a wall clock mapping which re-samples the clock offset at the given interval
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Maximum age of the clock offset before it is sampled again
+    pub fn with_interval(interval: time::Duration) -> io::Result<Self> {
+        let (monotonic, realtime) = Self::sample()?;
+        Ok(WallClock {
+            monotonic,
+            realtime,
+            interval,
+        })
+    }
+
+    fn sample() -> io::Result<(time::Duration, time::SystemTime)> {
+        let monotonic = clock_gettime(libc::CLOCK_MONOTONIC)?;
+        let realtime = clock_gettime(libc::CLOCK_REALTIME)?;
+        Ok((monotonic, time::UNIX_EPOCH + realtime))
+    }
+
+    /// Samples both clocks again to update the offset
+    pub fn resample(&mut self) -> io::Result<()> {
+        let (monotonic, realtime) = Self::sample()?;
+        self.monotonic = monotonic;
+        self.realtime = realtime;
+        Ok(())
+    }
+
+    /// Re-samples the clock offset if the last sample is older than the configured interval
+    pub fn refresh(&mut self) -> io::Result<()> {
+        let now = clock_gettime(libc::CLOCK_MONOTONIC)?;
+        if now.saturating_sub(self.monotonic) >= self.interval {
+            self.resample()?;
+        }
+        Ok(())
+    }
+
+    /// Converts a monotonic timestamp into wall clock time using the current offset
+    ///
+    /// # Arguments
+    ///
+    /// * `ts` - Timestamp in the `CLOCK_MONOTONIC` domain
+    pub fn to_system_time(&self, ts: Timestamp) -> time::SystemTime {
+        let ts = time::Duration::from(ts);
+        if ts >= self.monotonic {
+            self.realtime + (ts - self.monotonic)
+        } else {
+            self.realtime - (self.monotonic - ts)
+        }
+    }
+}
+
+pub(crate) fn clock_gettime(clock: libc::clockid_t) -> io::Result<time::Duration> {
+    let mut ts: libc::timespec = unsafe { mem::zeroed() };
+    if unsafe { libc::clock_gettime(clock, &mut ts) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(time::Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wall_clock_to_system_time() {
+        let clock = WallClock {
+            monotonic: time::Duration::from_secs(100),
+            realtime: time::UNIX_EPOCH + time::Duration::from_secs(1_000_000),
+            interval: time::Duration::from_secs(10),
+        };
+
+        let td = vec![
+            (Timestamp::new(100, 0), 1_000_000_000_000),
+            (Timestamp::new(101, 500), 1_000_001_000_500),
+            (Timestamp::new(99, 0), 999_999_000_000),
+        ];
+
+        for (ts, expected_us) in td {
+            let wall = clock.to_system_time(ts);
+            let us = wall.duration_since(time::UNIX_EPOCH).unwrap().as_micros();
+            assert_eq!(expected_us, us);
+        }
+    }
+
+    #[test]
+    fn test_timestamp_arithmetic() {
+        let a = Timestamp::new(1, 999_000);
+        let b = a + time::Duration::from_millis(2);
+        assert_eq!(Timestamp::new(2, 1_000), b);
+        assert_eq!(a, b - time::Duration::from_millis(2));
+        assert!(a < b);
+
+        // subtracting more than the timestamp saturates at zero
+        assert_eq!(Timestamp::new(0, 0), a - time::Duration::from_secs(5));
+        assert_eq!(None, a.checked_sub(time::Duration::from_secs(5)));
+        assert_eq!(
+            Some(Timestamp::new(0, 999_000)),
+            a.checked_sub(time::Duration::from_secs(1))
+        );
+
+        // non-normalized timestamps compare by their total value
+        assert_eq!(Timestamp::new(2, 1_000), Timestamp::new(1, 1_001_000));
+
+        assert_eq!(Ok(time::Duration::from_millis(2)), b.since(a));
+        assert_eq!(Err(time::Duration::from_millis(2)), a.since(b));
+        assert_eq!(Ok(time::Duration::ZERO), a.since(a));
+    }
+}
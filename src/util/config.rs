@@ -0,0 +1,310 @@
+use std::{collections::BTreeMap, fmt, io};
+
+use super::control::{
+    ControlDevice, ControlTable, Request, Requests, UnsupportedControlDetail, Value,
+};
+use crate::control::Value as CValue;
+use crate::device::Device;
+use crate::format::fourcc::ParseFourCCError;
+use crate::format::Format;
+use crate::fraction::Fraction;
+use crate::video::capture::Parameters;
+use crate::video::Capture;
+
+/// フォーマット、フレームレート、Controlをまとめたカメラのプロファイル
+///
+/// `serde` featureを有効にすると、YAMLやJSONのファイルから読み込める
+///
+/// ```yaml
+/// format:
+///   fourcc: YUYV
+///   width: 1280
+///   height: 720
+/// frame_rate: 30
+/// controls:
+///   brightness: 10
+///   white_balance_automatic: false
+///   gain: "50%"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceConfig {
+    /// 設定するフォーマット (省略時は変更しない)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub format: Option<FormatConfig>,
+    /// 設定するフレームレート [fps] (省略時は変更しない)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub frame_rate: Option<u32>,
+    /// Controlの名前と値 (名前は`ToCtrlName`で変換したもの)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub controls: BTreeMap<String, Value>,
+}
+
+/// プロファイルのフォーマット
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatConfig {
+    /// ピクセルフォーマット (`YUYV`, `MJPG` など4文字)
+    pub fourcc: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// プロファイルを適用した結果
+///
+/// ドライバはリクエストを近い値に調整するので、実際に設定された値を保持する
+#[derive(Debug)]
+pub struct AppliedConfig {
+    /// 実際に設定されたフォーマット
+    pub format: Option<Format>,
+    /// 実際に設定されたフレーム間隔 [s]
+    pub frame_interval: Option<Fraction>,
+    /// 設定できなかった、または読み出した値がリクエストと異なるControl
+    pub mismatches: Vec<UnsupportedControlDetail>,
+}
+
+impl DeviceConfig {
+    /// プロファイルをデバイスに適用する
+    ///
+    /// フォーマット、フレームレート、Controlの順に設定する。
+    /// フォーマットとフレームレートの設定に失敗した場合はエラーを返し、
+    /// Controlの設定に失敗した場合は`AppliedConfig::mismatches`に記録して続行する
+    pub fn apply_to(&self, dev: &Device) -> Result<AppliedConfig, ConfigError> {
+        let format = match &self.format {
+            Some(f) => {
+                let fourcc = f.fourcc.parse().map_err(ConfigError::FourCC)?;
+                Some(
+                    Capture::set_format(dev, &Format::new(f.width, f.height, fourcc))
+                        .map_err(ConfigError::Format)?,
+                )
+            }
+            None => None,
+        };
+
+        let frame_interval = match self.frame_rate {
+            Some(fps) => Some(
+                Capture::set_params(dev, &Parameters::with_fps(fps))
+                    .map_err(ConfigError::FrameRate)?
+                    .interval,
+            ),
+            None => None,
+        };
+
+        let mismatches = self.apply_controls(dev)?;
+
+        Ok(AppliedConfig {
+            format,
+            frame_interval,
+            mismatches,
+        })
+    }
+
+    /// プロファイルのControlだけをデバイスに設定する
+    ///
+    /// 設定できなかった、または読み出した値がリクエストと異なるControlを返す。
+    /// 無効 (INACTIVE) なControlも設定するので、オートモードの解除と手動の値を同時に指定できる
+    pub fn apply_controls(
+        &self,
+        dev: &impl ControlDevice,
+    ) -> Result<Vec<UnsupportedControlDetail>, ConfigError> {
+        let mut mismatches = vec![];
+        if !self.controls.is_empty() {
+            let controls = dev.query_controls().map_err(ConfigError::Controls)?;
+            let table = ControlTable::from(controls.as_slice());
+            for (name, value) in self.controls.iter() {
+                if let Some(detail) = apply_control(dev, &table, name, normalize(value)) {
+                    mismatches.push(detail);
+                }
+            }
+        }
+        Ok(mismatches)
+    }
+}
+
+/// プロファイルを適用できなかった場合のエラー
+#[derive(Debug)]
+pub enum ConfigError {
+    /// ピクセルフォーマットが4文字のFourCCではない
+    FourCC(ParseFourCCError),
+    /// フォーマットを設定できなかった
+    Format(io::Error),
+    /// フレームレートを設定できなかった
+    FrameRate(io::Error),
+    /// Controlを問い合わせられなかった
+    Controls(io::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::FourCC(e) => write!(f, "{}", e),
+            ConfigError::Format(e) => write!(f, "failed to set format: {}", e),
+            ConfigError::FrameRate(e) => write!(f, "failed to set frame rate: {}", e),
+            ConfigError::Controls(e) => write!(f, "failed to query controls: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::FourCC(e) => Some(e),
+            ConfigError::Format(e) | ConfigError::FrameRate(e) | ConfigError::Controls(e) => {
+                Some(e)
+            }
+        }
+    }
+}
+
+impl From<ConfigError> for io::Error {
+    fn from(e: ConfigError) -> Self {
+        let kind = match &e {
+            ConfigError::FourCC(_) => io::ErrorKind::InvalidInput,
+            ConfigError::Format(e) | ConfigError::FrameRate(e) | ConfigError::Controls(e) => {
+                e.kind()
+            }
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+/// 文字列で記述された値を、コマンドラインのリクエストと同じ規則で解釈し直す
+///
+/// YAMLなどでは`"50%"`を文字列としてしか書けないため
+fn normalize(value: &Value) -> Value {
+    match value {
+        Value::String(s) => Value::from(s.as_str()),
+        value => value.clone(),
+    }
+}
+
+/// Controlを1つ設定し、読み出した値と比較する
+///
+/// Controlごとにクラスが異なる可能性があるので、1つずつ設定する
+fn apply_control(
+    dev: &impl ControlDevice,
+    table: &ControlTable,
+    name: &str,
+    value: Value,
+) -> Option<UnsupportedControlDetail> {
+    let reqs = Requests::new(vec![Request::new(name, value)]);
+    if let Some(detail) = table.check(&reqs).pop() {
+        return Some(detail);
+    }
+
+    let ctrl = table.get_control(&reqs).pop()?;
+    let id = ctrl.id;
+    let requested = ctrl.value.clone();
+    if let Err(e) = dev.set_control(ctrl) {
+        return Some(UnsupportedControlDetail {
+            name: name.to_string(),
+            detail: format!("Failed to set: {}", e),
+        });
+    }
+
+    // 読み出せないControlは比較しない
+    let actual = dev
+        .query_control(id)
+        .and_then(|desc| dev.control(&desc))
+        .ok()?
+        .value;
+    match (&requested, &actual) {
+        (CValue::Integer(_), CValue::Integer(_)) | (CValue::Boolean(_), CValue::Boolean(_))
+            if requested != actual =>
+        {
+            Some(UnsupportedControlDetail {
+                name: name.to_string(),
+                detail: format!("Applied {:?}, read back {:?}", requested, actual),
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize(&Value::String("50%".to_string())),
+            Value::Percent(50.0)
+        );
+        assert_eq!(
+            normalize(&Value::String("10".to_string())),
+            Value::Integer(10)
+        );
+        assert_eq!(
+            normalize(&Value::String("auto".to_string())),
+            Value::String("auto".to_string())
+        );
+        assert_eq!(normalize(&Value::Boolean(true)), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_config_error() {
+        let e = ConfigError::FourCC(ParseFourCCError("YUV".to_string()));
+        assert_eq!(io::Error::from(e).kind(), io::ErrorKind::InvalidInput);
+
+        let e = ConfigError::Format(io::Error::from(io::ErrorKind::InvalidInput));
+        assert!(e.to_string().starts_with("failed to set format"));
+        assert_eq!(io::Error::from(e).kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_apply_controls() {
+        use crate::control::{Description, Flags, Type};
+        use crate::util::mock::MockDevice;
+        use crate::v4l_sys::{V4L2_CID_EXPOSURE_ABSOLUTE, V4L2_CID_EXPOSURE_AUTO};
+
+        let desc = |id, name: &str, maximum, default| Description {
+            id,
+            typ: Type::Integer,
+            name: name.to_string(),
+            minimum: 0,
+            maximum,
+            step: 1,
+            default,
+            flags: Flags::empty(),
+            elem_size: 4,
+            elems: 1,
+            items: None,
+        };
+        // オートモード (3) のときは手動露出が無効になる
+        let mut dev = MockDevice::new(vec![
+            desc(V4L2_CID_EXPOSURE_AUTO, "Auto Exposure", 3, 3),
+            desc(
+                V4L2_CID_EXPOSURE_ABSOLUTE,
+                "Exposure Time, Absolute",
+                5000,
+                50,
+            ),
+        ]);
+        dev.inactive_unless(V4L2_CID_EXPOSURE_ABSOLUTE, V4L2_CID_EXPOSURE_AUTO, 1);
+
+        let config = DeviceConfig {
+            controls: vec![
+                ("auto_exposure".to_string(), Value::Integer(1)),
+                ("exposure_time_absolute".to_string(), Value::Integer(100)),
+            ]
+            .into_iter()
+            .collect(),
+            ..DeviceConfig::default()
+        };
+        assert!(config.apply_controls(&dev).unwrap().is_empty());
+        assert_eq!(
+            Some(CValue::Integer(100)),
+            dev.value(V4L2_CID_EXPOSURE_ABSOLUTE)
+        );
+
+        let config = DeviceConfig {
+            controls: vec![("exposure".to_string(), Value::Integer(100))]
+                .into_iter()
+                .collect(),
+            ..DeviceConfig::default()
+        };
+        let mismatches = config.apply_controls(&dev).unwrap();
+        assert_eq!("exposure", mismatches[0].name);
+    }
+}
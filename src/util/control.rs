@@ -1,6 +1,8 @@
-use std::{collections::BTreeMap, convert::TryFrom};
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter::FromIterator;
+use std::{convert::TryFrom, fmt, io, mem};
 
-use super::ctrl_name::ToCtrlName;
+use super::ctrl_name::{nearest_ctrl_name, ToCtrlName};
 use crate::{control::Value as CValue, Control};
 
 /// 変更リクエストを保持する構造体
@@ -12,20 +14,174 @@ impl Requests {
     pub fn new(requests: Vec<Request>) -> Self {
         Self { requests }
     }
+
+    /// 別のリクエストを後から適用するように統合する
+    ///
+    /// 同じControlに対するリクエストは後の値で上書きし、最初に現れた位置を保つ。
+    /// ベースのプロファイルに実行時の設定を重ねるために使う
+    pub fn merge(&mut self, other: Requests) {
+        let mut merged: Vec<Request> = Vec::with_capacity(self.requests.len());
+        for r in self.requests.drain(..).chain(other.requests) {
+            match merged.iter_mut().find(|m| m.name == r.name) {
+                Some(m) => m.value = r.value,
+                None => merged.push(r),
+            }
+        }
+        self.requests = merged;
+    }
+
+    /// リクエストの数を返す
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// リクエストが空か
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// リクエストを順に返す
+    pub fn iter(&self) -> impl Iterator<Item = &Request> {
+        self.requests.iter()
+    }
+
+    /// JSONのプロファイルに変換する
+    ///
+    /// `{"gain": 10, "white_balance": "auto"}`のように名前と値のオブジェクトで、リクエストの順序を保つ
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// JSONのプロファイルからリクエストを作成する
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+// 手で編集しやすいように、名前をキーにしたオブジェクトとして扱う
+#[cfg(feature = "serde")]
+impl serde::Serialize for Requests {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.requests.len()))?;
+        for r in self.requests.iter() {
+            map.serialize_entry(&r.name, &r.value)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Requests {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RequestsVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for RequestsVisitor {
+            type Value = Requests;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a map of control names to values")
+            }
+
+            // 設定の順序に意味がある場合があるので、キーの順序を保つ
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut access: A,
+            ) -> Result<Requests, A::Error> {
+                let mut requests = vec![];
+                while let Some((name, value)) = access.next_entry::<String, Value>()? {
+                    requests.push(Request { name, value });
+                }
+                Ok(Requests::new(requests))
+            }
+        }
+
+        deserializer.deserialize_map(RequestsVisitor)
+    }
 }
 
 impl TryFrom<&str> for Requests {
-    type Error = String;
+    type Error = ControlParseError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let requests = value
-            .split(',')
+        if value.is_empty() {
+            return Err(ControlParseError::Empty);
+        }
+        let requests = split_requests(value)?
+            .into_iter()
             .map(Request::try_from)
             .collect::<Result<Vec<_>, _>>()?;
         Ok(Self::new(requests))
     }
 }
 
+/// コマンドラインの引数のように、1要素を1つのリクエストとして解釈する
+///
+/// 要素は`,`で分割しないので、値に`,`を含めるためにクォートする必要はない。空の要素は無視する
+impl<S: AsRef<str>> TryFrom<&[S]> for Requests {
+    type Error = ControlParseError;
+    fn try_from(values: &[S]) -> Result<Self, Self::Error> {
+        values
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|value| !value.is_empty())
+            .map(Request::try_from)
+            .collect()
+    }
+}
+
+impl FromIterator<Request> for Requests {
+    fn from_iter<I: IntoIterator<Item = Request>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+/// リクエストの一覧を`,`で分割する
+///
+/// `"`で囲まれた部分の`,`では分割しない。囲まれた部分では`\"`と`\\`でエスケープできる
+fn split_requests(value: &str) -> Result<Vec<&str>, ControlParseError> {
+    let mut items = vec![];
+    let mut start = 0;
+    let mut quoted = false;
+    let mut chars = value.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => {
+                chars.next();
+            }
+            ',' if !quoted => {
+                items.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if quoted {
+        return Err(ControlParseError::UnterminatedQuote(value.to_string()));
+    }
+    items.push(&value[start..]);
+    Ok(items)
+}
+
+/// `"`で囲まれた値から`"`を取り除き、エスケープを元に戻す
+fn unquote(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut s = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => s.extend(chars.next()),
+            c => s.push(c),
+        }
+    }
+    Some(s)
+}
+
 /// ユーザーが記述する変更リクエストを保持する構造体
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Request {
     name: String,
     value: Value,
@@ -38,66 +194,350 @@ impl Request {
             value,
         }
     }
+
+    /// 対象のControlの名前を返す
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 設定する値を返す
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
 }
 
 impl TryFrom<&str> for Request {
-    type Error = String;
+    type Error = ControlParseError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if value.is_empty() {
+            return Err(ControlParseError::Empty);
+        }
         let mut split = value.splitn(2, '=');
-        let name = split.next().ok_or(format!("field not found [{}]", value))?;
-        let value = split.next().ok_or(format!("value not found [{}]", value))?;
+        let name = split
+            .next()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| ControlParseError::MissingField(value.to_string()))?;
+        let value = split
+            .next()
+            .ok_or_else(|| ControlParseError::MissingValue(value.to_string()))?;
+        // "で囲まれた値は常に文字列として扱う
+        let value = match unquote(value) {
+            Some(s) => Value::String(s),
+            None => Value::from(value),
+        };
         Ok(Self {
             name: name.to_string(),
-            value: Value::from(value),
+            value,
         })
     }
 }
 
+/// 変更リクエストの文字列を解釈できなかった場合のエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlParseError {
+    /// Controlの名前がない (`=10`など)
+    MissingField(String),
+    /// 値がない (`gain`など)
+    MissingValue(String),
+    /// リクエストが空
+    Empty,
+    /// `"`が閉じられていない
+    UnterminatedQuote(String),
+}
+
+impl fmt::Display for ControlParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlParseError::MissingField(req) => write!(f, "field not found [{}]", req),
+            ControlParseError::MissingValue(req) => write!(f, "value not found [{}]", req),
+            ControlParseError::Empty => write!(f, "empty request"),
+            ControlParseError::UnterminatedQuote(req) => {
+                write!(f, "unterminated quote [{}]", req)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ControlParseError {}
+
 /// 変更リクエストで設定可能な値の種類
+///
+/// `serde` featureでは型のタグなしで記述する。整数は`10`、小数は`1.5`、真偽値は`true`、
+/// 割合は`"50%"`、`Raw`は`[1, 2]`、`Bytes`は`{"bytes": [1, 2]}`のように表す。
+/// 数値として解釈できる`%`で終わる文字列は割合になる
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Integer(i64),
     Boolean(bool),
     String(String),
+    /// ベンダー固有のControlに渡すバイト列
+    Raw(Vec<u8>),
+    /// Controlの設定可能な範囲に対する割合 (0〜100)
+    Percent(f32),
+    /// 配列のControl (U8/U16/U32) に渡すバイト列
+    ///
+    /// U16とU32の要素はネイティブのバイトオーダーで並べる
+    Bytes(Vec<u8>),
+    /// 固定小数点のControlに渡す小数
+    ///
+    /// `ControlTable::set_scale`で設定した倍率を掛けて整数に丸める
+    Float(f64),
 }
 
-impl From<Value> for CValue {
-    fn from(val: Value) -> Self {
+/// 割合と小数は対象のControlの範囲や倍率が分からないと変換できないので、変換せずに返す。
+/// これらの値は`ControlTable::get_control`で変換する
+impl TryFrom<Value> for CValue {
+    type Error = Value;
+    fn try_from(val: Value) -> Result<Self, Self::Error> {
         match val {
-            Value::Integer(i) => CValue::Integer(i),
-            Value::Boolean(b) => CValue::Boolean(b),
-            Value::String(s) => CValue::String(s),
+            Value::Integer(i) => Ok(CValue::Integer(i)),
+            Value::Boolean(b) => Ok(CValue::Boolean(b)),
+            Value::String(s) => Ok(CValue::String(s)),
+            Value::Raw(r) => Ok(CValue::Raw(r)),
+            Value::Bytes(b) => Ok(CValue::CompoundU8(b)),
+            val @ (Value::Float(_) | Value::Percent(_)) => Err(val),
         }
     }
 }
 
 impl From<&str> for Value {
     fn from(s: &str) -> Self {
-        if let Ok(i) = s.parse::<i64>() {
+        if let Some(p) = s.strip_suffix('%').and_then(|p| p.parse::<f32>().ok()) {
+            Value::Percent(p)
+        } else if let Some(i) = parse_radix(s) {
+            Value::Integer(i)
+        } else if let Ok(i) = s.parse::<i64>() {
             Value::Integer(i)
         } else if let Ok(b) = s.parse::<bool>() {
             Value::Boolean(b)
+        } else if let Some(f) = parse_float(s) {
+            Value::Float(f)
         } else {
             Value::String(s.to_string())
         }
     }
 }
 
+// 割合と小数、RawとBytesを区別できるように、値の型ごとに表現を分ける
+#[cfg(feature = "serde")]
+impl serde::Serialize for Value {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        match self {
+            Value::Integer(i) => serializer.serialize_i64(*i),
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Raw(r) => r.serialize(serializer),
+            Value::Percent(p) => serializer.collect_str(&format_args!("{}%", p)),
+            Value::Bytes(b) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("bytes", b)?;
+                map.end()
+            }
+            Value::Float(f) => serializer.serialize_f64(*f),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Value {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ValueVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ValueVisitor {
+            type Value = Value;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a number, boolean, string, byte array or {\"bytes\": [..]}")
+            }
+
+            fn visit_bool<E: serde::de::Error>(self, v: bool) -> Result<Value, E> {
+                Ok(Value::Boolean(v))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Value, E> {
+                Ok(Value::Integer(v))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Value, E> {
+                i64::try_from(v)
+                    .map(Value::Integer)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<Value, E> {
+                Ok(Value::Float(v))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Value, E> {
+                match v.strip_suffix('%').and_then(|p| p.parse::<f32>().ok()) {
+                    Some(p) => Ok(Value::Percent(p)),
+                    None => Ok(Value::String(v.to_string())),
+                }
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut access: A,
+            ) -> Result<Value, A::Error> {
+                let mut raw = Vec::with_capacity(access.size_hint().unwrap_or(0));
+                while let Some(b) = access.next_element::<u8>()? {
+                    raw.push(b);
+                }
+                Ok(Value::Raw(raw))
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut access: A,
+            ) -> Result<Value, A::Error> {
+                use serde::de::Error;
+
+                let bytes = match access.next_key::<String>()? {
+                    Some(key) if key == "bytes" => access.next_value::<Vec<u8>>()?,
+                    Some(key) => return Err(A::Error::unknown_field(&key, &["bytes"])),
+                    None => return Err(A::Error::missing_field("bytes")),
+                };
+                if let Some(key) = access.next_key::<String>()? {
+                    return Err(A::Error::unknown_field(&key, &["bytes"]));
+                }
+                Ok(Value::Bytes(bytes))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// `0x`で始まる16進数と`0b`で始まる2進数を解釈する (負の値は`-0x10`のように書く)
+fn parse_radix(s: &str) -> Option<i64> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let value = if let Some(h) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        i64::from_str_radix(h, 16).ok()?
+    } else if let Some(b) = s.strip_prefix("0b").or_else(|| s.strip_prefix("0B")) {
+        i64::from_str_radix(b, 2).ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// 小数を解釈する
+///
+/// `inf`や`nan`のようなメニューの項目名と紛らわしい値は小数として扱わない
+fn parse_float(s: &str) -> Option<f64> {
+    if !s.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.') {
+        return None;
+    }
+    s.parse::<f64>().ok().filter(|f| f.is_finite())
+}
+
+/// Controlを読み書きできるデバイス
+///
+/// `ControlTable`や`ControlSnapshot`はこのトレイトを通してデバイスにアクセスするので、
+/// テストではハードウェアの代わりに`MockDevice`を使える
+pub trait ControlDevice {
+    /// デバイスのControlの一覧を返す
+    fn query_controls(&self) -> io::Result<Vec<crate::control::Description>>;
+    /// idに対応するControlの情報を返す
+    fn query_control(&self, id: u32) -> io::Result<crate::control::Description>;
+    /// Controlの現在の値を返す
+    fn control(&self, desc: &crate::control::Description) -> io::Result<Control>;
+    /// Controlに値を設定する
+    fn set_control(&self, ctrl: Control) -> io::Result<()>;
+}
+
+impl ControlDevice for crate::device::Device {
+    fn query_controls(&self) -> io::Result<Vec<crate::control::Description>> {
+        crate::device::Device::query_controls(self)
+    }
+
+    fn query_control(&self, id: u32) -> io::Result<crate::control::Description> {
+        crate::device::Device::query_control(self, id)
+    }
+
+    fn control(&self, desc: &crate::control::Description) -> io::Result<Control> {
+        crate::device::Device::control(self, desc)
+    }
+
+    fn set_control(&self, ctrl: Control) -> io::Result<()> {
+        crate::device::Device::set_control(self, ctrl)
+    }
+}
+
 /// 対象デバイスのControlに対して、設定可能な値やデフォルト値、idなどの情報を保持する構造体
 #[derive(Debug)]
 pub struct ControlDesc {
     id: u32,
+    name: String,
     value: CValue,
     minimum: i64,
     maximum: i64,
+    step: i64,
+    typ: crate::control::Type,
+    flags: crate::control::Flags,
+    items: Vec<(u32, String)>,
+    /// 配列の要素数と要素のバイト数
+    elems: u32,
+    elem_size: u32,
+    /// 小数の値に掛ける倍率 (固定小数点のControl用)
+    scale: f64,
 }
 
 impl ControlDesc {
-    fn check(&self, name: impl Into<String>, value: &Value) -> Option<UnsupportedControlDeatil> {
+    fn check(&self, name: impl Into<String>, value: &Value) -> Option<UnsupportedControlDetail> {
         match (value, &self.value) {
+            // ビットマスクのmaximumは有効なビットの集合
+            (Value::Integer(i), CValue::Integer(_))
+                if self.typ == crate::control::Type::Bitmask =>
+            {
+                if *i < 0 || *i & !self.maximum != 0 {
+                    Some(UnsupportedControlDetail {
+                        name: name.into(),
+                        detail: format!("Invalid bits {:#x} (valid {:#x})", i, self.maximum),
+                    })
+                } else {
+                    None
+                }
+            }
             (Value::Integer(i), CValue::Integer(_)) => {
                 if *i < self.minimum || *i > self.maximum {
-                    Some(UnsupportedControlDeatil {
+                    Some(UnsupportedControlDetail {
+                        name: name.into(),
+                        detail: "Out of range".to_string(),
+                    })
+                } else if !is_aligned(*i, self.minimum, self.step) {
+                    // ドライバは黙って丸めるので、設定したつもりの値と異なってしまう
+                    Some(UnsupportedControlDetail {
+                        name: name.into(),
+                        detail: format!("Not aligned to step {}", self.step),
+                    })
+                } else {
+                    None
+                }
+            }
+            // 倍率を掛けた整数として、整数と同じ規則でチェックする
+            (Value::Float(f), CValue::Integer(_))
+                if matches!(
+                    self.typ,
+                    crate::control::Type::Integer | crate::control::Type::Integer64
+                ) =>
+            {
+                self.check(name, &Value::Integer(self.scaled(*f)))
+            }
+            // メニューやビットマスクの範囲に対する割合には意味がない
+            (Value::Percent(p), CValue::Integer(_))
+                if matches!(
+                    self.typ,
+                    crate::control::Type::Integer | crate::control::Type::Integer64
+                ) =>
+            {
+                if !(0.0..=100.0).contains(p) {
+                    Some(UnsupportedControlDetail {
                         name: name.into(),
                         detail: "Out of range".to_string(),
                     })
@@ -105,14 +545,193 @@ impl ControlDesc {
                     None
                 }
             }
+            (Value::String(s), CValue::Integer(_)) if self.typ == crate::control::Type::Menu => {
+                if self.menu_index(s).is_none() {
+                    Some(UnsupportedControlDetail {
+                        name: name.into(),
+                        detail: format!("No menu item '{}'", s),
+                    })
+                } else {
+                    None
+                }
+            }
+            // ボタンはどの値でも動作する
+            (_, CValue::None) => None,
             (Value::Boolean(_), CValue::Boolean(_)) => None,
-            (Value::String(_), CValue::String(_)) => None,
-            _ => Some(UnsupportedControlDeatil {
+            // 文字列のminimumとmaximumは文字数の範囲
+            (Value::String(s), CValue::String(_)) => {
+                let len = s.len() as i64;
+                if len < self.minimum || len > self.maximum {
+                    Some(UnsupportedControlDetail {
+                        name: name.into(),
+                        detail: format!(
+                            "Invalid length {} (allowed {}..={})",
+                            len, self.minimum, self.maximum
+                        ),
+                    })
+                } else {
+                    None
+                }
+            }
+            (
+                Value::Bytes(b) | Value::Raw(b),
+                CValue::CompoundU8(_) | CValue::CompoundU16(_) | CValue::CompoundU32(_),
+            ) => {
+                let len = self.elems as usize * self.elem_size as usize;
+                if b.len() != len {
+                    Some(UnsupportedControlDetail {
+                        name: name.into(),
+                        detail: format!("Invalid length {} (expected {})", b.len(), len),
+                    })
+                } else {
+                    None
+                }
+            }
+            (Value::Raw(_) | Value::Bytes(_), CValue::Raw(_)) => None,
+            _ => Some(UnsupportedControlDetail {
                 name: name.into(),
                 detail: format!("Type mismatch: {:?} {:?}", value, self.value),
             }),
         }
     }
+
+    /// 小数に倍率を掛けて整数に丸める
+    fn scaled(&self, value: f64) -> i64 {
+        (value * self.scale).round() as i64
+    }
+
+    /// テーブルの値がデフォルト値として設定できるか
+    fn has_default(&self) -> bool {
+        !matches!(
+            self.value,
+            CValue::None
+                | CValue::String(_)
+                | CValue::Raw(_)
+                | CValue::CompoundU8(_)
+                | CValue::CompoundU16(_)
+                | CValue::CompoundU32(_)
+        )
+    }
+
+    /// ドライバが返したControlの名前を返す
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// ドライバが返したControlのフラグを返す
+    ///
+    /// INACTIVEなどのフラグは他のControlの値によって変わる。テーブルの作成時か`refresh_flags`の時点の値
+    pub fn flags(&self) -> crate::control::Flags {
+        self.flags
+    }
+
+    /// 無効 (INACTIVE) か
+    ///
+    /// オートモードのときの手動露出などで、設定しても値が反映されない
+    pub fn is_inactive(&self) -> bool {
+        self.flags.contains(crate::control::Flags::INACTIVE)
+    }
+
+    /// ドライバが値を自動で変更する (VOLATILE) か
+    pub fn is_volatile(&self) -> bool {
+        self.flags.contains(crate::control::Flags::VOLATILE)
+    }
+
+    /// 書き込み専用 (WRITE_ONLY) で値を読み取れないか
+    pub fn is_write_only(&self) -> bool {
+        self.flags.contains(crate::control::Flags::WRITE_ONLY)
+    }
+
+    /// 値を配列などのペイロードで受け渡す (HAS_PAYLOAD) か
+    pub fn has_payload(&self) -> bool {
+        self.flags.contains(crate::control::Flags::HAS_PAYLOAD)
+    }
+
+    /// 設定可能な値の間隔を返す
+    ///
+    /// 整数の値は`minimum + k * step`でなければならない
+    pub fn step(&self) -> i64 {
+        self.step
+    }
+
+    /// 任意の値を、このControlに設定可能な最も近い値に変換する
+    ///
+    /// `[minimum, maximum]`の範囲に収めてから、最も近い`minimum + k * step`に丸める (中間の場合は大きい方)。
+    /// ドライバが`minimum > maximum`の範囲を返した場合はエラー
+    pub fn coerce(&self, value: i64) -> Result<i64, UnsupportedControlDetail> {
+        coerce(value, self.minimum, self.maximum, self.step).ok_or_else(|| {
+            UnsupportedControlDetail {
+                name: self.name.to_ctrl_name(),
+                detail: format!("Invalid range {}..={}", self.minimum, self.maximum),
+            }
+        })
+    }
+
+    /// メニューの項目名に対応するインデックスを返す
+    ///
+    /// 項目名はControlの名前と同じ規則で変換して比較するので、`Aperture Priority Mode`は`aperture_priority_mode`でも指定できる
+    fn menu_index(&self, label: &str) -> Option<i64> {
+        if self.typ != crate::control::Type::Menu {
+            return None;
+        }
+        let label = label.to_ctrl_name();
+        self.items
+            .iter()
+            .find(|(_, item)| item.to_ctrl_name() == label)
+            .map(|(index, _)| *index as i64)
+    }
+
+    /// リクエストの値をControlの値に変換する
+    ///
+    /// 割合は設定可能な範囲に対応させ、stepに合わせて丸める。メニューの項目名はインデックスに変換する。
+    /// 整数以外のControlへの割合など、変換できない値はエラー
+    fn resolve(&self, value: &Value) -> Result<CValue, UnsupportedControlDetail> {
+        if self.typ == crate::control::Type::Button {
+            return Ok(CValue::None);
+        }
+        let literal = || {
+            CValue::try_from(value.clone()).map_err(|value| UnsupportedControlDetail {
+                name: self.name.to_ctrl_name(),
+                detail: format!("Cannot convert {:?} to {:?}", value, self.typ),
+            })
+        };
+        match value {
+            Value::String(s) => match self.menu_index(s) {
+                Some(index) => Ok(CValue::Integer(index)),
+                None => literal(),
+            },
+            Value::Percent(p)
+                if matches!(
+                    self.typ,
+                    crate::control::Type::Integer | crate::control::Type::Integer64
+                ) =>
+            {
+                // 範囲の幅はi64に収まらないことがあるのでi128で計算する
+                let (minimum, maximum) = (i128::from(self.minimum), i128::from(self.maximum));
+                let offset = ((maximum - minimum) as f64 * *p as f64 / 100.0).round() as i128;
+                let target = (minimum + offset).clamp(minimum, maximum) as i64;
+                // 範囲が不正な場合は対応させられない
+                self.coerce(target).map(CValue::Integer)
+            }
+            // バイト列を配列の要素の型に詰め直す
+            Value::Bytes(b) | Value::Raw(b) => match self.value {
+                CValue::CompoundU8(_) => Ok(CValue::CompoundU8(b.clone())),
+                CValue::CompoundU16(_) => Ok(CValue::CompoundU16(
+                    b.chunks_exact(2)
+                        .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+                        .collect(),
+                )),
+                CValue::CompoundU32(_) => Ok(CValue::CompoundU32(
+                    b.chunks_exact(4)
+                        .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                        .collect(),
+                )),
+                _ => literal(),
+            },
+            Value::Float(f) => Ok(CValue::Integer(self.scaled(*f))),
+            _ => literal(),
+        }
+    }
 }
 
 /// 対象デバイスのControlの情報を保持する構造体
@@ -149,23 +768,42 @@ impl ControlDesc {
 #[derive(Debug)]
 pub struct ControlTable {
     map: BTreeMap<String, ControlDesc>,
+    /// 書き込めないのでテーブルに含めないControlの名前
+    read_only: BTreeSet<String>,
+    notes: Vec<ControlNote>,
 }
 
 impl From<&[crate::control::Description]> for ControlTable {
     fn from(controls: &[crate::control::Description]) -> Self {
         use crate::control::{Flags, Type, Value};
+        use crate::v4l_sys::V4L2_CTRL_CLASS_USER;
 
         let mut map = BTreeMap::new();
+        let mut read_only = BTreeSet::new();
+        let mut notes = vec![];
 
         for control in controls {
             if control.flags & Flags::READ_ONLY == Flags::READ_ONLY {
+                read_only.insert(control.name.to_ctrl_name());
                 continue;
             }
 
             let value = match control.typ {
                 Type::Integer | Type::Integer64 => Value::Integer(control.default),
+                Type::Bitmask => Value::Integer(control.default),
+                // ボタンは値を持たず、書き込むと動作する
+                Type::Button => Value::None,
                 Type::Boolean => Value::Boolean(control.default != 0),
-                Type::Menu => Value::Integer(control.default),
+                Type::Menu => Value::Integer(menu_default(control, &mut notes)),
+                // 文字列と配列のデフォルト値は問い合わせないと分からないので、空の値で型だけを表す
+                Type::String => Value::String(String::new()),
+                Type::U8 => Value::CompoundU8(Vec::new()),
+                Type::U16 => Value::CompoundU16(Vec::new()),
+                Type::U32 => Value::CompoundU32(Vec::new()),
+                // USERクラスで型が不明なControlはベンダー固有のバイト列として扱う
+                Type::Unknown(_) if control.id & 0xFFFF0000 == V4L2_CTRL_CLASS_USER => {
+                    Value::Raw(Vec::new())
+                }
                 // TODO: 他に対応可能な型があれば適宜追加
                 _ => continue,
             };
@@ -173,42 +811,172 @@ impl From<&[crate::control::Description]> for ControlTable {
                 control.name.to_ctrl_name(),
                 ControlDesc {
                     id: control.id,
+                    name: control.name.clone(),
                     value,
                     minimum: control.minimum,
                     maximum: control.maximum,
+                    step: control.step as i64,
+                    typ: control.typ,
+                    flags: control.flags,
+                    items: control
+                        .items
+                        .iter()
+                        .flatten()
+                        .map(|(index, item)| (*index, item.to_string()))
+                        .collect(),
+                    elems: control.elems,
+                    elem_size: control.elem_size,
+                    scale: 1.0,
                 },
             );
         }
 
-        ControlTable { map }
+        ControlTable {
+            map,
+            read_only,
+            notes,
+        }
+    }
+}
+
+/// 値を`[minimum, maximum]`の範囲に収めて、最も近い`minimum + k * step`に丸める
+///
+/// `minimum > maximum`の場合は`None`。範囲の端の値でも桁あふれしないようにi128で計算する
+pub(crate) fn coerce(value: i64, minimum: i64, maximum: i64, step: i64) -> Option<i64> {
+    if minimum > maximum {
+        return None;
+    }
+    let step = i128::from(step.max(1));
+    let (minimum, maximum) = (i128::from(minimum), i128::from(maximum));
+    let offset = i128::from(value).clamp(minimum, maximum) - minimum;
+    let steps = ((offset + step / 2) / step).min((maximum - minimum) / step);
+    // 結果は[minimum, maximum]に収まる
+    Some((minimum + steps * step) as i64)
+}
+
+/// 値が`minimum + k * step`か
+///
+/// `minimum`からの差はi64に収まらないことがあるのでi128で計算する。stepが1以下の場合は常にtrue
+pub(crate) fn is_aligned(value: i64, minimum: i64, step: i64) -> bool {
+    step <= 1 || (i128::from(value) - i128::from(minimum)) % i128::from(step) == 0
+}
+
+/// メニューのデフォルト値が有効な項目か確認する
+///
+/// ドライバによっては列挙された項目に含まれないデフォルト値を返すので、その場合は最初の有効な項目を使う
+fn menu_default(control: &crate::control::Description, notes: &mut Vec<ControlNote>) -> i64 {
+    let items = match &control.items {
+        Some(items) if !items.is_empty() => items,
+        _ => return control.default,
+    };
+    if items
+        .iter()
+        .any(|(index, _)| *index as i64 == control.default)
+    {
+        return control.default;
     }
+
+    let first = items[0].0 as i64;
+    notes.push(ControlNote {
+        name: control.name.to_ctrl_name(),
+        detail: format!(
+            "Invalid default menu item {}, using {} instead",
+            control.default, first
+        ),
+    });
+    first
 }
 
 impl ControlTable {
     /// リクエストがサポートされているかチェックする
-    pub fn check(&self, reqs: &Requests) -> Vec<UnsupportedControlDeatil> {
+    ///
+    /// 無効 (INACTIVE) なControlは他のControlを設定すると有効になることがあるので、エラーに含まれない。
+    /// 無効なControlへのリクエストは`warnings`で確認する
+    pub fn check(&self, reqs: &Requests) -> Vec<UnsupportedControlDetail> {
         let mut v = vec![];
         for r in reqs.requests.iter() {
-            if !self.map.contains_key(r.name.as_str()) {
-                v.push(UnsupportedControlDeatil {
+            match self.map.get(r.name.as_str()) {
+                Some(desc) => v.extend(desc.check(r.name.as_str(), &r.value)),
+                None => v.push(self.missing(&r.name)),
+            }
+        }
+        v
+    }
+
+    /// 作成時点で無効 (INACTIVE) なControlへのリクエストを返す
+    ///
+    /// オートモードのときの手動露出のように、そのまま設定しても反映されない可能性がある。
+    /// 同じリクエストでオートモードを解除する場合は`apply_ordered`で設定すれば反映される。
+    /// `check`でエラーになるリクエストは含まれない
+    pub fn warnings(&self, reqs: &Requests) -> Vec<UnsupportedControlDetail> {
+        reqs.requests
+            .iter()
+            .filter_map(|r| {
+                let desc = self.map.get(r.name.as_str())?;
+                if !desc.is_inactive() || desc.check(r.name.as_str(), &r.value).is_some() {
+                    return None;
+                }
+                Some(UnsupportedControlDetail {
                     name: r.name.clone(),
-                    detail: "Control not found".to_string(),
-                });
-            } else {
-                let desc = self.map.get(r.name.as_str()).unwrap();
-                if let Some(detail) = desc.check(r.name.as_str(), &r.value) {
-                    v.push(detail);
+                    detail: "Control is inactive".to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// テーブルのControlのフラグをデバイスに問い合わせて更新する
+    ///
+    /// INACTIVEなどのフラグは他のControlの値によって変わるので、Controlを設定した後に呼ぶ
+    pub fn refresh_flags(&mut self, dev: &impl ControlDevice) -> io::Result<()> {
+        for desc in self.map.values_mut() {
+            desc.flags = dev.query_control(desc.id)?.flags;
+        }
+        Ok(())
+    }
+
+    /// リクエストがすべてサポートされているかチェックする
+    ///
+    /// `check`と同じだが、CLIなどで`?`を使えるように`Result`で返す
+    pub fn validate(&self, reqs: &Requests) -> Result<(), ControlCheckErrors> {
+        let errors = self.check(reqs);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ControlCheckErrors(errors))
+        }
+    }
+
+    /// テーブルに含まれないControlのエラーを返す
+    ///
+    /// 読み取り専用のControlは存在するが設定できないので、存在しないControlと区別する。
+    /// 存在しない場合は、名前の似ているControlを候補として示す
+    fn missing(&self, name: &str) -> UnsupportedControlDetail {
+        let detail = if self.read_only.contains(name) {
+            "Control is read-only".to_string()
+        } else {
+            match nearest_ctrl_name(name, self.names()) {
+                Some(suggestion) => {
+                    format!("Control not found (did you mean '{}'?)", suggestion)
                 }
+                None => "Control not found".to_string(),
             }
+        };
+        UnsupportedControlDetail {
+            name: name.to_string(),
+            detail,
         }
-        v
     }
 
     /// リクエストに対応するControlのデフォルト値を取得する
+    ///
+    /// ボタンはデフォルト値を持たないので含まれない。文字列と配列もデフォルト値が分からないので含まれない
     pub fn get_default(&self, reqs: &Requests) -> Vec<crate::control::Control> {
         let mut v = vec![];
         for r in reqs.requests.iter() {
             if let Some(x) = self.map.get(r.name.as_str()) {
+                if !x.has_default() {
+                    continue;
+                }
                 v.push(Control {
                     id: x.id,
                     value: x.value.clone(),
@@ -219,47 +987,781 @@ impl ControlTable {
     }
 
     /// 設定値に基づいたControlを返す
+    ///
+    /// 整数以外のControlへの割合など、Controlの値に変換できないリクエストは含まれない
     pub fn get_control(&self, reqs: &Requests) -> Vec<crate::control::Control> {
         let mut v = vec![];
         for r in reqs.requests.iter() {
             if let Some(x) = self.map.get(r.name.as_str()) {
-                v.push(Control {
-                    id: x.id,
-                    value: r.value.clone().into(),
-                });
+                if let Ok(value) = x.resolve(&r.value) {
+                    v.push(Control { id: x.id, value });
+                }
             }
         }
         v
     }
-}
 
-/// 設定不可能なリクエストが来た場合のエラー詳細
-#[derive(Debug)]
-pub struct UnsupportedControlDeatil {
-    pub name: String,
-    pub detail: String,
-}
+    /// 1つの設定値をチェックして、設定可能なControlを返す
+    ///
+    /// `check`と同じ規則で型、範囲、stepを確認する。1つのControlを変更するだけなら`Requests`を作る必要はない
+    pub fn build(&self, name: &str, value: Value) -> Result<Control, UnsupportedControlDetail> {
+        let desc = self.map.get(name).ok_or_else(|| self.missing(name))?;
+        if let Some(detail) = desc.check(name, &value) {
+            return Err(detail);
+        }
+        Ok(Control {
+            id: desc.id,
+            value: desc.resolve(&value)?,
+        })
+    }
 
-#[cfg(test)]
-mod tests {
+    /// 設定値に基づいたControlをControlクラスごとにまとめて返す
+    ///
+    /// `Device::set_controls`は1回の`VIDIOC_S_EXT_CTRLS`で設定するので、同じクラスのControlしか渡せない。
+    /// クラスごとに渡せば、それぞれのクラスの中ではすべて設定されるか、何も設定されないかのどちらかになる
+    pub fn get_control_by_class(
+        &self,
+        reqs: &Requests,
+    ) -> BTreeMap<crate::control::Class, Vec<crate::control::Control>> {
+        let mut classes: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for ctrl in self.get_control(reqs) {
+            classes
+                .entry(crate::control::Class::of(ctrl.id))
+                .or_default()
+                .push(ctrl);
+        }
+        classes
+    }
 
-    use super::*;
+    /// 設定値を設定可能な値に補正してControlを返す
+    ///
+    /// 整数の値は`[minimum, maximum]`の範囲に収めて、最も近いstepに丸める。
+    /// スライダーを少し範囲外まで動かした場合などに、エラーにせず設定するために使う。
+    /// 補正した値は2つ目の返り値で報告するので、UIに実際の値を反映できる
+    pub fn get_control_clamped(
+        &self,
+        reqs: &Requests,
+    ) -> (Vec<crate::control::Control>, Vec<ClampedControl>) {
+        use crate::control::Type;
 
-    #[test]
-    fn test_unsupported_control_detail() {
-        let desc = ControlDesc {
-            id: 0,
-            value: CValue::Integer(0),
-            minimum: 0,
-            maximum: 100,
-        };
+        let mut v = vec![];
+        let mut clamped = vec![];
+        for r in reqs.requests.iter() {
+            if let Some(x) = self.map.get(r.name.as_str()) {
+                let value = match (&r.value, x.typ) {
+                    (Value::Integer(i), Type::Integer | Type::Integer64) => {
+                        // 範囲が不正な場合は補正せずに渡し、ドライバの判断に任せる
+                        let applied = x.coerce(*i).unwrap_or(*i);
+                        if applied != *i {
+                            clamped.push(ClampedControl {
+                                name: r.name.clone(),
+                                requested: *i,
+                                applied,
+                            });
+                        }
+                        CValue::Integer(applied)
+                    }
+                    (value, _) => match x.resolve(value) {
+                        Ok(value) => value,
+                        Err(_) => continue,
+                    },
+                };
+                v.push(Control { id: x.id, value });
+            }
+        }
+        (v, clamped)
+    }
 
-        let td = vec![
-            (Value::Integer(-1), false),
-            (Value::Integer(0), true),
-            (Value::Integer(100), true),
+    /// テーブルのControlの現在の値を、再設定可能なリクエストとして取得する
+    ///
+    /// 後で同じ状態に戻すために使う。無効 (INACTIVE) なControlは設定しても反映されないので、
+    /// リクエストには含めずに2つ目の返り値で名前を返す。ボタンと値を読み取れないControlは含まれない
+    pub fn snapshot(&self, dev: &impl ControlDevice) -> io::Result<(Requests, Vec<String>)> {
+        use crate::control::{Flags, Type};
+
+        let mut requests = vec![];
+        let mut inactive = vec![];
+        for (name, desc) in self.map.iter() {
+            if desc.typ == Type::Button || desc.flags.contains(Flags::WRITE_ONLY) {
+                continue;
+            }
+            // INACTIVEは他のControlの値によって変わるので、現在のフラグを問い合わせる
+            let current = dev.query_control(desc.id)?;
+            if current.flags.contains(Flags::INACTIVE) {
+                inactive.push(name.clone());
+                continue;
+            }
+            let value = match dev.control(&current)?.value {
+                CValue::Integer(i) => Value::Integer(i),
+                CValue::Boolean(b) => Value::Boolean(b),
+                CValue::String(s) => Value::String(s),
+                CValue::Raw(r) => Value::Raw(r),
+                CValue::CompoundU8(b) => Value::Bytes(b),
+                CValue::CompoundU16(a) => {
+                    Value::Bytes(a.iter().flat_map(|e| e.to_ne_bytes()).collect())
+                }
+                CValue::CompoundU32(a) => {
+                    Value::Bytes(a.iter().flat_map(|e| e.to_ne_bytes()).collect())
+                }
+                _ => continue,
+            };
+            requests.push(Request::new(name, value));
+        }
+        Ok((Requests::new(requests), inactive))
+    }
+
+    /// リクエストをデバイスに設定し、失敗した場合は設定前の値に戻す
+    ///
+    /// 対象のControlの現在の値を保存してから1つずつ設定する。
+    /// 途中で失敗すると、それまでに設定したControlを保存した値に戻して、失敗したControlを返す。
+    /// 書き込み専用のControlとボタンは値を読み取れないので戻せない
+    pub fn apply_atomic(
+        &self,
+        dev: &impl ControlDevice,
+        reqs: &Requests,
+    ) -> Result<(), ApplyError> {
+        use crate::control::{Flags, Type};
+
+        let ctrls = self.resolve_all(reqs)?;
+
+        let mut snapshot: Vec<(&str, Control)> = vec![];
+        for (name, ctrl) in ctrls.iter() {
+            let desc = &self.map[*name];
+            if desc.typ == Type::Button
+                || desc.flags.contains(Flags::WRITE_ONLY)
+                || snapshot.iter().any(|(_, c)| c.id == ctrl.id)
+            {
+                continue;
+            }
+            let current = dev
+                .query_control(ctrl.id)
+                .and_then(|desc| dev.control(&desc))
+                .map_err(|error| ApplyError {
+                    name: name.to_string(),
+                    error,
+                    rollback_failed: vec![],
+                })?;
+            snapshot.push((name, current));
+        }
+
+        let mut applied = vec![];
+        for (name, ctrl) in ctrls {
+            let id = ctrl.id;
+            if let Err(error) = dev.set_control(ctrl) {
+                // 後に設定したものから戻す
+                let rollback_failed = snapshot
+                    .into_iter()
+                    .rev()
+                    .filter(|(_, c)| applied.contains(&c.id))
+                    .filter_map(|(name, c)| dev.set_control(c).err().map(|_| name.to_string()))
+                    .collect();
+                return Err(ApplyError {
+                    name: name.to_string(),
+                    error,
+                    rollback_failed,
+                });
+            }
+            applied.push(id);
+        }
+        Ok(())
+    }
+
+    /// リクエストを、無効 (INACTIVE) なControlを後回しにしてデバイスに設定する
+    ///
+    /// オートモードのときの手動露出のように、他のControlの値によって無効になるControlがある。
+    /// 設定する直前にフラグを問い合わせ、無効なControlは残りを設定した後に再び試す。
+    /// 設定が進まなくなった場合は無効なまま設定し、その名前を返す。
+    /// 設定する前に`check`でリクエストを確認し、存在しないControlや範囲外の値があれば何も設定せずにエラーを返す。
+    /// 設定中に失敗した場合は、それまでに設定したControlを戻さない
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::convert::TryFrom;
+    /// use v4l::device::Device;
+    /// use v4l::util::control::{ControlTable, Requests};
+    ///
+    /// let dev = Device::new(0).unwrap();
+    /// let ctrlmap = ControlTable::from(dev.query_controls().unwrap().as_slice());
+    ///
+    /// // 手動露出はオートモードを解除した後に設定される
+    /// let reqs = Requests::try_from("exposure_time_absolute=100,auto_exposure=manual_mode").unwrap();
+    /// let inactive = ctrlmap.apply_ordered(&dev, &reqs).unwrap();
+    /// assert!(inactive.is_empty());
+    /// ```
+    pub fn apply_ordered(
+        &self,
+        dev: &impl ControlDevice,
+        reqs: &Requests,
+    ) -> Result<Vec<String>, ApplyError> {
+        use crate::control::Flags;
+
+        if let Some(detail) = self.check(reqs).into_iter().next() {
+            return Err(ApplyError {
+                name: detail.name.clone(),
+                error: io::Error::new(io::ErrorKind::InvalidInput, detail),
+                rollback_failed: vec![],
+            });
+        }
+
+        apply_in_order(
+            self.resolve_all(reqs)?,
+            |id| Ok(dev.query_control(id)?.flags.contains(Flags::INACTIVE)),
+            |ctrl| dev.set_control(ctrl),
+        )
+    }
+
+    /// テーブルに存在するControlへのリクエストを、名前とControlの組に変換する
+    ///
+    /// Controlの値に変換できないリクエストがあればエラー
+    fn resolve_all<'r>(&self, reqs: &'r Requests) -> Result<Vec<(&'r str, Control)>, ApplyError> {
+        let mut v = vec![];
+        for r in reqs.requests.iter() {
+            if let Some(x) = self.map.get(r.name.as_str()) {
+                let value = x.resolve(&r.value).map_err(|detail| ApplyError {
+                    name: r.name.clone(),
+                    error: io::Error::new(io::ErrorKind::InvalidInput, detail),
+                    rollback_failed: vec![],
+                })?;
+                v.push((r.name.as_str(), Control { id: x.id, value }));
+            }
+        }
+        Ok(v)
+    }
+}
+
+/// 無効なControlを後回しにしながら、設定が進まなくなるまで繰り返し設定する
+///
+/// 設定しきれなかった無効なControlは最後にそのまま設定し、その名前を返す
+fn apply_in_order(
+    mut pending: Vec<(&str, Control)>,
+    mut inactive: impl FnMut(u32) -> io::Result<bool>,
+    mut set: impl FnMut(Control) -> io::Result<()>,
+) -> Result<Vec<String>, ApplyError> {
+    let error = |name: &str, error| ApplyError {
+        name: name.to_string(),
+        error,
+        rollback_failed: vec![],
+    };
+
+    loop {
+        let count = pending.len();
+        let mut deferred = vec![];
+        for (name, ctrl) in pending {
+            if inactive(ctrl.id).map_err(|e| error(name, e))? {
+                deferred.push((name, ctrl));
+                continue;
+            }
+            set(ctrl).map_err(|e| error(name, e))?;
+        }
+
+        if deferred.is_empty() {
+            return Ok(vec![]);
+        }
+        if deferred.len() == count {
+            // 有効にするControlが残っていない
+            let names = deferred.iter().map(|(name, _)| name.to_string()).collect();
+            for (name, ctrl) in deferred {
+                set(ctrl).map_err(|e| error(name, e))?;
+            }
+            return Ok(names);
+        }
+        pending = deferred;
+    }
+}
+
+impl ControlTable {
+    /// 名前に対応するControlの情報を返す
+    pub fn get(&self, name: &str) -> Option<&ControlDesc> {
+        self.map.get(name)
+    }
+
+    /// 小数で指定された値に掛ける倍率を設定する
+    ///
+    /// V4L2のControlは整数なので、小数を扱うドライバは固定小数点で表現している。
+    /// 例えばズームが100倍の固定小数点なら、倍率を100にすると`zoom=1.5`が150として設定される。
+    /// 倍率はドライバから取得できないので、デフォルトは1。Controlが存在しない場合は`false`を返す
+    pub fn set_scale(&mut self, name: &str, scale: f64) -> bool {
+        match self.map.get_mut(name) {
+            Some(desc) => {
+                desc.scale = scale;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 設定可能なControlの名前を名前順に返す
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.map.keys().map(|name| name.as_str())
+    }
+
+    /// 名前に対応するControlが設定可能か
+    pub fn contains(&self, name: &str) -> bool {
+        self.map.contains_key(name)
+    }
+
+    /// 設定可能なControlの数を返す
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// 設定可能なControlがないか
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// 名前に対応するControlの設定可能な範囲などを返す
+    ///
+    /// UIでスライダー、チェックボックス、ドロップダウンのどれを表示するか決めるために使う
+    pub fn describe(&self, name: &str) -> Option<ControlInfo> {
+        self.map.get(name).map(|desc| ControlInfo {
+            id: desc.id,
+            value_type: desc.typ,
+            minimum: desc.minimum,
+            maximum: desc.maximum,
+            step: desc.step,
+            default: desc.value.clone(),
+            menu: desc
+                .items
+                .iter()
+                .map(|(index, label)| MenuEntry {
+                    index: *index,
+                    label: label.clone(),
+                })
+                .collect(),
+        })
+    }
+
+    /// リクエストで使う名前に対応する、ドライバが返した表示用の名前を返す
+    ///
+    /// CLIでは`exposure_time_absolute`のように受け付けて、メッセージには`Exposure Time, Absolute`と表示するために使う
+    pub fn display_name(&self, ctrl_name: &str) -> Option<&str> {
+        self.map.get(ctrl_name).map(|desc| desc.name.as_str())
+    }
+
+    /// Controlの一覧をスキーマとして出力する
+    ///
+    /// Web UIなどでウィジェットを生成するための一方向の出力で、`serde` featureを有効にするとシリアライズできる
+    pub fn to_schema(&self) -> ControlSchema {
+        let controls = self
+            .map
+            .iter()
+            .map(|(name, desc)| {
+                let (typ, default) = match desc.value {
+                    CValue::Integer(i) => (desc.typ.to_string().to_lowercase(), i),
+                    CValue::Boolean(b) => (desc.typ.to_string().to_lowercase(), b as i64),
+                    CValue::Raw(_) => ("raw".to_string(), 0),
+                    _ => (desc.typ.to_string().to_lowercase(), 0),
+                };
+                ControlSchemaEntry {
+                    name: name.clone(),
+                    id: desc.id,
+                    typ,
+                    minimum: desc.minimum,
+                    maximum: desc.maximum,
+                    step: desc.step,
+                    default,
+                    menu: desc
+                        .items
+                        .iter()
+                        .map(|(index, label)| MenuEntry {
+                            index: *index,
+                            label: label.clone(),
+                        })
+                        .collect(),
+                    flags: desc
+                        .flags
+                        .iter_names()
+                        .map(|(flag, _)| flag.to_lowercase())
+                        .collect(),
+                }
+            })
+            .collect();
+
+        ControlSchema { controls }
+    }
+
+    /// ボタンのControlを動作させるためのControlを返す
+    ///
+    /// 返り値を`Device::set_control`に渡すと動作する。ボタン以外のControlの場合は`None`
+    pub fn trigger(&self, name: &str) -> Option<Control> {
+        self.map
+            .get(name)
+            .filter(|desc| desc.typ == crate::control::Type::Button)
+            .map(|desc| Control {
+                id: desc.id,
+                value: CValue::None,
+            })
+    }
+
+    /// idに対応するControlの名前を返す
+    pub fn name_of(&self, id: u32) -> Option<&str> {
+        self.map
+            .iter()
+            .find(|(_, desc)| desc.id == id)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// 2つの時点で取得したControlの値を比較し、変化したControlを返す
+    ///
+    /// `before`の順序で返し、片方にしか存在しないControlは含まれない。
+    /// プロファイルの適用前後で比較して、どのControlが変更されたかを確認するために使う
+    pub fn diff(&self, before: &[Control], after: &[Control]) -> Vec<ControlChange> {
+        changes(before.iter().map(|b| (b.id, &b.value)), |id| {
+            after.iter().find(|a| a.id == *id).map(|a| &a.value)
+        })
+        .into_iter()
+        .map(|(id, before, after)| ControlChange {
+            id,
+            name: self.name_of(id).map(str::to_string),
+            before,
+            after,
+        })
+        .collect()
+    }
+
+    /// テーブルの作成時にドライバの情報を補正したControlの一覧を返す
+    pub fn notes(&self) -> &[ControlNote] {
+        &self.notes
+    }
+
+    /// Controlの名前をControlクラスごとにまとめて返す
+    ///
+    /// 設定画面で`v4l2-ctl --list-ctrls-menus`のようにクラス単位で表示するために使う
+    pub fn by_class(&self) -> BTreeMap<crate::control::Class, Vec<&str>> {
+        let mut classes: BTreeMap<_, Vec<&str>> = BTreeMap::new();
+        for (name, desc) in self.map.iter() {
+            classes
+                .entry(crate::control::Class::of(desc.id))
+                .or_default()
+                .push(name.as_str());
+        }
+        classes
+    }
+
+    /// ベンダー固有のrawなControlの名前を返す
+    ///
+    /// これらのControlには`Value::Raw`でバイト列をそのまま渡す
+    pub fn raw_controls(&self) -> Vec<&str> {
+        self.map
+            .iter()
+            .filter(|(_, desc)| matches!(desc.value, CValue::Raw(_)))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// 2つのControlTableで設定可能なControlを比較する
+    ///
+    /// カメラの機種を変更する前に、プリセットで使うControlが新しい機種でも使えるか確認するために使う
+    pub fn capability_diff(&self, other: &ControlTable) -> CapabilityDiff {
+        let mut diff = CapabilityDiff::default();
+        for (name, desc) in self.map.iter() {
+            match other.map.get(name) {
+                None => diff.only_in_self.push(name.clone()),
+                Some(x) => {
+                    if mem::discriminant(&desc.value) != mem::discriminant(&x.value)
+                        || desc.minimum != x.minimum
+                        || desc.maximum != x.maximum
+                    {
+                        diff.differs.push(name.clone());
+                    }
+                }
+            }
+        }
+        for name in other.map.keys() {
+            if !self.map.contains_key(name) {
+                diff.only_in_other.push(name.clone());
+            }
+        }
+        diff
+    }
+}
+
+/// `get_control_clamped`で補正された値
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClampedControl {
+    /// リクエストで使った名前
+    pub name: String,
+    /// リクエストの値
+    pub requested: i64,
+    /// 実際に設定する値
+    pub applied: i64,
+}
+
+/// 2つのControlTableの差分
+#[derive(Debug, Default, PartialEq)]
+pub struct CapabilityDiff {
+    /// selfにのみ存在するControl
+    pub only_in_self: Vec<String>,
+    /// otherにのみ存在するControl
+    pub only_in_other: Vec<String>,
+    /// 両方に存在するが、型または設定可能な範囲が異なるControl
+    pub differs: Vec<String>,
+}
+
+impl CapabilityDiff {
+    /// 差分がないかどうか
+    pub fn is_empty(&self) -> bool {
+        self.only_in_self.is_empty() && self.only_in_other.is_empty() && self.differs.is_empty()
+    }
+}
+
+/// ControlTableのスキーマ
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ControlSchema {
+    pub controls: Vec<ControlSchemaEntry>,
+}
+
+/// スキーマに含まれる1つのControlの情報
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ControlSchemaEntry {
+    /// リクエストで使う名前
+    pub name: String,
+    pub id: u32,
+    /// 値の型 (integer, boolean, menu, raw など)
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub typ: String,
+    pub minimum: i64,
+    pub maximum: i64,
+    pub step: i64,
+    /// デフォルト値 (booleanは0か1)
+    pub default: i64,
+    /// メニューの項目 (メニュー以外は空)
+    pub menu: Vec<MenuEntry>,
+    /// Controlのフラグ (slider, volatile など)
+    pub flags: Vec<String>,
+}
+
+/// `describe`で返すControlの情報
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlInfo {
+    pub id: u32,
+    /// 値の型
+    pub value_type: crate::control::Type,
+    pub minimum: i64,
+    pub maximum: i64,
+    pub step: i64,
+    /// デフォルト値 (ボタンは`Value::None`)
+    pub default: CValue,
+    /// メニューの項目 (メニュー以外は空)
+    pub menu: Vec<MenuEntry>,
+}
+
+/// メニューの項目
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MenuEntry {
+    pub index: u32,
+    pub label: String,
+}
+
+/// `ControlTable::diff`と`ControlSnapshot::diff`で返す値が変化したControl
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlChange {
+    pub id: u32,
+    /// テーブルでの名前 (テーブルにないControlは`None`)
+    pub name: Option<String>,
+    pub before: CValue,
+    pub after: CValue,
+}
+
+impl fmt::Display for ControlChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{}", name)?,
+            None => write!(f, "{:#x}", self.id)?,
+        }
+        write!(f, ": {:?} -> {:?}", self.before, self.after)
+    }
+}
+
+/// テーブルの作成時に補正したControlの詳細
+#[derive(Debug)]
+pub struct ControlNote {
+    pub name: String,
+    pub detail: String,
+}
+
+/// ある時点での対象デバイスのControlの値
+///
+/// ドライバが自動で変更したControl(オートモードのゲインなど)を調べるために、定期的に取得して比較する
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControlSnapshot {
+    pub values: BTreeMap<String, CValue>,
+    /// 名前に対応するControlのid
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ids: BTreeMap<String, u32>,
+}
+
+impl ControlSnapshot {
+    /// デバイスの読み取り可能なControlの値を取得する
+    ///
+    /// 値を読み取れないControlは含まれない
+    pub fn capture(dev: &impl ControlDevice) -> io::Result<Self> {
+        use crate::control::{Flags, Type};
+
+        let mut values = BTreeMap::new();
+        let mut ids = BTreeMap::new();
+        for desc in dev.query_controls()? {
+            if desc.flags.contains(Flags::WRITE_ONLY)
+                || desc.typ == Type::CtrlClass
+                || desc.typ == Type::Button
+            {
+                continue;
+            }
+            if let Ok(ctrl) = dev.control(&desc) {
+                let name = desc.name.to_ctrl_name();
+                ids.insert(name.clone(), desc.id);
+                values.insert(name, ctrl.value);
+            }
+        }
+        Ok(ControlSnapshot { values, ids })
+    }
+
+    /// 名前に対応するControlの値を返す
+    pub fn get(&self, name: &str) -> Option<&CValue> {
+        self.values.get(name)
+    }
+
+    /// 2つのスナップショットで値が異なるControlを返す
+    ///
+    /// `before`を`self`、`after`を`other`の値とし、片方にしか存在しないControlは含まれない。
+    /// idを記録していないスナップショット(手動で作成したものなど)では`id`は0になる
+    pub fn diff(&self, other: &ControlSnapshot) -> Vec<ControlChange> {
+        changes(self.values.iter(), |name| other.values.get(*name))
+            .into_iter()
+            .map(|(name, before, after)| ControlChange {
+                id: self
+                    .ids
+                    .get(name)
+                    .or_else(|| other.ids.get(name))
+                    .copied()
+                    .unwrap_or_default(),
+                name: Some(name.clone()),
+                before,
+                after,
+            })
+            .collect()
+    }
+}
+
+/// `before`の順序で値が変化した`(キー, 変化前, 変化後)`を返す
+///
+/// `after`で値が見つからないキーは含まれない
+fn changes<'a, K>(
+    before: impl IntoIterator<Item = (K, &'a CValue)>,
+    after: impl Fn(&K) -> Option<&'a CValue>,
+) -> Vec<(K, CValue, CValue)> {
+    before
+        .into_iter()
+        .filter_map(|(key, b)| match after(&key) {
+            Some(a) if a != b => Some((key, b.clone(), a.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `apply_atomic`で設定に失敗した場合のエラー
+#[derive(Debug)]
+pub struct ApplyError {
+    /// 失敗したControlのリクエストで使った名前
+    pub name: String,
+    /// 失敗した原因
+    pub error: io::Error,
+    /// 設定前の値に戻せなかったControlの名前
+    pub rollback_failed: Vec<String>,
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to set {}: {}", self.name, self.error)?;
+        if !self.rollback_failed.is_empty() {
+            write!(f, ", rollback failed [{}]", self.rollback_failed.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ApplyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// 設定不可能なリクエストが来た場合のエラー詳細
+#[derive(Debug)]
+pub struct UnsupportedControlDetail {
+    pub name: String,
+    pub detail: String,
+}
+
+/// 旧名 (綴りの誤り)
+#[deprecated(note = "renamed to UnsupportedControlDetail")]
+pub type UnsupportedControlDeatil = UnsupportedControlDetail;
+
+impl fmt::Display for UnsupportedControlDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.detail)
+    }
+}
+
+impl std::error::Error for UnsupportedControlDetail {}
+
+/// `validate`で見つかった設定不可能なリクエストの一覧
+///
+/// `?`で呼び出し元に返せるように、まとめて1つのエラーとして扱う
+#[derive(Debug)]
+pub struct ControlCheckErrors(pub Vec<UnsupportedControlDetail>);
+
+impl fmt::Display for ControlCheckErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, detail) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", detail)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ControlCheckErrors {}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_unsupported_control_detail() {
+        let desc = ControlDesc {
+            id: 0,
+            name: String::new(),
+            value: CValue::Integer(0),
+            minimum: 0,
+            maximum: 100,
+            step: 1,
+            typ: crate::control::Type::Integer,
+            flags: crate::control::Flags::empty(),
+            items: vec![],
+            elems: 1,
+            elem_size: 8,
+            scale: 1.0,
+        };
+
+        let td = vec![
+            (Value::Integer(-1), false),
+            (Value::Integer(0), true),
+            (Value::Integer(100), true),
             (Value::Integer(101), false),
             (Value::Boolean(true), false),
+            (Value::Percent(0.0), true),
+            (Value::Percent(100.0), true),
+            (Value::Percent(100.5), false),
         ];
         for (value, expected) in td {
             let detail = desc.check("test", &value);
@@ -267,6 +1769,829 @@ mod tests {
         }
     }
 
+    fn desc(
+        id: u32,
+        typ: crate::control::Type,
+        minimum: i64,
+        maximum: i64,
+    ) -> crate::control::Description {
+        crate::control::Description {
+            id,
+            typ,
+            name: String::new(),
+            minimum,
+            maximum,
+            step: 1,
+            default: 0,
+            flags: crate::control::Flags::empty(),
+            elem_size: 0,
+            elems: 0,
+            items: None,
+        }
+    }
+
+    fn table(controls: Vec<(&str, crate::control::Description)>) -> ControlTable {
+        let controls = controls
+            .into_iter()
+            .map(|(name, mut desc)| {
+                desc.name = name.to_string();
+                desc
+            })
+            .collect::<Vec<_>>();
+        ControlTable::from(controls.as_slice())
+    }
+
+    #[test]
+    fn test_capability_diff() {
+        use crate::control::Type;
+
+        let a = table(vec![
+            ("Gain", desc(1, Type::Integer, 0, 100)),
+            ("Exposure", desc(2, Type::Integer, 1, 5000)),
+            ("Sharpness", desc(3, Type::Integer, 0, 7)),
+        ]);
+        let b = table(vec![
+            ("Gain", desc(1, Type::Integer, 0, 100)),
+            ("Exposure", desc(2, Type::Integer, 1, 10000)),
+            ("Frame Rate", desc(4, Type::Integer, 1, 60)),
+        ]);
+
+        let diff = a.capability_diff(&b);
+        assert_eq!(vec!["sharpness".to_string()], diff.only_in_self);
+        assert_eq!(vec!["frame_rate".to_string()], diff.only_in_other);
+        assert_eq!(vec!["exposure".to_string()], diff.differs);
+        assert!(a.capability_diff(&a).is_empty());
+    }
+
+    #[test]
+    fn test_raw_controls() {
+        use crate::control::Type;
+        use crate::v4l_sys::{V4L2_CID_BRIGHTNESS, V4L2_CID_USER_BASE, V4L2_CTRL_CLASS_CAMERA};
+
+        let t = table(vec![
+            (
+                "Brightness",
+                desc(V4L2_CID_BRIGHTNESS, Type::Integer, 0, 255),
+            ),
+            (
+                "Vendor Blob",
+                desc(V4L2_CID_USER_BASE | 0x1000, Type::Unknown(0x0200), 0, 0),
+            ),
+            (
+                "Camera Blob",
+                desc(V4L2_CTRL_CLASS_CAMERA | 0x1000, Type::Unknown(0x0200), 0, 0),
+            ),
+        ]);
+        assert_eq!(vec!["vendor_blob"], t.raw_controls());
+
+        let reqs = Requests::new(vec![Request::new("vendor_blob", Value::Raw(vec![1, 2]))]);
+        assert!(t.check(&reqs).is_empty());
+        let ctrls = t.get_control(&reqs);
+        assert_eq!(1, ctrls.len());
+        assert!(matches!(ctrls[0].value, CValue::Raw(ref r) if r == &[1, 2]));
+
+        // デフォルト値が分からないので、空のバイト列を設定しない
+        assert!(t.get_default(&reqs).is_empty());
+    }
+
+    #[test]
+    fn test_inactive() {
+        use crate::control::{Flags, Type};
+        use crate::v4l_sys::{V4L2_CID_EXPOSURE_ABSOLUTE, V4L2_CID_EXPOSURE_AUTO};
+
+        let mut exposure = desc(V4L2_CID_EXPOSURE_ABSOLUTE, Type::Integer, 1, 5000);
+        exposure.flags = Flags::INACTIVE | Flags::VOLATILE;
+        let t = table(vec![
+            (
+                "Auto Exposure",
+                desc(V4L2_CID_EXPOSURE_AUTO, Type::Integer, 0, 3),
+            ),
+            ("Exposure Time, Absolute", exposure),
+        ]);
+
+        let desc = t.get("exposure_time_absolute").unwrap();
+        assert!(desc.is_inactive());
+        assert!(desc.is_volatile());
+        assert!(!desc.is_write_only());
+        assert!(!t.get("auto_exposure").unwrap().is_inactive());
+
+        // オートモードを解除すれば設定できるのでエラーにはしない
+        let reqs = Requests::try_from("auto_exposure=1,exposure_time_absolute=100").unwrap();
+        assert!(t.validate(&reqs).is_ok());
+        let warnings = t.warnings(&reqs);
+        assert_eq!(1, warnings.len());
+        assert_eq!(
+            "exposure_time_absolute: Control is inactive",
+            warnings[0].to_string()
+        );
+
+        // 値の誤りはエラーとしてのみ報告する
+        let reqs = Requests::try_from("exposure_time_absolute=0").unwrap();
+        assert_eq!("Out of range", t.check(&reqs)[0].detail);
+        assert!(t.warnings(&reqs).is_empty());
+    }
+
+    #[test]
+    fn test_apply_in_order() {
+        use std::cell::RefCell;
+
+        // 1: オートモード (0で手動), 2: オートモードのときは無効な手動露出, 3: 常に無効
+        let auto = RefCell::new(true);
+        let applied = RefCell::new(vec![]);
+        let inactive = |id| Ok((id == 2 && *auto.borrow()) || id == 3);
+        let set = |ctrl: Control| {
+            if let (1, CValue::Integer(i)) = (ctrl.id, &ctrl.value) {
+                *auto.borrow_mut() = *i != 0;
+            }
+            applied.borrow_mut().push(ctrl.id);
+            Ok(())
+        };
+        let ctrl = |id| Control {
+            id,
+            value: CValue::Integer(0),
+        };
+
+        let reqs = vec![("exposure", ctrl(2)), ("auto", ctrl(1))];
+        assert!(apply_in_order(reqs, inactive, set).unwrap().is_empty());
+        assert_eq!(vec![1, 2], *applied.borrow());
+
+        applied.borrow_mut().clear();
+        *auto.borrow_mut() = true;
+        let reqs = vec![
+            ("locked", ctrl(3)),
+            ("exposure", ctrl(2)),
+            ("auto", ctrl(1)),
+        ];
+        assert_eq!(
+            vec!["locked".to_string()],
+            apply_in_order(reqs, inactive, set).unwrap()
+        );
+        assert_eq!(vec![1, 2, 3], *applied.borrow());
+
+        let reqs = vec![("auto", ctrl(1))];
+        let failed = apply_in_order(reqs, inactive, |_| {
+            Err(io::Error::new(io::ErrorKind::InvalidInput, "EINVAL"))
+        });
+        assert_eq!("auto", failed.unwrap_err().name);
+    }
+
+    #[test]
+    fn test_diff() {
+        use crate::control::Type;
+        use crate::v4l_sys::{V4L2_CID_BRIGHTNESS, V4L2_CID_CONTRAST, V4L2_CID_USER_BASE};
+
+        let t = table(vec![
+            (
+                "Brightness",
+                desc(V4L2_CID_BRIGHTNESS, Type::Integer, 0, 255),
+            ),
+            ("Contrast", desc(V4L2_CID_CONTRAST, Type::Integer, 0, 255)),
+        ]);
+        let unknown = V4L2_CID_USER_BASE | 0x1000;
+        let ctrl = |id, value| Control {
+            id,
+            value: CValue::Integer(value),
+        };
+
+        let before = vec![
+            ctrl(V4L2_CID_BRIGHTNESS, 10),
+            ctrl(V4L2_CID_CONTRAST, 20),
+            ctrl(unknown, 1),
+        ];
+        let after = vec![
+            ctrl(unknown, 2),
+            ctrl(V4L2_CID_CONTRAST, 20),
+            ctrl(V4L2_CID_BRIGHTNESS, 15),
+        ];
+
+        let changes = t.diff(&before, &after);
+        assert_eq!(
+            vec![
+                ControlChange {
+                    id: V4L2_CID_BRIGHTNESS,
+                    name: Some("brightness".to_string()),
+                    before: CValue::Integer(10),
+                    after: CValue::Integer(15),
+                },
+                ControlChange {
+                    id: unknown,
+                    name: None,
+                    before: CValue::Integer(1),
+                    after: CValue::Integer(2),
+                },
+            ],
+            changes
+        );
+        assert!(t.diff(&before, &before).is_empty());
+        assert!(t.diff(&before, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_by_class() {
+        use crate::control::{Class, Type};
+        use crate::v4l_sys::{V4L2_CID_BRIGHTNESS, V4L2_CID_CONTRAST, V4L2_CID_EXPOSURE_ABSOLUTE};
+
+        let t = table(vec![
+            (
+                "Brightness",
+                desc(V4L2_CID_BRIGHTNESS, Type::Integer, 0, 255),
+            ),
+            ("Contrast", desc(V4L2_CID_CONTRAST, Type::Integer, 0, 255)),
+            (
+                "Exposure Time, Absolute",
+                desc(V4L2_CID_EXPOSURE_ABSOLUTE, Type::Integer, 1, 5000),
+            ),
+        ]);
+
+        let classes = t.by_class();
+        assert_eq!(2, classes.len());
+        assert_eq!(vec!["brightness", "contrast"], classes[&Class::User]);
+        assert_eq!(vec!["exposure_time_absolute"], classes[&Class::Camera]);
+        assert_eq!(
+            vec![Class::User, Class::Camera],
+            classes.keys().copied().collect::<Vec<_>>()
+        );
+
+        let reqs = Requests::new(vec![
+            Request::new("exposure_time_absolute", Value::Integer(100)),
+            Request::new("brightness", Value::Integer(128)),
+            Request::new("contrast", Value::Integer(64)),
+        ]);
+        let batches = t.get_control_by_class(&reqs);
+        assert_eq!(2, batches.len());
+        assert_eq!(
+            vec![V4L2_CID_BRIGHTNESS, V4L2_CID_CONTRAST],
+            batches[&Class::User]
+                .iter()
+                .map(|ctrl| ctrl.id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(1, batches[&Class::Camera].len());
+    }
+
+    #[test]
+    fn test_menu_default() {
+        use crate::control::{MenuItem, Type};
+
+        let mut valid = desc(1, Type::Menu, 0, 3);
+        valid.default = 1;
+        valid.items = Some(vec![(1, MenuItem::Name("a".to_string()))]);
+        let mut invalid = desc(2, Type::Menu, 0, 3);
+        invalid.items = Some(vec![
+            (2, MenuItem::Name("b".to_string())),
+            (3, MenuItem::Name("c".to_string())),
+        ]);
+
+        let t = table(vec![("Valid", valid), ("Invalid", invalid)]);
+        let reqs = Requests::new(vec![
+            Request::new("valid", Value::Integer(0)),
+            Request::new("invalid", Value::Integer(0)),
+        ]);
+        let defaults = t.get_default(&reqs);
+        assert!(matches!(defaults[0].value, CValue::Integer(1)));
+        assert!(matches!(defaults[1].value, CValue::Integer(2)));
+        assert_eq!(1, t.notes().len());
+        assert_eq!("invalid", t.notes()[0].name);
+    }
+
+    #[test]
+    fn test_menu_label() {
+        use crate::control::{MenuItem, Type};
+
+        let mut mode = desc(1, Type::Menu, 0, 3);
+        mode.items = Some(vec![
+            (1, MenuItem::Name("Manual Mode".to_string())),
+            (3, MenuItem::Name("Aperture Priority Mode".to_string())),
+        ]);
+        let t = table(vec![("Auto Exposure", mode)]);
+
+        let reqs = Requests::try_from("auto_exposure=aperture_priority_mode").unwrap();
+        assert!(t.check(&reqs).is_empty());
+        assert!(matches!(t.get_control(&reqs)[0].value, CValue::Integer(3)));
+
+        let reqs = Requests::new(vec![Request::new(
+            "auto_exposure",
+            Value::String("Manual Mode".to_string()),
+        )]);
+        assert!(matches!(t.get_control(&reqs)[0].value, CValue::Integer(1)));
+
+        let reqs = Requests::try_from("auto_exposure=shutter_priority_mode").unwrap();
+        let check = t.check(&reqs);
+        assert_eq!(1, check.len());
+        assert_eq!("No menu item 'shutter_priority_mode'", check[0].detail);
+    }
+
+    #[test]
+    fn test_value_from_str() {
+        let td = vec![
+            ("10", Value::Integer(10)),
+            ("-0x10", Value::Integer(-16)),
+            ("0b101", Value::Integer(5)),
+            ("true", Value::Boolean(true)),
+            ("1.5", Value::Float(1.5)),
+            ("-.25", Value::Float(-0.25)),
+            ("50%", Value::Percent(50.0)),
+            ("inf", Value::String("inf".to_string())),
+            ("auto", Value::String("auto".to_string())),
+        ];
+
+        for (input, expected) in td {
+            assert_eq!(expected, Value::from(input));
+        }
+    }
+
+    #[test]
+    fn test_float() {
+        use crate::control::Type;
+
+        let mut t = table(vec![
+            ("Zoom, Absolute", desc(1, Type::Integer, 100, 500)),
+            ("Mirror", desc(2, Type::Boolean, 0, 1)),
+        ]);
+        assert!(t.set_scale("zoom_absolute", 100.0));
+        assert!(!t.set_scale("pan_absolute", 100.0));
+
+        let ctrl = t.build("zoom_absolute", Value::from("1.5")).unwrap();
+        assert_eq!(CValue::Integer(150), ctrl.value);
+        let err = t.build("zoom_absolute", Value::Float(0.5)).unwrap_err();
+        assert_eq!("Out of range", err.detail);
+        assert!(t.build("mirror", Value::Float(1.0)).is_err());
+    }
+
+    #[test]
+    fn test_bitmask() {
+        use crate::control::Type;
+
+        assert_eq!(Value::from("0x30"), Value::Integer(0x30));
+        assert_eq!(Value::from("0XfF"), Value::Integer(0xff));
+
+        let mut flags = desc(1, Type::Bitmask, 0, 0x31);
+        flags.default = 0x01;
+        let t = table(vec![("Some Flags", flags)]);
+
+        let reqs = Requests::try_from("some_flags=0x30").unwrap();
+        assert!(t.check(&reqs).is_empty());
+        assert!(matches!(
+            t.get_control(&reqs)[0].value,
+            CValue::Integer(0x30)
+        ));
+        assert!(matches!(
+            t.get_default(&reqs)[0].value,
+            CValue::Integer(0x01)
+        ));
+
+        for req in ["some_flags=0x02", "some_flags=-1", "some_flags=50%"] {
+            let reqs = Requests::try_from(req).unwrap();
+            assert_eq!(1, t.check(&reqs).len(), "{}", req);
+        }
+    }
+
+    #[test]
+    fn test_button() {
+        use crate::control::Type;
+
+        let t = table(vec![
+            ("Trigger Reset", desc(1, Type::Button, 0, 0)),
+            ("Gain", desc(2, Type::Integer, 0, 100)),
+        ]);
+
+        let ctrl = t.trigger("trigger_reset").unwrap();
+        assert_eq!(1, ctrl.id);
+        assert!(matches!(ctrl.value, CValue::None));
+        assert!(t.trigger("gain").is_none());
+        assert!(t.trigger("unknown").is_none());
+
+        for req in ["trigger_reset=1", "trigger_reset=true", "trigger_reset=now"] {
+            let reqs = Requests::try_from(req).unwrap();
+            assert!(t.check(&reqs).is_empty(), "{}", req);
+            assert!(matches!(t.get_control(&reqs)[0].value, CValue::None));
+            assert!(t.get_default(&reqs).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_to_schema() {
+        use crate::control::{Flags, MenuItem, Type};
+
+        let mut gain = desc(1, Type::Integer, 0, 100);
+        gain.default = 10;
+        gain.flags = Flags::SLIDER;
+        let mut mode = desc(2, Type::Menu, 0, 1);
+        mode.items = Some(vec![
+            (0, MenuItem::Name("Manual".to_string())),
+            (1, MenuItem::Name("Auto".to_string())),
+        ]);
+
+        let schema = table(vec![("Gain", gain), ("Mode", mode)]).to_schema();
+        assert_eq!(2, schema.controls.len());
+
+        let gain = &schema.controls[0];
+        assert_eq!("gain", gain.name);
+        assert_eq!("integer", gain.typ);
+        assert_eq!(10, gain.default);
+        assert_eq!(vec!["slider".to_string()], gain.flags);
+        assert!(gain.menu.is_empty());
+
+        let mode = &schema.controls[1];
+        assert_eq!("menu", mode.typ);
+        assert_eq!(
+            vec![
+                MenuEntry {
+                    index: 0,
+                    label: "Manual".to_string()
+                },
+                MenuEntry {
+                    index: 1,
+                    label: "Auto".to_string()
+                },
+            ],
+            mode.menu
+        );
+    }
+
+    #[test]
+    fn test_coerce() {
+        let desc = ControlDesc {
+            id: 0,
+            name: String::new(),
+            value: CValue::Integer(0),
+            minimum: 3,
+            maximum: 2047,
+            step: 8,
+            typ: crate::control::Type::Integer,
+            flags: crate::control::Flags::empty(),
+            items: vec![],
+            elems: 1,
+            elem_size: 8,
+            scale: 1.0,
+        };
+
+        let td = vec![
+            (i64::MIN, 3),
+            (0, 3),
+            (3, 3),
+            (6, 3),
+            (7, 11),
+            (11, 11),
+            (14, 11),
+            (15, 19),
+            (2043, 2043),
+            (2047, 2043),
+            (i64::MAX, 2043),
+        ];
+        for (value, expected) in td {
+            assert_eq!(Some(expected), desc.coerce(value).ok(), "coerce({})", value);
+        }
+
+        let desc = ControlDesc { step: 0, ..desc };
+        assert_eq!(Some(100), desc.coerce(100).ok());
+
+        // 範囲の端でも桁あふれしない
+        let wide = ControlDesc {
+            minimum: i64::MIN,
+            maximum: i64::MAX,
+            step: i64::MAX,
+            ..desc
+        };
+        assert_eq!(Some(i64::MIN), wide.coerce(i64::MIN).ok());
+        assert_eq!(Some(-1), wide.coerce(0).ok());
+        assert_eq!(Some(i64::MAX - 1), wide.coerce(i64::MAX).ok());
+
+        let invalid = ControlDesc {
+            minimum: 10,
+            maximum: 0,
+            ..wide
+        };
+        assert!(invalid.coerce(5).is_err());
+    }
+
+    #[test]
+    fn test_get_control_clamped() {
+        use crate::control::Type;
+
+        let mut exposure = desc(2, Type::Integer, 3, 2047);
+        exposure.step = 8;
+        let t = table(vec![
+            ("Gain", desc(1, Type::Integer, 0, 100)),
+            ("Exposure", exposure),
+            ("Enable", desc(3, Type::Boolean, 0, 1)),
+        ]);
+        let reqs = Requests::new(vec![
+            Request::new("gain", Value::Integer(105)),
+            Request::new("exposure", Value::Integer(12)),
+            Request::new("enable", Value::Boolean(true)),
+            Request::new("gain", Value::Integer(50)),
+        ]);
+
+        let (controls, clamped) = t.get_control_clamped(&reqs);
+        let values = controls
+            .into_iter()
+            .map(|c| (c.id, c.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (1, CValue::Integer(100)),
+                (2, CValue::Integer(11)),
+                (3, CValue::Boolean(true)),
+                (1, CValue::Integer(50)),
+            ],
+            values
+        );
+        assert_eq!(
+            vec![
+                ClampedControl {
+                    name: "gain".to_string(),
+                    requested: 105,
+                    applied: 100,
+                },
+                ClampedControl {
+                    name: "exposure".to_string(),
+                    requested: 12,
+                    applied: 11,
+                },
+            ],
+            clamped
+        );
+    }
+
+    #[test]
+    fn test_display_name() {
+        use crate::control::Type;
+
+        let t = table(vec![(
+            "Exposure Time, Absolute",
+            desc(1, Type::Integer, 1, 5000),
+        )]);
+        assert_eq!(
+            Some("Exposure Time, Absolute"),
+            t.display_name("exposure_time_absolute")
+        );
+        assert_eq!(None, t.display_name("Exposure Time, Absolute"));
+    }
+
+    #[test]
+    fn test_step() {
+        use crate::control::Type;
+
+        let mut exposure = desc(1, Type::Integer, 1, 5001);
+        exposure.step = 10;
+        let t = table(vec![("Exposure", exposure)]);
+        assert_eq!(10, t.get("exposure").unwrap().step());
+
+        let reqs = Requests::new(vec![
+            Request::new("exposure", Value::Integer(1001)),
+            Request::new("exposure", Value::Integer(1005)),
+            Request::new("exposure", Value::Integer(5001)),
+        ]);
+        let check = t.check(&reqs);
+        assert_eq!(1, check.len());
+        assert_eq!("exposure", check[0].name);
+        assert_eq!("Not aligned to step 10", check[0].detail);
+
+        // minimumからの差がi64に収まらなくても桁あふれしない
+        let mut wide = desc(2, Type::Integer64, i64::MIN, i64::MAX);
+        wide.step = 2;
+        let t = table(vec![("Wide", wide)]);
+        let reqs = Requests::new(vec![
+            Request::new("wide", Value::Integer(i64::MAX - 1)),
+            Request::new("wide", Value::Integer(i64::MAX)),
+        ]);
+        let check = t.check(&reqs);
+        assert_eq!(1, check.len());
+        assert_eq!("Not aligned to step 2", check[0].detail);
+    }
+
+    #[test]
+    fn test_validate() {
+        use crate::control::Type;
+
+        let t = table(vec![
+            ("Brightness", desc(1, Type::Integer, -64, 64)),
+            ("Mirror", desc(2, Type::Boolean, 0, 1)),
+        ]);
+
+        assert!(t
+            .validate(&Requests::try_from("brightness=10,mirror=true").unwrap())
+            .is_ok());
+        let err = t
+            .validate(&Requests::try_from("brightness=100,zoom=1").unwrap())
+            .unwrap_err();
+        assert_eq!(2, err.0.len());
+        assert_eq!(
+            "brightness: Out of range; zoom: Control not found",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        use crate::control::{Flags, Type};
+
+        let mut pan = desc(3, Type::Integer, -10, 10);
+        pan.flags = Flags::READ_ONLY;
+        let t = table(vec![
+            ("Gain", desc(1, Type::Integer, 0, 255)),
+            ("Brightness", desc(2, Type::Integer, -64, 64)),
+            ("Pan Speed", pan),
+        ]);
+
+        assert_eq!(2, t.len());
+        assert!(!t.is_empty());
+        assert_eq!(vec!["brightness", "gain"], t.names().collect::<Vec<_>>());
+        assert!(t.contains("gain"));
+        assert!(!t.contains("pan_speed"));
+    }
+
+    #[test]
+    fn test_read_only() {
+        use crate::control::{Flags, Type};
+
+        let mut pan = desc(2, Type::Integer, -10, 10);
+        pan.flags = Flags::READ_ONLY;
+        let t = table(vec![
+            ("Brightness", desc(1, Type::Integer, -64, 64)),
+            ("Pan Speed", pan),
+        ]);
+        assert!(t.get("pan_speed").is_none());
+
+        let reqs = Requests::new(vec![
+            Request::new("pan_speed", Value::Integer(0)),
+            Request::new("tilt_speed", Value::Integer(0)),
+        ]);
+        let check = t.check(&reqs);
+        assert_eq!(2, check.len());
+        assert_eq!("Control is read-only", check[0].detail);
+        assert_eq!("Control not found", check[1].detail);
+
+        let err = t.build("pan_speed", Value::Integer(0)).unwrap_err();
+        assert_eq!("Control is read-only", err.detail);
+
+        let err = t.build("Brightnes", Value::Integer(0)).unwrap_err();
+        assert_eq!("Control not found (did you mean 'brightness'?)", err.detail);
+    }
+
+    #[test]
+    fn test_array() {
+        use crate::control::Type;
+
+        let mut lut = desc(1, Type::U16, 0, 0xffff);
+        lut.elems = 4;
+        lut.elem_size = 2;
+        let t = table(vec![
+            ("Gamma LUT", lut),
+            ("Label", desc(2, Type::String, 0, 8)),
+        ]);
+
+        let bytes: Vec<u8> = [1u16, 2, 3, 4]
+            .iter()
+            .flat_map(|e| e.to_ne_bytes())
+            .collect();
+        let ctrl = t.build("gamma_lut", Value::Bytes(bytes)).unwrap();
+        assert_eq!(CValue::CompoundU16(vec![1, 2, 3, 4]), ctrl.value);
+
+        let err = t.build("gamma_lut", Value::Bytes(vec![0; 6])).unwrap_err();
+        assert_eq!("Invalid length 6 (expected 8)", err.detail);
+
+        let ctrl = t.build("label", Value::String("cam0".to_string())).unwrap();
+        assert_eq!(CValue::String("cam0".to_string()), ctrl.value);
+        let err = t
+            .build("label", Value::String("camera-front".to_string()))
+            .unwrap_err();
+        assert_eq!("Invalid length 12 (allowed 0..=8)", err.detail);
+
+        // 文字列と配列はデフォルト値が分からない
+        let reqs = Requests::new(vec![Request::new(
+            "label",
+            Value::String("cam0".to_string()),
+        )]);
+        assert!(t.get_default(&reqs).is_empty());
+    }
+
+    #[test]
+    fn test_describe() {
+        use crate::control::Type;
+
+        let t = table(vec![
+            ("Brightness", desc(1, Type::Integer, -64, 64)),
+            ("Mirror", desc(2, Type::Boolean, 0, 1)),
+        ]);
+
+        let info = t.describe("brightness").unwrap();
+        assert_eq!(1, info.id);
+        assert_eq!(Type::Integer, info.value_type);
+        assert_eq!((-64, 64, 1), (info.minimum, info.maximum, info.step));
+        assert!(info.menu.is_empty());
+        assert_eq!(Type::Boolean, t.describe("mirror").unwrap().value_type);
+        assert!(t.describe("gain").is_none());
+    }
+
+    #[test]
+    fn test_build() {
+        use crate::control::Type;
+
+        let mut exposure = desc(1, Type::Integer, 1, 5001);
+        exposure.step = 10;
+        let t = table(vec![
+            ("Exposure", exposure),
+            ("Mirror", desc(2, Type::Boolean, 0, 1)),
+        ]);
+
+        let ctrl = t.build("exposure", Value::Integer(1001)).unwrap();
+        assert_eq!(1, ctrl.id);
+        assert_eq!(CValue::Integer(1001), ctrl.value);
+
+        let err = t.build("exposure", Value::Integer(1005)).unwrap_err();
+        assert_eq!("Not aligned to step 10", err.detail);
+        let err = t.build("exposure", Value::Integer(6001)).unwrap_err();
+        assert_eq!("Out of range", err.detail);
+        let err = t.build("mirror", Value::Integer(1)).unwrap_err();
+        assert_eq!("mirror", err.name);
+        let err = t.build("gain", Value::Integer(1)).unwrap_err();
+        assert_eq!("Control not found", err.detail);
+    }
+
+    #[test]
+    fn test_percent() {
+        use crate::control::Type;
+
+        let mut t = table(vec![
+            ("Brightness", desc(1, Type::Integer, -64, 64)),
+            ("Exposure", desc(2, Type::Integer, 3, 2047)),
+            ("Mirror", desc(3, Type::Boolean, 0, 1)),
+            ("Mode", desc(4, Type::Menu, 0, 3)),
+            ("Wide", desc(5, Type::Integer64, i64::MIN, i64::MAX)),
+        ]);
+        t.map.get_mut("exposure").unwrap().step = 8;
+
+        let td = vec![
+            ("brightness", 0.0, -64),
+            ("brightness", 50.0, 0),
+            ("brightness", 100.0, 64),
+            ("exposure", 0.0, 3),
+            ("exposure", 50.0, 1027),
+            ("exposure", 100.0, 2043),
+        ];
+        for (name, percent, expected) in td {
+            let reqs = Requests::new(vec![Request::new(name, Value::Percent(percent))]);
+            assert!(t.check(&reqs).is_empty());
+            let ctrls = t.get_control(&reqs);
+            assert!(matches!(ctrls[0].value, CValue::Integer(i) if i == expected));
+        }
+
+        let reqs = Requests::new(vec![Request::new("mirror", Value::Percent(50.0))]);
+        assert_eq!(1, t.check(&reqs).len());
+        // 範囲が分からないと変換できないので、丸めた整数として設定しない
+        assert!(t.get_control(&reqs).is_empty());
+        assert!(t.build("mirror", Value::Percent(50.0)).is_err());
+        assert_eq!(
+            Err(Value::Percent(50.0)),
+            CValue::try_from(Value::Percent(50.0))
+        );
+        let reqs = Requests::new(vec![Request::new("mode", Value::Percent(50.0))]);
+        assert_eq!(1, t.check(&reqs).len());
+
+        // 範囲の幅がi64に収まらなくても桁あふれしない
+        let reqs = Requests::new(vec![Request::new("wide", Value::Percent(100.0))]);
+        assert!(t.check(&reqs).is_empty());
+        let ctrls = t.get_control(&reqs);
+        assert!(matches!(ctrls[0].value, CValue::Integer(i64::MAX)));
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        use crate::v4l_sys::V4L2_CID_GAIN;
+
+        let before = ControlSnapshot {
+            values: vec![
+                ("gain".to_string(), CValue::Integer(10)),
+                ("exposure".to_string(), CValue::Integer(100)),
+                ("mirror".to_string(), CValue::Boolean(false)),
+            ]
+            .into_iter()
+            .collect(),
+            ids: vec![("gain".to_string(), V4L2_CID_GAIN)]
+                .into_iter()
+                .collect(),
+        };
+        let mut after = before.clone();
+        after.values.insert("gain".to_string(), CValue::Integer(12));
+        after.values.remove("mirror");
+        after
+            .values
+            .insert("sharpness".to_string(), CValue::Integer(1));
+
+        assert_eq!(
+            vec![ControlChange {
+                id: V4L2_CID_GAIN,
+                name: Some("gain".to_string()),
+                before: CValue::Integer(10),
+                after: CValue::Integer(12),
+            }],
+            before.diff(&after)
+        );
+        assert!(before.diff(&before).is_empty());
+    }
+
     #[test]
     fn test_request_from_str() {
         let td = vec![
@@ -274,6 +2599,8 @@ mod tests {
             ("gain=1", "gain", Value::Integer(1)),
             ("gain=true", "gain", Value::Boolean(true)),
             ("gain=false", "gain", Value::Boolean(false)),
+            ("gain=50%", "gain", Value::Percent(50.0)),
+            ("gain=12.5%", "gain", Value::Percent(12.5)),
             (
                 "white_balance=auto",
                 "white_balance",
@@ -290,6 +2617,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_request_from_str_error() {
+        let td = vec![
+            ("gain", ControlParseError::MissingValue("gain".to_string())),
+            ("=1", ControlParseError::MissingField("=1".to_string())),
+            ("", ControlParseError::Empty),
+        ];
+
+        for (input, err) in td {
+            assert_eq!(Err(err), Request::try_from(input).map(|_| ()));
+        }
+        assert_eq!(
+            "value not found [gain]",
+            Request::try_from("gain").err().unwrap().to_string()
+        );
+    }
+
     #[test]
     fn test_requests_from_str() {
         let td = vec![
@@ -303,5 +2647,178 @@ mod tests {
             let reqs = reqs.unwrap();
             assert_eq!(len, reqs.requests.len());
         }
+        assert_eq!(
+            Err(ControlParseError::Empty),
+            Requests::try_from("").map(|_| ())
+        );
+        assert_eq!(
+            Err(ControlParseError::Empty),
+            Requests::try_from("gain=0,").map(|_| ())
+        );
+        assert_eq!(
+            Err(ControlParseError::MissingValue("exposure".to_string())),
+            Requests::try_from("gain=0,exposure").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_requests_from_slice() {
+        let args = vec![
+            "gain=10".to_string(),
+            String::new(),
+            "title=a,b".to_string(),
+        ];
+        let reqs = Requests::try_from(args.as_slice()).unwrap();
+        assert_eq!(
+            vec![
+                ("gain", &Value::Integer(10)),
+                ("title", &Value::String("a,b".to_string())),
+            ],
+            reqs.iter()
+                .map(|r| (r.name(), r.value()))
+                .collect::<Vec<_>>()
+        );
+
+        let empty: &[&str] = &[];
+        assert!(Requests::try_from(empty).unwrap().is_empty());
+        assert_eq!(
+            Err(ControlParseError::MissingValue("exposure".to_string())),
+            Requests::try_from(&["gain=0", "exposure"][..]).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_requests_merge() {
+        let mut base = Requests::try_from("gain=0,white_balance=auto,gain=5").unwrap();
+        base.merge(Requests::try_from("exposure=100,gain=10").unwrap());
+
+        assert_eq!(3, base.len());
+        assert_eq!(
+            vec![
+                ("gain", &Value::Integer(10)),
+                ("white_balance", &Value::String("auto".to_string())),
+                ("exposure", &Value::Integer(100)),
+            ],
+            base.iter()
+                .map(|r| (r.name(), r.value()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_requests_json() {
+        let json = r#"{"gain": 10, "white_balance": "auto", "enable": true}"#;
+        let reqs = Requests::from_json(json).unwrap();
+        let td = [
+            ("gain", Value::Integer(10)),
+            ("white_balance", Value::String("auto".to_string())),
+            ("enable", Value::Boolean(true)),
+        ];
+        assert_eq!(td.len(), reqs.requests.len());
+        for ((name, value), r) in td.iter().zip(reqs.requests.iter()) {
+            assert_eq!(*name, r.name);
+            assert_eq!(*value, r.value);
+        }
+
+        let again = Requests::from_json(&reqs.to_json().unwrap()).unwrap();
+        assert_eq!(reqs.requests.len(), again.requests.len());
+        for (a, b) in reqs.requests.iter().zip(again.requests.iter()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.value, b.value);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_value_json() {
+        let td = vec![
+            (Value::Integer(-10), "-10"),
+            (Value::Integer(i64::MAX), "9223372036854775807"),
+            (Value::Boolean(true), "true"),
+            (Value::String("auto".to_string()), r#""auto""#),
+            (Value::Raw(vec![1, 2, 255]), "[1,2,255]"),
+            (Value::Percent(12.5), r#""12.5%""#),
+            (Value::Percent(50.0), r#""50%""#),
+            (Value::Bytes(vec![0, 128]), r#"{"bytes":[0,128]}"#),
+            (Value::Float(1.5), "1.5"),
+            (Value::Float(2.0), "2.0"),
+        ];
+        for (value, json) in td {
+            assert_eq!(json, serde_json::to_string(&value).unwrap());
+            assert_eq!(
+                value,
+                serde_json::from_str::<Value>(json).unwrap(),
+                "{}",
+                json
+            );
+        }
+
+        for json in [
+            r#"{"raw":[1]}"#,
+            r#"{"bytes":[1],"raw":[2]}"#,
+            "[256]",
+            "18446744073709551615",
+        ] {
+            assert!(serde_json::from_str::<Value>(json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_requests_quoted() {
+        let td = vec![
+            (
+                r#"preset="a,b",gain=10"#,
+                vec![
+                    ("preset", Value::String("a,b".to_string())),
+                    ("gain", Value::Integer(10)),
+                ],
+            ),
+            (
+                r#"preset="say \"hi\", ok""#,
+                vec![("preset", Value::String(r#"say "hi", ok"#.to_string()))],
+            ),
+            (
+                r#"preset="a\\",gain=1"#,
+                vec![
+                    ("preset", Value::String(r"a\".to_string())),
+                    ("gain", Value::Integer(1)),
+                ],
+            ),
+            // 囲まれた値は数値として扱わない
+            (
+                r#"preset="10""#,
+                vec![("preset", Value::String("10".to_string()))],
+            ),
+            // 値の末尾の,
+            (
+                r#"preset="a,",gain=1"#,
+                vec![
+                    ("preset", Value::String("a,".to_string())),
+                    ("gain", Value::Integer(1)),
+                ],
+            ),
+        ];
+
+        for (input, expected) in td {
+            let reqs = Requests::try_from(input).unwrap();
+            assert_eq!(expected.len(), reqs.requests.len(), "{}", input);
+            for (req, (name, value)) in reqs.requests.iter().zip(expected) {
+                assert_eq!(name, req.name);
+                assert_eq!(value, req.value);
+            }
+        }
+
+        // リストの末尾の,
+        assert_eq!(
+            Err(ControlParseError::Empty),
+            Requests::try_from(r#"preset="a,b","#).map(|_| ())
+        );
+        assert_eq!(
+            Err(ControlParseError::UnterminatedQuote(
+                r#"preset="a,b,gain=1"#.to_string()
+            )),
+            Requests::try_from(r#"preset="a,b,gain=1"#).map(|_| ())
+        );
     }
 }
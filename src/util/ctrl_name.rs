@@ -13,8 +13,55 @@ where
     fn to_ctrl_name(&self) -> String {
         let text = self.as_ref();
 
-        text.replace(" ", "_").replace(",", "").to_lowercase()
+        // v4l2-ctlのname2varと同じく、英数字以外の連続は1つの`_`にまとめ、先頭と末尾では取り除く
+        let mut name = String::with_capacity(text.len());
+        let mut separator = false;
+        for c in text.chars() {
+            if c.is_ascii_alphanumeric() {
+                if separator && !name.is_empty() {
+                    name.push('_');
+                }
+                separator = false;
+                name.push(c.to_ascii_lowercase());
+            } else {
+                separator = true;
+            }
+        }
+        name
+    }
+}
+
+/// 候補の中から名前に最も近いControlの名前を返す
+///
+/// 名前を`to_ctrl_name`で変換してから編集距離 (Levenshtein距離) で比較する。
+/// 距離が名前の長さの1/3 (最低1) を超える候補は似ていないものとして扱う
+pub fn nearest_ctrl_name<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let name = name.to_ctrl_name();
+    let max = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (levenshtein(&name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// 2つの文字列の編集距離を返す
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
     }
+    prev[b.len()]
 }
 
 #[cfg(test)]
@@ -28,10 +75,50 @@ mod tests {
             ("Sensor Mode", "sensor_mode"),
             ("White Balance Temperature", "white_balance_temperature"),
             ("Height Align", "height_align"),
+            ("Gain (Analogue)", "gain_analogue"),
+            ("Focus, Auto (Continuous)", "focus_auto_continuous"),
+            ("Zoom, Absolute / Relative", "zoom_absolute_relative"),
+            ("Red/Blue Balance", "red_blue_balance"),
+            ("exposure_time_absolute", "exposure_time_absolute"),
+            // v4l2-ctlの出力
+            ("MPEG-4 Profile", "mpeg_4_profile"),
+            ("H264 8x8 Transform Enable", "h264_8x8_transform_enable"),
+            ("Pan, Reset", "pan_reset"),
+            ("Chroma AGC", "chroma_agc"),
+            // 英数字以外はすべて区切り文字
+            ("a,b", "a_b"),
+            ("a.b:c&d", "a_b_c_d"),
+            (" -Leading and trailing- ", "leading_and_trailing"),
         ];
 
         for (input, expected) in td {
             assert_eq!(expected, input.to_ctrl_name().as_str());
         }
     }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(0, levenshtein("gain", "gain"));
+        assert_eq!(1, levenshtein("gain", "gan"));
+        assert_eq!(3, levenshtein("kitten", "sitting"));
+        assert_eq!(4, levenshtein("", "gain"));
+    }
+
+    #[test]
+    fn test_nearest_ctrl_name() {
+        let names = ["brightness", "frame_rate", "gain", "exposure_time_absolute"];
+
+        let td = vec![
+            ("frame rate", Some("frame_rate")),
+            ("Frame-Rate", Some("frame_rate")),
+            ("brightnes", Some("brightness")),
+            ("exposure_time_absolut", Some("exposure_time_absolute")),
+            ("gian", None),
+            ("zoom", None),
+        ];
+
+        for (input, expected) in td {
+            assert_eq!(expected, nearest_ctrl_name(input, names.iter().copied()));
+        }
+    }
 }
@@ -0,0 +1,281 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io;
+
+use super::control::ControlDevice;
+use crate::control::{Description, Flags, Type, Value as CValue};
+use crate::Control;
+
+/// テスト用にControlをメモリ上に保持するデバイス
+///
+/// `ControlDevice`を実装し、ハードウェアがなくても`ControlTable`のデバイスを扱う関数を試せる。
+/// 値は範囲をチェックしてから保存し、書き込みの履歴を残す。
+/// エラーはドライバと同じく`EINVAL`(存在しない)、`EACCES`(読み取り専用/書き込み専用)、`ERANGE`(範囲外)で返す
+///
+/// # Example
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use v4l::control::{Description, Flags, Type, Value};
+/// use v4l::util::control::{ControlTable, Requests};
+/// use v4l::util::mock::MockDevice;
+///
+/// let gain = Description {
+///     id: 0x00980913,
+///     typ: Type::Integer,
+///     name: "Gain".to_string(),
+///     minimum: 0,
+///     maximum: 255,
+///     step: 1,
+///     default: 32,
+///     flags: Flags::empty(),
+///     elem_size: 4,
+///     elems: 1,
+///     items: None,
+/// };
+/// let dev = MockDevice::new(vec![gain]);
+/// let table = ControlTable::from(dev.descriptions());
+///
+/// table.apply_atomic(&dev, &Requests::try_from("gain=64").unwrap()).unwrap();
+/// assert_eq!(Some(Value::Integer(64)), dev.value(0x00980913));
+/// ```
+#[derive(Debug)]
+pub struct MockDevice {
+    controls: Vec<Description>,
+    values: RefCell<BTreeMap<u32, CValue>>,
+    writes: RefCell<Vec<(u32, CValue)>>,
+    /// `(id, 切り替えるControlのid, 有効になる値)`
+    dependencies: Vec<(u32, u32, i64)>,
+}
+
+impl MockDevice {
+    /// Controlの一覧からデバイスを作成する
+    ///
+    /// 値はデフォルト値で初期化する
+    pub fn new(controls: Vec<Description>) -> Self {
+        let values = controls
+            .iter()
+            .filter_map(|desc| {
+                let value = match desc.typ {
+                    Type::Integer
+                    | Type::Integer64
+                    | Type::Menu
+                    | Type::IntegerMenu
+                    | Type::Bitmask => CValue::Integer(desc.default),
+                    Type::Boolean => CValue::Boolean(desc.default != 0),
+                    Type::String => CValue::String(String::new()),
+                    _ => return None,
+                };
+                Some((desc.id, value))
+            })
+            .collect();
+        MockDevice {
+            controls,
+            values: RefCell::new(values),
+            writes: RefCell::new(vec![]),
+            dependencies: vec![],
+        }
+    }
+
+    /// Controlの一覧を返す
+    pub fn descriptions(&self) -> &[Description] {
+        &self.controls
+    }
+
+    /// Controlが他のControlの値によって無効 (INACTIVE) になるようにする
+    ///
+    /// オートモードのときの手動露出のように、`master`が`value`でないときは`id`が無効になる
+    pub fn inactive_unless(&mut self, id: u32, master: u32, value: i64) {
+        self.dependencies.push((id, master, value));
+    }
+
+    /// Controlの現在の値を返す
+    pub fn value(&self, id: u32) -> Option<CValue> {
+        self.values.borrow().get(&id).cloned()
+    }
+
+    /// ドライバが値を変更したように、履歴に残さずに値を書き換える
+    pub fn set_value(&self, id: u32, value: CValue) {
+        self.values.borrow_mut().insert(id, value);
+    }
+
+    /// 書き込まれた`(id, 値)`を順に返す
+    pub fn writes(&self) -> Vec<(u32, CValue)> {
+        self.writes.borrow().clone()
+    }
+
+    /// 現在のフラグを反映したControlの情報を返す
+    fn describe(&self, desc: &Description) -> Description {
+        let mut desc = desc.clone();
+        let values = self.values.borrow();
+        let inactive = self
+            .dependencies
+            .iter()
+            .filter(|(id, _, _)| *id == desc.id)
+            .any(|(_, master, value)| values.get(master) != Some(&CValue::Integer(*value)));
+        desc.flags.set(Flags::INACTIVE, inactive);
+        desc
+    }
+
+    fn find(&self, id: u32) -> io::Result<&Description> {
+        self.controls
+            .iter()
+            .find(|desc| desc.id == id)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))
+    }
+}
+
+impl ControlDevice for MockDevice {
+    fn query_controls(&self) -> io::Result<Vec<Description>> {
+        Ok(self
+            .controls
+            .iter()
+            .map(|desc| self.describe(desc))
+            .collect())
+    }
+
+    fn query_control(&self, id: u32) -> io::Result<Description> {
+        self.find(id).map(|desc| self.describe(desc))
+    }
+
+    fn control(&self, desc: &Description) -> io::Result<Control> {
+        let desc = self.find(desc.id)?;
+        if desc.flags.contains(Flags::WRITE_ONLY) {
+            return Err(io::Error::from_raw_os_error(libc::EACCES));
+        }
+        let value = self
+            .value(desc.id)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL))?;
+        Ok(Control { id: desc.id, value })
+    }
+
+    fn set_control(&self, ctrl: Control) -> io::Result<()> {
+        let desc = self.find(ctrl.id)?;
+        if desc.flags.contains(Flags::READ_ONLY) {
+            return Err(io::Error::from_raw_os_error(libc::EACCES));
+        }
+        if let CValue::Integer(i) = ctrl.value {
+            if desc.typ != Type::Bitmask && (i < desc.minimum || i > desc.maximum) {
+                return Err(io::Error::from_raw_os_error(libc::ERANGE));
+            }
+        }
+
+        self.writes.borrow_mut().push((ctrl.id, ctrl.value.clone()));
+        if desc.typ != Type::Button {
+            self.values.borrow_mut().insert(ctrl.id, ctrl.value);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::control::{ControlChange, ControlSnapshot, ControlTable, Requests};
+    use crate::v4l_sys::{V4L2_CID_EXPOSURE_ABSOLUTE, V4L2_CID_EXPOSURE_AUTO, V4L2_CID_GAIN};
+    use std::convert::TryFrom;
+
+    fn desc(id: u32, name: &str, typ: Type, maximum: i64, default: i64) -> Description {
+        Description {
+            id,
+            typ,
+            name: name.to_string(),
+            minimum: 0,
+            maximum,
+            step: 1,
+            default,
+            flags: Flags::empty(),
+            elem_size: 4,
+            elems: 1,
+            items: None,
+        }
+    }
+
+    /// オートモード (3) のときは手動露出が無効になるデバイス
+    fn device() -> MockDevice {
+        let mut dev = MockDevice::new(vec![
+            desc(V4L2_CID_EXPOSURE_AUTO, "Auto Exposure", Type::Integer, 3, 3),
+            desc(
+                V4L2_CID_EXPOSURE_ABSOLUTE,
+                "Exposure Time, Absolute",
+                Type::Integer,
+                5000,
+                100,
+            ),
+            desc(V4L2_CID_GAIN, "Gain", Type::Integer, 255, 0),
+        ]);
+        dev.inactive_unless(V4L2_CID_EXPOSURE_ABSOLUTE, V4L2_CID_EXPOSURE_AUTO, 1);
+        dev
+    }
+
+    #[test]
+    fn test_apply_ordered() {
+        let dev = device();
+        let table = ControlTable::from(dev.query_controls().unwrap().as_slice());
+        let reqs = Requests::try_from("exposure_time_absolute=200,auto_exposure=1").unwrap();
+
+        assert!(table.apply_ordered(&dev, &reqs).unwrap().is_empty());
+        assert_eq!(
+            vec![
+                (V4L2_CID_EXPOSURE_AUTO, CValue::Integer(1)),
+                (V4L2_CID_EXPOSURE_ABSOLUTE, CValue::Integer(200)),
+            ],
+            dev.writes()
+        );
+    }
+
+    #[test]
+    fn test_apply_ordered_check() {
+        let dev = device();
+        let table = ControlTable::from(dev.descriptions());
+
+        // 名前の誤りは何も設定せずにエラーにする
+        let reqs = Requests::try_from("gain=10,exposure=200").unwrap();
+        let error = table.apply_ordered(&dev, &reqs).unwrap_err();
+        assert_eq!("exposure", error.name);
+        assert_eq!(io::ErrorKind::InvalidInput, error.error.kind());
+        assert!(dev.writes().is_empty());
+
+        let reqs = Requests::try_from("gain=256").unwrap();
+        let error = table.apply_ordered(&dev, &reqs).unwrap_err();
+        assert_eq!("gain", error.name);
+        assert!(dev.writes().is_empty());
+    }
+
+    #[test]
+    fn test_apply_atomic_rollback() {
+        let dev = device();
+        let table = ControlTable::from(dev.descriptions());
+        // 範囲はテーブルのチェックを通さずに、ドライバのエラーとして返す
+        let reqs = Requests::try_from("gain=10,auto_exposure=7").unwrap();
+
+        let error = table.apply_atomic(&dev, &reqs).unwrap_err();
+        assert_eq!("auto_exposure", error.name);
+        assert_eq!(Some(libc::ERANGE), error.error.raw_os_error());
+        assert!(error.rollback_failed.is_empty());
+        assert_eq!(Some(CValue::Integer(0)), dev.value(V4L2_CID_GAIN));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let dev = device();
+        let table = ControlTable::from(dev.descriptions());
+
+        let (reqs, inactive) = table.snapshot(&dev).unwrap();
+        assert_eq!(vec!["exposure_time_absolute".to_string()], inactive);
+        assert_eq!(2, reqs.len());
+
+        let before = ControlSnapshot::capture(&dev).unwrap();
+        dev.set_value(V4L2_CID_GAIN, CValue::Integer(5));
+        let after = ControlSnapshot::capture(&dev).unwrap();
+        assert_eq!(
+            vec![ControlChange {
+                id: V4L2_CID_GAIN,
+                name: Some("gain".to_string()),
+                before: CValue::Integer(0),
+                after: CValue::Integer(5),
+            }],
+            before.diff(&after)
+        );
+    }
+}
@@ -1,2 +1,4 @@
+pub mod config;
 pub mod control;
 pub mod ctrl_name;
+pub mod mock;
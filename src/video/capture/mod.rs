@@ -8,8 +8,11 @@ use crate::buffer::Type;
 use crate::device::Device;
 use crate::format::FourCC;
 use crate::format::{Description as FormatDescription, Format};
+use crate::fraction::Fraction;
 use crate::frameinterval::FrameInterval;
 use crate::framesize::FrameSize;
+use crate::parameters::Capabilities;
+use crate::selection::{Flags as SelectionFlags, Rect, Target as SelectionTarget};
 use crate::v4l2;
 use crate::v4l_sys::*;
 use crate::video::traits::Capture;
@@ -17,9 +20,13 @@ use crate::video::traits::Capture;
 impl Capture for Device {
     impl_enum_frameintervals!();
     impl_enum_framesizes!();
-    impl_enum_formats!(Type::VideoCapture);
+    impl_enum_formats!(Type::VideoCapture, Type::VideoCaptureMplane);
     impl_format!(Type::VideoCapture);
     impl_set_format!(Type::VideoCapture);
+    impl_selection!(Type::VideoCapture);
+    impl_set_selection!(Type::VideoCapture);
+    impl_crop!(Type::VideoCapture);
+    impl_set_crop!(Type::VideoCapture);
 
     fn params(&self) -> io::Result<Parameters> {
         unsafe {
@@ -54,4 +61,45 @@ impl Capture for Device {
 
         self.params()
     }
+
+    fn set_fps(&self, num: u32, den: u32) -> io::Result<Parameters> {
+        if num == 0 || den == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid frame rate {}/{}", num, den),
+            ));
+        }
+
+        let mut params = self.params()?;
+        if !params.capabilities.contains(Capabilities::TIME_PER_FRAME) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "VIDIOC_S_PARM: frame rate cannot be set",
+            ));
+        }
+
+        // the frame interval is the inverse of the frame rate
+        params.interval = Fraction::new(den, num);
+        self.set_params(&params)
+    }
+
+    fn set_output_size(&self, width: u32, height: u32) -> io::Result<Rect> {
+        let mut fmt = self.format()?;
+        fmt.width = width;
+        fmt.height = height;
+        let fmt = self.set_format(&fmt)?;
+
+        let rect = Rect::new(0, 0, fmt.width, fmt.height);
+        match self.set_selection(SelectionTarget::Compose, rect, SelectionFlags::empty()) {
+            Ok(rect) => Ok(rect),
+            // No selection API or no compose support: the driver scales to the format size.
+            Err(e)
+                if e.kind() == io::ErrorKind::InvalidInput
+                    || e.raw_os_error() == Some(libc::ENOTTY) =>
+            {
+                Ok(rect)
+            }
+            Err(e) => Err(e),
+        }
+    }
 }
@@ -80,49 +80,16 @@ macro_rules! impl_enum_framesizes {
 }
 
 macro_rules! impl_enum_formats {
-    ($typ:expr) => {
+    ($typ:expr, $mplane:expr) => {
         fn enum_formats(&self) -> io::Result<Vec<FormatDescription>> {
-            let mut formats: Vec<FormatDescription> = Vec::new();
-            let mut v4l2_fmt = v4l2_fmtdesc {
-                index: 0,
-                type_: $typ as u32,
-                ..unsafe { mem::zeroed() }
-            };
-
-            let mut ret: io::Result<()>;
-
-            unsafe {
-                ret = v4l2::ioctl(
-                    self.handle().fd(),
-                    v4l2::vidioc::VIDIOC_ENUM_FMT,
-                    &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
-                );
+            let formats = self.enum_formats_for($typ)?;
+            if !formats.is_empty() {
+                return Ok(formats);
             }
 
-            if ret.is_err() {
-                // Enumerating the first format (at index 0) failed, so there are no formats available
-                // for this device. Just return an empty vec in this case.
-                return Ok(Vec::new());
-            }
-
-            while ret.is_ok() {
-                formats.push(FormatDescription::from(v4l2_fmt));
-                v4l2_fmt.index += 1;
-
-                unsafe {
-                    v4l2_fmt.description = mem::zeroed();
-                }
-
-                unsafe {
-                    ret = v4l2::ioctl(
-                        self.handle().fd(),
-                        v4l2::vidioc::VIDIOC_ENUM_FMT,
-                        &mut v4l2_fmt as *mut _ as *mut std::os::raw::c_void,
-                    );
-                }
-            }
-
-            Ok(formats)
+            // devices which only implement the multi-planar API enumerate nothing for
+            // single-planar buffers
+            self.enum_formats_for($mplane)
         }
     };
 }
@@ -166,3 +133,94 @@ macro_rules! impl_set_format {
         }
     };
 }
+
+macro_rules! impl_selection {
+    ($typ:expr) => {
+        fn selection(&self, target: SelectionTarget) -> io::Result<Rect> {
+            unsafe {
+                let mut v4l2_sel = v4l2_selection {
+                    type_: $typ as u32,
+                    target: target as u32,
+                    ..mem::zeroed()
+                };
+                v4l2::ioctl(
+                    self.handle().fd(),
+                    v4l2::vidioc::VIDIOC_G_SELECTION,
+                    &mut v4l2_sel as *mut _ as *mut std::os::raw::c_void,
+                )?;
+
+                Ok(Rect::from(v4l2_sel.r))
+            }
+        }
+    };
+}
+
+macro_rules! impl_set_selection {
+    ($typ:expr) => {
+        fn set_selection(
+            &self,
+            target: SelectionTarget,
+            rect: Rect,
+            flags: SelectionFlags,
+        ) -> io::Result<Rect> {
+            unsafe {
+                let mut v4l2_sel = v4l2_selection {
+                    type_: $typ as u32,
+                    target: target as u32,
+                    flags: flags.into(),
+                    r: rect.into(),
+                    ..mem::zeroed()
+                };
+                v4l2::ioctl(
+                    self.handle().fd(),
+                    v4l2::vidioc::VIDIOC_S_SELECTION,
+                    &mut v4l2_sel as *mut _ as *mut std::os::raw::c_void,
+                )?;
+
+                // the driver writes back the rectangle it actually applied
+                Ok(Rect::from(v4l2_sel.r))
+            }
+        }
+    };
+}
+
+macro_rules! impl_crop {
+    ($typ:expr) => {
+        fn crop(&self) -> io::Result<Rect> {
+            unsafe {
+                let mut v4l2_crop = v4l2_crop {
+                    type_: $typ as u32,
+                    ..mem::zeroed()
+                };
+                v4l2::ioctl(
+                    self.handle().fd(),
+                    v4l2::vidioc::VIDIOC_G_CROP,
+                    &mut v4l2_crop as *mut _ as *mut std::os::raw::c_void,
+                )?;
+
+                Ok(Rect::from(v4l2_crop.c))
+            }
+        }
+    };
+}
+
+macro_rules! impl_set_crop {
+    ($typ:expr) => {
+        fn set_crop(&self, rect: Rect) -> io::Result<Rect> {
+            unsafe {
+                let mut v4l2_crop = v4l2_crop {
+                    type_: $typ as u32,
+                    c: rect.into(),
+                };
+                v4l2::ioctl(
+                    self.handle().fd(),
+                    v4l2::vidioc::VIDIOC_S_CROP,
+                    &mut v4l2_crop as *mut _ as *mut std::os::raw::c_void,
+                )?;
+            }
+
+            // VIDIOC_S_CROP is write-only, so query the rectangle actually applied
+            self.crop()
+        }
+    };
+}
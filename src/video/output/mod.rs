@@ -10,6 +10,7 @@ use crate::format::FourCC;
 use crate::format::{Description as FormatDescription, Format};
 use crate::frameinterval::FrameInterval;
 use crate::framesize::FrameSize;
+use crate::selection::{Flags as SelectionFlags, Rect, Target as SelectionTarget};
 use crate::v4l2;
 use crate::v4l_sys::*;
 use crate::video::traits::Output;
@@ -17,9 +18,13 @@ use crate::video::traits::Output;
 impl Output for Device {
     impl_enum_frameintervals!();
     impl_enum_framesizes!();
-    impl_enum_formats!(Type::VideoOutput);
+    impl_enum_formats!(Type::VideoOutput, Type::VideoOutputMplane);
     impl_format!(Type::VideoOutput);
     impl_set_format!(Type::VideoOutput);
+    impl_selection!(Type::VideoOutput);
+    impl_set_selection!(Type::VideoOutput);
+    impl_crop!(Type::VideoOutput);
+    impl_set_crop!(Type::VideoOutput);
 
     fn params(&self) -> io::Result<Parameters> {
         unsafe {
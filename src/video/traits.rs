@@ -1,5 +1,6 @@
 use std::io;
 
+use crate::selection::{Flags as SelectionFlags, Rect, Target as SelectionTarget};
 use crate::video::capture::Parameters as CaptureParameters;
 use crate::video::output::Parameters as OutputParameters;
 use crate::{
@@ -51,6 +52,79 @@ pub trait Capture {
     ///
     /// * `params` - Desired parameters
     fn set_params(&self, params: &CaptureParameters) -> io::Result<CaptureParameters>;
+
+    /// Requests a frame rate and returns the actual parameters
+    ///
+    /// Many drivers (e.g. UVC) only allow to change the frame rate this way, not through
+    /// controls. The driver picks the closest interval it supports, check the `interval` of the
+    /// returned parameters for the one it granted. Fails with [`io::ErrorKind::Unsupported`] if
+    /// the device does not support frame rate selection.
+    ///
+    /// # Arguments
+    ///
+    /// * `num` - Frame rate numerator, e.g. 30 for 30 fps
+    /// * `den` - Frame rate denominator, e.g. 1 for 30 fps or 1001 for 29.97 fps with 30000
+    fn set_fps(&self, num: u32, den: u32) -> io::Result<CaptureParameters>;
+
+    /// Returns the selection rectangle for the given target
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Selection target, e.g. the crop or compose rectangle
+    fn selection(&self, target: SelectionTarget) -> io::Result<Rect>;
+
+    /// Modifies the selection rectangle for the given target and returns the actual rectangle
+    ///
+    /// The driver adjusts the rectangle to match the hardware constraints, the flags control in
+    /// which direction it may do so.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Selection target, e.g. the crop or compose rectangle
+    /// * `rect` - Desired rectangle
+    /// * `flags` - Constraints for adjusting the rectangle
+    fn set_selection(
+        &self,
+        target: SelectionTarget,
+        rect: Rect,
+        flags: SelectionFlags,
+    ) -> io::Result<Rect>;
+
+    /// Returns the crop rectangle
+    ///
+    /// Uses the legacy crop API, which is still the only one implemented by some older drivers.
+    /// Prefer [`Capture::selection`] with [`SelectionTarget::Crop`] otherwise.
+    fn crop(&self) -> io::Result<Rect>;
+
+    /// Modifies the crop rectangle and returns the actual rectangle
+    ///
+    /// The driver rounds the rectangle to match the hardware constraints.
+    ///
+    /// # Arguments
+    ///
+    /// * `rect` - Desired rectangle
+    fn set_crop(&self, rect: Rect) -> io::Result<Rect>;
+
+    /// Modifies the size of the (scaled) output image and returns the actual composing area
+    ///
+    /// On devices with a scaler (e.g. the ISP of many SoCs), the image data is first cropped from
+    /// the sensor according to the crop rectangle, then scaled to the compose rectangle, which
+    /// is placed inside a buffer of the size described by the format. This sets the format to
+    /// the requested size first and then makes the compose rectangle cover the entire buffer,
+    /// so the cropped image is scaled to the new size. The crop rectangle is left untouched,
+    /// so cropping via [`Capture::set_selection`] and scaling via this method can be combined
+    /// for digital zoom.
+    ///
+    /// Devices without compose support scale the cropped image to the format size directly,
+    /// in that case only the format is modified.
+    ///
+    /// Like [`Capture::set_format`], this must be done before buffers are allocated.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Desired width of the output image
+    /// * `height` - Desired height of the output image
+    fn set_output_size(&self, width: u32, height: u32) -> io::Result<Rect>;
 }
 
 /// Output device protocol
@@ -96,4 +170,43 @@ pub trait Output {
     ///
     /// * `params` - Desired parameters
     fn set_params(&self, params: &OutputParameters) -> io::Result<OutputParameters>;
+
+    /// Returns the selection rectangle for the given target
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Selection target, e.g. the crop or compose rectangle
+    fn selection(&self, target: SelectionTarget) -> io::Result<Rect>;
+
+    /// Modifies the selection rectangle for the given target and returns the actual rectangle
+    ///
+    /// The driver adjusts the rectangle to match the hardware constraints, the flags control in
+    /// which direction it may do so.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Selection target, e.g. the crop or compose rectangle
+    /// * `rect` - Desired rectangle
+    /// * `flags` - Constraints for adjusting the rectangle
+    fn set_selection(
+        &self,
+        target: SelectionTarget,
+        rect: Rect,
+        flags: SelectionFlags,
+    ) -> io::Result<Rect>;
+
+    /// Returns the crop rectangle
+    ///
+    /// Uses the legacy crop API, which is still the only one implemented by some older drivers.
+    /// Prefer [`Output::selection`] with [`SelectionTarget::Crop`] otherwise.
+    fn crop(&self) -> io::Result<Rect>;
+
+    /// Modifies the crop rectangle and returns the actual rectangle
+    ///
+    /// The driver rounds the rectangle to match the hardware constraints.
+    ///
+    /// # Arguments
+    ///
+    /// * `rect` - Desired rectangle
+    fn set_crop(&self, rect: Rect) -> io::Result<Rect>;
 }